@@ -0,0 +1,108 @@
+//! Frame encoding and decoding, measured through `send_command` and
+//! `read_frame` against a loopback transport that answers instantly.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pn532_nfc::pn532::frame;
+use pn532_nfc::{AuthCache, GpioCache, Hooks, Options, Result, Stats, Transport, PN532};
+
+const ACK: [u8; 6] = [0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00];
+
+/// A transport that replays canned PN532 output.
+#[derive(Default)]
+struct Loopback {
+    options: Options,
+    auth_cache: AuthCache,
+    gpio_cache: GpioCache,
+    hooks: Hooks,
+    stats: Stats,
+    response: Vec<u8>,
+    output: VecDeque<Vec<u8>>,
+}
+
+impl Loopback {
+    /// Answer every command with an ACK followed by `response`.
+    fn answering(response: Vec<u8>) -> Self {
+        Self { response, ..Self::default() }
+    }
+}
+
+impl Transport for Loopback {
+    fn gpio_init(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn reset(&mut self, _pin: u8) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let output = self.output.pop_front().unwrap_or_else(|| self.response.clone());
+        let len = output.len().min(buf.len());
+        buf[..len].copy_from_slice(&output[..len]);
+        Ok(len)
+    }
+
+    fn write_data(&mut self, frame: &[u8]) -> Result<()> {
+        black_box(frame);
+        self.output.push_back(ACK.to_vec());
+        self.output.push_back(self.response.clone());
+        Ok(())
+    }
+
+    fn wait_ready(&mut self, _timeout: Duration) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn wake_up(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl PN532 for Loopback {
+    fn options(&self) -> &Options {
+        &self.options
+    }
+
+    fn auth_cache(&mut self) -> &mut AuthCache {
+        &mut self.auth_cache
+    }
+
+    fn gpio_cache(&mut self) -> &mut GpioCache {
+        &mut self.gpio_cache
+    }
+
+    fn hooks(&mut self) -> &mut Hooks {
+        &mut self.hooks
+    }
+
+    fn stats_mut(&mut self) -> &mut Stats {
+        &mut self.stats
+    }
+}
+
+/// A normal information frame from the PN532 carrying `data`.
+fn frame_codec(c: &mut Criterion) {
+    // GetFirmwareVersion: IC, Ver, Rev, Support.
+    let mut pn532 = Loopback::answering(frame::encode(&[0xD5, 0x03, 0x32, 0x01, 0x06, 0x07]).unwrap());
+    c.bench_function("send_command GetFirmwareVersion", |b| {
+        b.iter(|| pn532.send_command(0x02, &[], 4, Duration::from_secs(1)).unwrap())
+    });
+
+    // InDataExchange READ: status and a 16 byte block.
+    let mut block = vec![0xD5, 0x41, 0x00];
+    block.extend(0..16);
+    let mut pn532 = Loopback::answering(frame::encode(&block).unwrap());
+    c.bench_function("send_command InDataExchange READ", |b| {
+        b.iter(|| pn532.send_command(0x40, black_box(&[0x01, 0x30, 0x04]), 17, Duration::from_secs(1)).unwrap())
+    });
+
+    // The largest frame the PN532 sends.
+    let mut pn532 = Loopback::answering(frame::encode(&[0xAB; 255]).unwrap());
+    c.bench_function("read_frame 255 bytes", |b| b.iter(|| pn532.read_frame(255).unwrap()));
+}
+
+criterion_group!(benches, frame_codec);
+criterion_main!(benches);
@@ -0,0 +1,14 @@
+//! Feed arbitrary bytes to the frame decoder: it must never panic, and any
+//! information frame it accepts must survive re-encoding.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pn532_nfc::pn532::frame::{self, Frame};
+
+fuzz_target!(|bytes: &[u8]| {
+    if let Ok(Frame::Information(data)) = frame::decode(bytes) {
+        let encoded = frame::encode(data).expect("decoded data fits in a frame");
+        assert_eq!(frame::decode(&encoded).unwrap(), Frame::Information(data));
+    }
+});
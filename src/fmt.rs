@@ -0,0 +1,32 @@
+//! Logging shims.
+//!
+//! The driver traces frames and commands through the `log` crate by default.
+//! With the `defmt` feature enabled the same messages are emitted through
+//! `defmt` instead, so they can be read over RTT on embedded targets.
+
+macro_rules! debug {
+    ($($arg:tt)*) => {{
+        #[cfg(not(feature = "defmt"))]
+        ::log::debug!($($arg)*);
+        #[cfg(feature = "defmt")]
+        ::defmt::debug!($($arg)*);
+    }};
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => {{
+        #[cfg(not(feature = "defmt"))]
+        ::log::info!($($arg)*);
+        #[cfg(feature = "defmt")]
+        ::defmt::info!($($arg)*);
+    }};
+}
+
+macro_rules! warn {
+    ($($arg:tt)*) => {{
+        #[cfg(not(feature = "defmt"))]
+        ::log::warn!($($arg)*);
+        #[cfg(feature = "defmt")]
+        ::defmt::warn!($($arg)*);
+    }};
+}
@@ -0,0 +1,25 @@
+//! Driver for the NXP PN532 NFC controller (e.g. the WaveShare PN532 NFC HAT)
+//! on a Raspberry Pi.
+//!
+//! Open a reader with [`PN532Spi::new`] (or several sharing one bus through
+//! [`ReaderManager`]) and drive it through the [`PN532`] trait.
+
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+extern crate core;
+
+#[macro_use]
+mod fmt;
+pub mod pn532;
+
+pub use pn532::{PN532, PN532Gpio, LowPowerMode, RawFraming, TargetEvent, GpioCache, GpioState, Block, Page, Sector, ActivationResult, BaudModulation, BitRate, Command, DiagnoseResult, DiagnoseTest, FelicaBlock, FelicaParams, FirmwareVersion, GeneralStatus, MifareParams, Modulation, ParametersFlags, RfTimeout, SamMode, SerialBaudRate, TargetConfig, TargetModeFlags, TargetState, TargetStatus, TxFraming, TxMode, TxSpeed, TypeBPolling, WakeupSources, SelectedTarget, Target, PassiveDetection, PassiveTarget, TagKind, TypeBTarget, FelicaTarget, DepTarget, Error, CommandOutcome, Hook, Hooks, Options, Result, Retries, RetryPolicy, SelfTest, SelfTestReport, Stats, CommandStats, Counters, Status, Timeouts, Transport, MirrorMode, NtagAccess, NtagConfig, NtagVariant, TagVersion, Uid, Watchdog};
+pub use pn532::{AnalogPreset, AnalogSettings, MIFARE_CMD_AUTH_A, MIFARE_CMD_AUTH_B, SectorKeys, AuthCache, KeyMap, KeyType, SectorKey, AccessBits, DataAccess, validate_manufacturer_block, MagicCard, TrailerAccess, ClassicDump, SectorDump, DumpAddress, NtagDump, RestoreChange, RestoreOptions, RestoreReport, Canceller};
+pub use pn532::frame::FrameError;
+pub use pn532::spi::{PN532Spi, PN532SpiBuilder, SpiDevice};
+pub use pn532::manager::{ReaderId, ReaderManager};
+pub use pn532::apdu::{Apdu, ApduChannel, Rapdu, StatusWord};
+pub use pn532::Ats;
+pub use pn532::desfire::DesfireTag;
+pub use pn532::session::{IsoDepTag, MifareClassicTag, Ntag2xxTag, Tag};
+#[cfg(feature = "tokio")]
+pub use pn532::async_spi::AsyncPN532Spi;
@@ -1,9 +0,0 @@
-#![feature(box_syntax)]
-#![allow(non_snake_case)]
-#![allow(dead_code)]
-extern crate core;
-
-mod pn532;
-
-fn main() {
-}
@@ -0,0 +1,132 @@
+//! Memory addressing for MIFARE Classic (blocks and sectors) and NTAG /
+//! MIFARE Ultralight (pages) tags.
+//!
+//! Classic blocks and Type 2 pages are both single bytes on the wire, the
+//! newtypes keep one from being passed where the other is expected.
+
+use std::fmt;
+
+use crate::pn532::{Error, Result};
+
+/// Sectors 0-31 have 4 blocks, sectors 32-39 (MIFARE Classic 4K) have 16.
+const SMALL_SECTORS: u8 = 32;
+const SECTORS: u8 = 40;
+
+/// A 16 byte MIFARE Classic block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Block(pub u8);
+
+impl Block {
+    /// The sector containing this block.
+    pub fn sector(self) -> Sector {
+        if self.0 < SMALL_SECTORS * 4 {
+            Sector(self.0 / 4)
+        } else {
+            Sector(SMALL_SECTORS + (self.0 - SMALL_SECTORS * 4) / 16)
+        }
+    }
+
+    /// Whether this is the sector trailer holding the keys and access bits.
+    pub fn is_trailer(self) -> bool {
+        self == self.sector().trailer()
+    }
+
+    /// Whether this is block 0, holding the UID and manufacturer data.
+    pub fn is_manufacturer(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl From<u8> for Block {
+    fn from(block: u8) -> Self {
+        Block(block)
+    }
+}
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "block {}", self.0)
+    }
+}
+
+/// A MIFARE Classic sector, 0-15 on 1K cards and 0-39 on 4K cards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Sector(u8);
+
+impl Sector {
+    pub fn new(sector: u8) -> Result<Self> {
+        if sector < SECTORS {
+            Ok(Sector(sector))
+        } else {
+            Err(Error::InvalidArgument(format!("sector {} out of range (0-{})", sector, SECTORS - 1)))
+        }
+    }
+
+    pub fn number(self) -> u8 {
+        self.0
+    }
+
+    /// Number of blocks in this sector, including the trailer.
+    pub fn block_count(self) -> u8 {
+        if self.0 < SMALL_SECTORS { 4 } else { 16 }
+    }
+
+    /// The first block of this sector.
+    pub fn first_block(self) -> Block {
+        if self.0 < SMALL_SECTORS {
+            Block(self.0 * 4)
+        } else {
+            Block(SMALL_SECTORS * 4 + (self.0 - SMALL_SECTORS) * 16)
+        }
+    }
+
+    /// The sector trailer, the last block of this sector.
+    pub fn trailer(self) -> Block {
+        Block(self.first_block().0 + (self.block_count() - 1))
+    }
+
+    /// All blocks of this sector, trailer included.
+    pub fn blocks(self) -> impl Iterator<Item = Block> {
+        let first = self.first_block().0;
+        (first..=self.trailer().0).map(Block)
+    }
+}
+
+impl TryFrom<u8> for Sector {
+    type Error = Error;
+
+    fn try_from(sector: u8) -> Result<Self> {
+        Sector::new(sector)
+    }
+}
+
+impl From<Block> for Sector {
+    fn from(block: Block) -> Self {
+        block.sector()
+    }
+}
+
+impl fmt::Display for Sector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "sector {}", self.0)
+    }
+}
+
+/// A 4 byte NTAG / MIFARE Ultralight page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Page(pub u8);
+
+impl From<u8> for Page {
+    fn from(page: u8) -> Self {
+        Page(page)
+    }
+}
+
+impl fmt::Display for Page {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "page {}", self.0)
+    }
+}
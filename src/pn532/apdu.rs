@@ -0,0 +1,505 @@
+//! ISO/IEC 7816-4 APDUs over an [`IsoDepTag`]: command encoding, short or
+//! extended, and answers with their decoded SW1 SW2 status.
+//!
+//! The PN532 handles the ISO-DEP blocks of InDataExchange, and chains the
+//! answer itself.  Commands longer than one InDataExchange are sent in
+//! parts with the MI bit set on the target number, which makes the PN532
+//! chain them.  A 61xx status is followed by GET RESPONSE until the whole
+//! answer is in, and a 6Cxx status repeats the command with the Le the card
+//! asked for.
+
+use std::fmt;
+
+use crate::pn532::frame::MAX_DATA_LEN;
+use crate::pn532::session::IsoDepTag;
+use crate::pn532::{Error, PN532, Result};
+
+const INS_GET_RESPONSE: u8 = 0xC0;
+
+/// Set on the target number of InDataExchange when more data follows.
+const MORE_INFORMATION: u8 = 0x40;
+
+/// Most command bytes in one InDataExchange, besides TFI, command code and
+/// target number.
+const MAX_PART_LEN: usize = MAX_DATA_LEN - 3;
+
+/// Most GET RESPONSE rounds for one command, against a card that never
+/// stops announcing more data.
+const MAX_GET_RESPONSES: usize = 256;
+
+/// Logical channel bits of an interindustry CLA, kept for GET RESPONSE.
+const CLA_CHANNEL: u8 = 0x03;
+
+/// A command APDU.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Apdu {
+    pub cla: u8,
+    pub ins: u8,
+    pub p1: u8,
+    pub p2: u8,
+    /// The command data, sent with Lc if not empty.
+    pub data: Vec<u8>,
+    /// Most answer bytes expected, 256 at most in a short APDU and 65536 in
+    /// an extended one.  `None` when no answer data is expected.
+    pub le: Option<usize>,
+}
+
+impl Apdu {
+    pub fn new(cla: u8, ins: u8, p1: u8, p2: u8) -> Self {
+        Self { cla, ins, p1, p2, data: Vec::new(), le: None }
+    }
+
+    pub fn data(mut self, data: &[u8]) -> Self {
+        self.data = data.to_vec();
+        self
+    }
+
+    pub fn le(mut self, le: usize) -> Self {
+        self.le = Some(le);
+        self
+    }
+
+    /// Whether Lc or Le need the extended length fields.
+    pub fn is_extended(&self) -> bool {
+        self.data.len() > 0xFF || self.le.is_some_and(|le| le > 0x100)
+    }
+
+    /// The encoded APDU: header, then Lc and data, then Le, each as present.
+    /// An Le of 256 (short) or 65536 (extended) is encoded as zeros.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        if self.data.len() > 0xFFFF {
+            return Err(Error::InvalidArgument(format!("APDU data is at most 65535 bytes, got {}", self.data.len())));
+        }
+        if let Some(le) = self.le.filter(|&le| le == 0 || le > 0x1_0000) {
+            return Err(Error::InvalidArgument(format!("APDU Le is 1 to 65536, got {}", le)));
+        }
+
+        let mut bytes = vec![self.cla, self.ins, self.p1, self.p2];
+        let extended = self.is_extended();
+        if !self.data.is_empty() {
+            if extended {
+                bytes.push(0x00);
+                bytes.extend_from_slice(&(self.data.len() as u16).to_be_bytes());
+            } else {
+                bytes.push(self.data.len() as u8);
+            }
+            bytes.extend_from_slice(&self.data);
+        }
+        match self.le {
+            Some(le) if extended => {
+                if self.data.is_empty() {
+                    bytes.push(0x00);
+                }
+                bytes.extend_from_slice(&(le as u16).to_be_bytes());
+            }
+            Some(le) => bytes.push(le as u8),
+            None => {}
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// A response APDU.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rapdu {
+    pub data: Vec<u8>,
+    pub status: StatusWord,
+}
+
+impl Rapdu {
+    /// Split an answer into its data and trailing SW1 SW2.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let Some(len) = bytes.len().checked_sub(2) else {
+            return Err(Error::UnexpectedResponse("APDU response has no status word!"));
+        };
+        let (data, sw) = bytes.split_at(len);
+        Ok(Self { data: data.to_vec(), status: StatusWord::from(u16::from_be_bytes([sw[0], sw[1]])) })
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status.is_success()
+    }
+
+    /// The data if the status is 9000, otherwise [`Error::Apdu`].
+    pub fn into_result(self) -> Result<Vec<u8>> {
+        if self.is_success() {
+            Ok(self.data)
+        } else {
+            Err(Error::Apdu(self.status))
+        }
+    }
+}
+
+/// The SW1 SW2 status of a response APDU (ISO/IEC 7816-4 §5.6).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum StatusWord {
+    /// 9000: normal processing.
+    Success,
+    /// 61xx: SW2 more answer bytes are available with GET RESPONSE, 0 for
+    /// 256 or more.
+    BytesAvailable(u8),
+    /// 62xx: warning, non-volatile memory unchanged.
+    WarningUnchanged(u8),
+    /// 63xx: warning, non-volatile memory changed; 63Cx tells a counter.
+    WarningChanged(u8),
+    /// 64xx: execution error, non-volatile memory unchanged.
+    ExecutionErrorUnchanged(u8),
+    /// 65xx: execution error, non-volatile memory changed.
+    ExecutionErrorChanged(u8),
+    /// 66xx: security-related issue.
+    SecurityIssue(u8),
+    /// 6700: wrong length.
+    WrongLength,
+    /// 68xx: function in CLA not supported, such as logical channels or
+    /// secure messaging.
+    ClaFunctionNotSupported(u8),
+    /// 6982: security status not satisfied.
+    SecurityStatusNotSatisfied,
+    /// 6983: authentication method blocked.
+    AuthenticationBlocked,
+    /// 6984: reference data not usable.
+    ReferenceDataNotUsable,
+    /// 6985: conditions of use not satisfied.
+    ConditionsNotSatisfied,
+    /// 6986: command not allowed, no current EF.
+    NoCurrentEf,
+    /// 69xx: other command not allowed.
+    CommandNotAllowed(u8),
+    /// 6A80: incorrect parameters in the command data.
+    WrongData,
+    /// 6A81: function not supported.
+    FunctionNotSupported,
+    /// 6A82: file or application not found.
+    FileNotFound,
+    /// 6A83: record not found.
+    RecordNotFound,
+    /// 6A84: not enough memory space in the file.
+    NotEnoughMemory,
+    /// 6A86: incorrect P1 P2.
+    IncorrectP1P2,
+    /// 6A88: referenced data not found.
+    ReferenceNotFound,
+    /// 6Axx: other wrong parameters P1 P2.
+    WrongParameters(u8),
+    /// 6B00: wrong parameters P1 P2.
+    WrongP1P2,
+    /// 6Cxx: wrong Le, SW2 is the exact length, 0 for 256.
+    WrongLe(u8),
+    /// 6D00: instruction code not supported or invalid.
+    InsNotSupported,
+    /// 6E00: class not supported.
+    ClaNotSupported,
+    /// 6F00: no precise diagnosis.
+    NoPreciseDiagnosis,
+    /// A status not defined by ISO/IEC 7816-4, such as the 91xx of DESFire
+    /// native commands.
+    Unknown(u16),
+}
+
+impl StatusWord {
+    /// SW1 and SW2 as one number.
+    pub fn code(self) -> u16 {
+        let with = |sw1: u8, sw2: u8| u16::from_be_bytes([sw1, sw2]);
+        match self {
+            StatusWord::Success => 0x9000,
+            StatusWord::BytesAvailable(sw2) => with(0x61, sw2),
+            StatusWord::WarningUnchanged(sw2) => with(0x62, sw2),
+            StatusWord::WarningChanged(sw2) => with(0x63, sw2),
+            StatusWord::ExecutionErrorUnchanged(sw2) => with(0x64, sw2),
+            StatusWord::ExecutionErrorChanged(sw2) => with(0x65, sw2),
+            StatusWord::SecurityIssue(sw2) => with(0x66, sw2),
+            StatusWord::WrongLength => 0x6700,
+            StatusWord::ClaFunctionNotSupported(sw2) => with(0x68, sw2),
+            StatusWord::SecurityStatusNotSatisfied => 0x6982,
+            StatusWord::AuthenticationBlocked => 0x6983,
+            StatusWord::ReferenceDataNotUsable => 0x6984,
+            StatusWord::ConditionsNotSatisfied => 0x6985,
+            StatusWord::NoCurrentEf => 0x6986,
+            StatusWord::CommandNotAllowed(sw2) => with(0x69, sw2),
+            StatusWord::WrongData => 0x6A80,
+            StatusWord::FunctionNotSupported => 0x6A81,
+            StatusWord::FileNotFound => 0x6A82,
+            StatusWord::RecordNotFound => 0x6A83,
+            StatusWord::NotEnoughMemory => 0x6A84,
+            StatusWord::IncorrectP1P2 => 0x6A86,
+            StatusWord::ReferenceNotFound => 0x6A88,
+            StatusWord::WrongParameters(sw2) => with(0x6A, sw2),
+            StatusWord::WrongP1P2 => 0x6B00,
+            StatusWord::WrongLe(sw2) => with(0x6C, sw2),
+            StatusWord::InsNotSupported => 0x6D00,
+            StatusWord::ClaNotSupported => 0x6E00,
+            StatusWord::NoPreciseDiagnosis => 0x6F00,
+            StatusWord::Unknown(code) => code,
+        }
+    }
+
+    pub fn sw1(self) -> u8 {
+        self.code().to_be_bytes()[0]
+    }
+
+    pub fn sw2(self) -> u8 {
+        self.code().to_be_bytes()[1]
+    }
+
+    pub fn is_success(self) -> bool {
+        self == StatusWord::Success
+    }
+
+    /// Whether the command was processed with a warning (62xx or 63xx).
+    pub fn is_warning(self) -> bool {
+        matches!(self, StatusWord::WarningUnchanged(_) | StatusWord::WarningChanged(_))
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            StatusWord::Success => "success",
+            StatusWord::BytesAvailable(_) => "more response bytes available",
+            StatusWord::WarningUnchanged(_) => "warning, memory unchanged",
+            StatusWord::WarningChanged(_) => "warning, memory changed",
+            StatusWord::ExecutionErrorUnchanged(_) => "execution error, memory unchanged",
+            StatusWord::ExecutionErrorChanged(_) => "execution error, memory changed",
+            StatusWord::SecurityIssue(_) => "security issue",
+            StatusWord::WrongLength => "wrong length",
+            StatusWord::ClaFunctionNotSupported(_) => "function in CLA not supported",
+            StatusWord::SecurityStatusNotSatisfied => "security status not satisfied",
+            StatusWord::AuthenticationBlocked => "authentication method blocked",
+            StatusWord::ReferenceDataNotUsable => "reference data not usable",
+            StatusWord::ConditionsNotSatisfied => "conditions of use not satisfied",
+            StatusWord::NoCurrentEf => "no current EF",
+            StatusWord::CommandNotAllowed(_) => "command not allowed",
+            StatusWord::WrongData => "incorrect command data",
+            StatusWord::FunctionNotSupported => "function not supported",
+            StatusWord::FileNotFound => "file or application not found",
+            StatusWord::RecordNotFound => "record not found",
+            StatusWord::NotEnoughMemory => "not enough memory",
+            StatusWord::IncorrectP1P2 => "incorrect P1 P2",
+            StatusWord::ReferenceNotFound => "referenced data not found",
+            StatusWord::WrongParameters(_) => "wrong parameters",
+            StatusWord::WrongP1P2 => "wrong P1 P2",
+            StatusWord::WrongLe(_) => "wrong Le",
+            StatusWord::InsNotSupported => "instruction not supported",
+            StatusWord::ClaNotSupported => "class not supported",
+            StatusWord::NoPreciseDiagnosis => "no precise diagnosis",
+            StatusWord::Unknown(_) => "unknown status",
+        }
+    }
+}
+
+impl From<u16> for StatusWord {
+    fn from(code: u16) -> Self {
+        let [sw1, sw2] = code.to_be_bytes();
+        match (sw1, sw2) {
+            (0x90, 0x00) => StatusWord::Success,
+            (0x61, _) => StatusWord::BytesAvailable(sw2),
+            (0x62, _) => StatusWord::WarningUnchanged(sw2),
+            (0x63, _) => StatusWord::WarningChanged(sw2),
+            (0x64, _) => StatusWord::ExecutionErrorUnchanged(sw2),
+            (0x65, _) => StatusWord::ExecutionErrorChanged(sw2),
+            (0x66, _) => StatusWord::SecurityIssue(sw2),
+            (0x67, 0x00) => StatusWord::WrongLength,
+            (0x68, _) => StatusWord::ClaFunctionNotSupported(sw2),
+            (0x69, 0x82) => StatusWord::SecurityStatusNotSatisfied,
+            (0x69, 0x83) => StatusWord::AuthenticationBlocked,
+            (0x69, 0x84) => StatusWord::ReferenceDataNotUsable,
+            (0x69, 0x85) => StatusWord::ConditionsNotSatisfied,
+            (0x69, 0x86) => StatusWord::NoCurrentEf,
+            (0x69, _) => StatusWord::CommandNotAllowed(sw2),
+            (0x6A, 0x80) => StatusWord::WrongData,
+            (0x6A, 0x81) => StatusWord::FunctionNotSupported,
+            (0x6A, 0x82) => StatusWord::FileNotFound,
+            (0x6A, 0x83) => StatusWord::RecordNotFound,
+            (0x6A, 0x84) => StatusWord::NotEnoughMemory,
+            (0x6A, 0x86) => StatusWord::IncorrectP1P2,
+            (0x6A, 0x88) => StatusWord::ReferenceNotFound,
+            (0x6A, _) => StatusWord::WrongParameters(sw2),
+            (0x6B, 0x00) => StatusWord::WrongP1P2,
+            (0x6C, _) => StatusWord::WrongLe(sw2),
+            (0x6D, 0x00) => StatusWord::InsNotSupported,
+            (0x6E, 0x00) => StatusWord::ClaNotSupported,
+            (0x6F, 0x00) => StatusWord::NoPreciseDiagnosis,
+            _ => StatusWord::Unknown(code),
+        }
+    }
+}
+
+impl fmt::Display for StatusWord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (SW {:04X})", self.description(), self.code())
+    }
+}
+
+/// APDUs to an ISO/IEC 14443-4 card.
+pub struct ApduChannel<'r, P: PN532 + ?Sized> {
+    tag: IsoDepTag<'r, P>,
+}
+
+impl<'r, P: PN532 + ?Sized> ApduChannel<'r, P> {
+    pub fn new(tag: IsoDepTag<'r, P>) -> Self {
+        Self { tag }
+    }
+
+    pub fn tag(&mut self) -> &mut IsoDepTag<'r, P> {
+        &mut self.tag
+    }
+
+    pub fn into_inner(self) -> IsoDepTag<'r, P> {
+        self.tag
+    }
+
+    /// Send `apdu` and return the card's answer, fetched with GET RESPONSE
+    /// as long as the card announces more (61xx), and with the command
+    /// repeated once with the Le the card asked for (6Cxx).  Only these
+    /// statuses are acted on; check the others with [`Rapdu::into_result`].
+    pub fn transceive(&mut self, apdu: &Apdu) -> Result<Rapdu> {
+        let mut answer = self.send(&apdu.to_bytes()?)?;
+        if let StatusWord::WrongLe(le) = answer.status {
+            let retry = apdu.clone().le(if le == 0 { 0x100 } else { le as usize });
+            answer = self.send(&retry.to_bytes()?)?;
+        }
+
+        let mut data = answer.data;
+        let mut status = answer.status;
+        let mut rounds = 0;
+        while let StatusWord::BytesAvailable(le) = status {
+            rounds += 1;
+            if rounds > MAX_GET_RESPONSES {
+                return Err(Error::UnexpectedResponse("APDU response never ends!"));
+            }
+            let get_response = Apdu::new(apdu.cla & CLA_CHANNEL, INS_GET_RESPONSE, 0x00, 0x00).le(if le == 0 { 0x100 } else { le as usize });
+            let next = self.send(&get_response.to_bytes()?)?;
+            data.extend_from_slice(&next.data);
+            status = next.status;
+        }
+
+        Ok(Rapdu { data, status })
+    }
+
+    /// Send encoded APDU bytes, chained over several InDataExchange if
+    /// needed.
+    fn send(&mut self, bytes: &[u8]) -> Result<Rapdu> {
+        let target = self.tag.card().target_number;
+        let timeout = self.tag.timeout();
+        let mut parts = bytes.chunks(MAX_PART_LEN).peekable();
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                return Rapdu::from_bytes(&self.tag.exchange(part)?);
+            }
+            self.tag.reader().in_data_exchange_with_timeout(target | MORE_INFORMATION, part, timeout)?.ok_or(Error::Timeout)?;
+        }
+        Err(Error::InvalidArgument("an APDU is at least 4 bytes".to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pn532::mock::{ClassicCard, MockPn532};
+    use crate::pn532::{PassiveTarget, Uid};
+
+    const SELECT: [u8; 4] = [0x00, 0xA4, 0x04, 0x00];
+
+    fn select() -> Apdu {
+        Apdu::new(0x00, 0xA4, 0x04, 0x00)
+    }
+
+    #[test]
+    fn encodes_short_apdus() {
+        assert_eq!(select().to_bytes().unwrap(), SELECT);
+        assert_eq!(select().le(0x10).to_bytes().unwrap(), [&SELECT[..], &[0x10]].concat());
+        assert_eq!(select().data(&[0xD2, 0x76]).to_bytes().unwrap(), [&SELECT[..], &[0x02, 0xD2, 0x76]].concat());
+        assert_eq!(select().data(&[0xD2, 0x76]).le(0xFF).to_bytes().unwrap(), [&SELECT[..], &[0x02, 0xD2, 0x76, 0xFF]].concat());
+        assert!(!select().data(&[0; 0xFF]).le(0x100).is_extended());
+    }
+
+    #[test]
+    fn encodes_le_256_as_zero() {
+        assert_eq!(select().le(0x100).to_bytes().unwrap(), [&SELECT[..], &[0x00]].concat());
+    }
+
+    #[test]
+    fn encodes_extended_apdus() {
+        let data = vec![0xAB; 0x100];
+        let apdu = select().data(&data);
+        assert!(apdu.is_extended());
+        assert_eq!(apdu.to_bytes().unwrap(), [&SELECT[..], &[0x00, 0x01, 0x00], &data[..]].concat());
+        // Le follows the extended Lc on 2 bytes.
+        assert_eq!(apdu.le(0x1234).to_bytes().unwrap(), [&SELECT[..], &[0x00, 0x01, 0x00], &data[..], &[0x12, 0x34]].concat());
+        // Without data, Le takes 3 bytes.
+        assert_eq!(select().le(0x101).to_bytes().unwrap(), [&SELECT[..], &[0x00, 0x01, 0x01]].concat());
+    }
+
+    #[test]
+    fn encodes_extended_le_65536_as_zeros() {
+        assert_eq!(select().le(0x1_0000).to_bytes().unwrap(), [&SELECT[..], &[0x00, 0x00, 0x00]].concat());
+        assert_eq!(select().data(&[0x01]).le(0x1_0000).to_bytes().unwrap(), [&SELECT[..], &[0x00, 0x00, 0x01, 0x01, 0x00, 0x00]].concat());
+    }
+
+    #[test]
+    fn rejects_out_of_range_lengths() {
+        assert!(select().le(0).to_bytes().is_err());
+        assert!(select().le(0x1_0001).to_bytes().is_err());
+        assert!(select().data(&vec![0; 0x1_0000]).to_bytes().is_err());
+    }
+
+    #[test]
+    fn maps_status_words() {
+        assert_eq!(StatusWord::from(0x9000), StatusWord::Success);
+        assert_eq!(StatusWord::from(0x6110), StatusWord::BytesAvailable(0x10));
+        assert_eq!(StatusWord::from(0x6C08), StatusWord::WrongLe(0x08));
+        assert_eq!(StatusWord::from(0x6A82), StatusWord::FileNotFound);
+        assert_eq!(StatusWord::from(0x6A99), StatusWord::WrongParameters(0x99));
+        assert_eq!(StatusWord::from(0x9100), StatusWord::Unknown(0x9100));
+        for code in [0x9000, 0x6110, 0x6C00, 0x6300, 0x6982, 0x6A82, 0x6A99, 0x6F00, 0x9100] {
+            let status = StatusWord::from(code);
+            assert_eq!(status.code(), code);
+            assert_eq!((status.sw1(), status.sw2()), (code.to_be_bytes()[0], code.to_be_bytes()[1]));
+        }
+    }
+
+    #[test]
+    fn splits_response_apdus() {
+        let answer = Rapdu::from_bytes(&[0x01, 0x02, 0x90, 0x00]).unwrap();
+        assert_eq!(answer, Rapdu { data: vec![0x01, 0x02], status: StatusWord::Success });
+        assert_eq!(answer.into_result().unwrap(), [0x01, 0x02]);
+        assert!(matches!(Rapdu::from_bytes(&[0x6A, 0x82]).unwrap().into_result(), Err(Error::Apdu(StatusWord::FileNotFound))));
+        assert!(Rapdu::from_bytes(&[0x90]).is_err());
+    }
+
+    fn iso_dep_card(answers: &[&[u8]]) -> MockPn532 {
+        let mut pn532 = MockPn532::new(ClassicCard::new([0x04, 0x01, 0x02, 0x03]));
+        pn532.iso_dep_answers = answers.iter().map(|answer| answer.to_vec()).collect();
+        pn532
+    }
+
+    fn transceive(pn532: &mut MockPn532, apdu: &Apdu) -> Rapdu {
+        let card = PassiveTarget { target_number: 1, atqa: [0x03, 0x44], sak: 0x20, uid: Uid::try_from(&[0x04, 0x01, 0x02, 0x03][..]).unwrap(), ats: None };
+        let tag = IsoDepTag::new(pn532, card).unwrap();
+        ApduChannel::new(tag).transceive(apdu).unwrap()
+    }
+
+    /// The APDUs sent with InDataExchange.
+    fn sent_apdus(pn532: &MockPn532) -> Vec<Vec<u8>> {
+        pn532.commands.iter().filter_map(|command| command.strip_prefix(&[0x40, 0x01][..])).map(<[u8]>::to_vec).collect()
+    }
+
+    #[test]
+    fn fetches_61xx_answers_with_get_response() {
+        let mut pn532 = iso_dep_card(&[&[0x01, 0x02, 0x61, 0x03], &[0x03, 0x04, 0x05, 0x61, 0x00], &[0x06, 0x90, 0x00]]);
+        let answer = transceive(&mut pn532, &Apdu::new(0x02, 0xB0, 0x00, 0x00).le(0x02));
+        assert_eq!(answer, Rapdu { data: vec![0x01, 0x02, 0x03, 0x04, 0x05, 0x06], status: StatusWord::Success });
+        // GET RESPONSE keeps the logical channel and asks for what SW2
+        // announced, 256 for 00.
+        assert_eq!(sent_apdus(&pn532), [vec![0x02, 0xB0, 0x00, 0x00, 0x02], vec![0x02, 0xC0, 0x00, 0x00, 0x03], vec![0x02, 0xC0, 0x00, 0x00, 0x00]]);
+    }
+
+    #[test]
+    fn repeats_6cxx_answers_with_the_right_le() {
+        let mut pn532 = iso_dep_card(&[&[0x6C, 0x04], &[0x01, 0x02, 0x03, 0x04, 0x90, 0x00]]);
+        let answer = transceive(&mut pn532, &Apdu::new(0x00, 0xB0, 0x00, 0x00).le(0x10));
+        assert_eq!(answer, Rapdu { data: vec![0x01, 0x02, 0x03, 0x04], status: StatusWord::Success });
+        assert_eq!(sent_apdus(&pn532), [vec![0x00, 0xB0, 0x00, 0x00, 0x10], vec![0x00, 0xB0, 0x00, 0x00, 0x04]]);
+    }
+}
@@ -0,0 +1,227 @@
+//! Async wrapper around [`PN532Spi`], available with the `tokio` feature.
+//!
+//! The PN532 waits for cards and responses for up to several seconds. Every
+//! operation runs on tokio's blocking thread pool so those waits never stall
+//! the async executor. When an IRQ pin is wired, waiting for a card is done
+//! with an async GPIO interrupt instead, which uses no thread at all and wakes
+//! up as soon as the PN532 asserts the line.
+
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use rppal::gpio::{InputPin, Trigger};
+use tokio::sync::{Mutex as AsyncMutex, Notify};
+use tokio::{task, time};
+
+use crate::pn532::command::{Command, InListPassiveTarget};
+use crate::pn532::spi::{PN532Spi, PN532SpiBuilder};
+use crate::pn532::{begin_exchange, end_wait, finish_exchange, parse_response, report_exchange, select_single_target, BaudModulation, Block, Error, FirmwareVersion, Page, PassiveTarget, Request, Result, Uid, PN532};
+
+/// A PN532 connected over SPI, driven from async code.
+///
+/// Cloning the handle shares the reader; operations issued concurrently run
+/// one after another.
+///
+/// ```no_run
+/// # async fn run() -> pn532_nfc::Result<()> {
+/// use pn532_nfc::{AsyncPN532Spi, BaudModulation};
+///
+/// let pn532 = AsyncPN532Spi::new(Some(4), None, Some(20)).await?;
+/// if let Some(target) = pn532.read_passive_target(BaudModulation::Iso14443aType106, 1.0).await? {
+///     println!("Found card: {}", target.uid);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AsyncPN532Spi {
+    reader: Arc<Mutex<PN532Spi>>,
+    /// Held for a whole command, which may span several blocking calls.
+    exchange: Arc<AsyncMutex<()>>,
+}
+
+impl AsyncPN532Spi {
+    /// Open and initialize the PN532 like [`PN532Spi::new`].
+    pub async fn new(cs: Option<u8>, irq: Option<u8>, reset: Option<u8>) -> Result<Self> {
+        Ok(Self::from_reader(blocking(move || PN532Spi::new(cs, irq, reset)).await?))
+    }
+
+    /// Open and initialize the PN532 described by `builder`.
+    pub async fn build(builder: PN532SpiBuilder) -> Result<Self> {
+        Ok(Self::from_reader(blocking(move || builder.build()).await?))
+    }
+
+    /// Drive an already initialized reader from async code.
+    pub fn from_reader(reader: PN532Spi) -> Self {
+        Self {
+            reader: Arc::new(Mutex::new(reader)),
+            exchange: Arc::new(AsyncMutex::new(())),
+        }
+    }
+
+    /// Run `f` with exclusive access to the reader on the blocking thread
+    /// pool, for operations that have no async variant.
+    pub async fn with_reader<R, F>(&self, f: F) -> Result<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut PN532Spi) -> Result<R> + Send + 'static,
+    {
+        let _exchange = self.exchange.lock().await;
+        self.run(f).await
+    }
+
+    /// Run `f` on the blocking thread pool. The caller holds `exchange`.
+    async fn run<R, F>(&self, f: F) -> Result<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut PN532Spi) -> Result<R> + Send + 'static,
+    {
+        let reader = self.reader.clone();
+        blocking(move || f(&mut reader.lock().unwrap_or_else(PoisonError::into_inner))).await
+    }
+
+    /// Send `request` and parse its response like [`PN532::execute`]. The
+    /// response is awaited through an async interrupt on the IRQ pin when
+    /// one is in use, otherwise on the blocking thread pool.
+    async fn execute<R>(&self, request: R, timeout: Option<Duration>) -> Result<Option<R::Response>>
+    where
+        R: Request + Send + 'static,
+        R::Response: Send,
+    {
+        let _exchange = self.exchange.lock().await;
+        self.execute_locked(request, timeout).await
+    }
+
+    /// [`AsyncPN532Spi::execute`] for a caller holding `exchange`.
+    async fn execute_locked<R>(&self, request: R, timeout: Option<Duration>) -> Result<Option<R::Response>>
+    where
+        R: Request + Send + 'static,
+        R::Response: Send,
+    {
+        let uses_irq = self.run(|reader| Ok(reader.irq_input()?.is_some())).await?;
+        if !uses_irq {
+            return self.run(move |reader| reader.execute(&request, timeout)).await;
+        }
+
+        let params = request.params();
+        let start = Instant::now();
+        let result = self.exchange_with_irq(R::COMMAND, params.clone(), timeout).await;
+        self.run(move |reader| {
+            report_exchange(reader, R::COMMAND.code(), result.as_ref().map(|response| response.as_ref().map(Vec::len)), Some(start));
+            match result? {
+                Some(response) => parse_response(reader, &request, &params, response).map(Some),
+                None => Ok(None),
+            }
+        })
+        .await
+    }
+
+    /// The exchange of [`PN532::send_command`], with the response awaited
+    /// through an async interrupt on the IRQ pin.
+    async fn exchange_with_irq(&self, command: Command, params: Vec<u8>, timeout: Option<Duration>) -> Result<Option<Vec<u8>>> {
+        let code = command.code();
+        let params = Arc::new(params);
+
+        let sent = params.clone();
+        let (acked, timeout) = self.run(move |reader| {
+            let timeout = timeout.unwrap_or_else(|| reader.timeouts().response_for(command));
+            Ok((begin_exchange(reader, code, &sent)?, timeout))
+        })
+        .await?;
+        if !acked {
+            return Ok(None);
+        }
+
+        let ready = match self.run(|reader| reader.irq_input()).await? {
+            Some(irq) => wait_for_irq(irq, timeout).await,
+            None => Err(Error::Cancelled),
+        };
+        let sent = params.clone();
+        if !self.run(move |reader| end_wait(reader, code, &sent, ready)).await? {
+            return Ok(None);
+        }
+
+        self.run(move |reader| {
+            let mut response = vec![0; command.max_response_len()];
+            let len = finish_exchange(reader, code, &params, &mut response)?;
+            response.truncate(len);
+            Ok(Some(response))
+        })
+        .await
+    }
+
+    /// See [`PN532::get_firmware_version`].
+    pub async fn get_firmware_version(&self) -> Result<FirmwareVersion> {
+        self.with_reader(|reader| reader.get_firmware_version()).await
+    }
+
+    /// See [`PN532::send_command`].
+    pub async fn send_command(&self, command: u8, params: Vec<u8>, max_response: usize, timeout: Duration) -> Result<Option<Vec<u8>>> {
+        self.with_reader(move |reader| reader.send_command(command, &params, max_response, timeout)).await
+    }
+
+    /// See [`PN532::read_passive_target`].
+    pub async fn read_passive_target(&self, baud: BaudModulation, timeout: f64) -> Result<Option<PassiveTarget>> {
+        let _exchange = self.exchange.lock().await;
+        // Selecting a card, even the same one again, resets its authentication.
+        self.run(|reader| {
+            reader.auth_cache().clear();
+            Ok(())
+        })
+        .await?;
+        let request = InListPassiveTarget { max_targets: 1, baud, initiator_data: Vec::new() };
+        match self.execute_locked(request, Some(Duration::from_secs_f64(timeout))).await? {
+            None => Ok(None),
+            Some(targets) => self.run(move |reader| select_single_target(reader, &targets)).await,
+        }
+    }
+
+    /// See [`PN532::mifare_classic_authenticate_block`].
+    pub async fn mifare_classic_authenticate_block(&self, uid: Uid, block: Block, key_number: u8, key: [u8; 6]) -> Result<bool> {
+        self.with_reader(move |reader| reader.mifare_classic_authenticate_block(&uid, block, key_number, &key)).await
+    }
+
+    /// See [`PN532::mifare_classic_read_block`].
+    pub async fn mifare_classic_read_block(&self, block: Block) -> Result<Vec<u8>> {
+        self.with_reader(move |reader| reader.mifare_classic_read_block(block)).await
+    }
+
+    /// See [`PN532::mifare_classic_write_block`].
+    pub async fn mifare_classic_write_block(&self, block: Block, data: Vec<u8>) -> Result<bool> {
+        self.with_reader(move |reader| reader.mifare_classic_write_block(block, &data)).await
+    }
+
+    /// See [`PN532::ntag2xx_read_block`].
+    pub async fn ntag2xx_read_block(&self, page: Page) -> Result<Vec<u8>> {
+        self.with_reader(move |reader| reader.ntag2xx_read_block(page)).await
+    }
+
+    /// See [`PN532::ntag2xx_write_block`].
+    pub async fn ntag2xx_write_block(&self, page: Page, data: Vec<u8>) -> Result<bool> {
+        self.with_reader(move |reader| reader.ntag2xx_write_block(page, &data)).await
+    }
+}
+
+/// Wait up to `timeout` for the PN532 to pull the IRQ line low.  Returns
+/// whether it did.
+async fn wait_for_irq(mut irq: InputPin, timeout: Duration) -> Result<bool> {
+    // The line is only released once the response is read, so checking its
+    // level after arming the interrupt cannot miss a response.
+    let ready = Arc::new(Notify::new());
+    let notify = ready.clone();
+    irq.set_async_interrupt(Trigger::FallingEdge, move |_| notify.notify_one())?;
+    Ok(irq.is_low() || time::timeout(timeout, ready.notified()).await.is_ok())
+}
+
+/// Run `f` on the blocking thread pool, forwarding its panics.
+async fn blocking<R, F>(f: F) -> Result<R>
+where
+    R: Send + 'static,
+    F: FnOnce() -> Result<R> + Send + 'static,
+{
+    match task::spawn_blocking(f).await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(_) => Err(Error::Cancelled),
+    }
+}
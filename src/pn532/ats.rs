@@ -0,0 +1,227 @@
+//! The ATS (Answer To Select) of an ISO/IEC 14443-4 type A card
+//! (ISO/IEC 14443-4 §5.2): frame size, bit rates, timings and historical
+//! bytes.
+
+use std::time::Duration;
+
+use crate::pn532::frame::MAX_DATA_LEN;
+use crate::pn532::{BitRate, Error, Result};
+
+/// T0: TA(1), TB(1) and TC(1) follow.
+const T0_TA: u8 = 0x10;
+const T0_TB: u8 = 0x20;
+const T0_TC: u8 = 0x40;
+const T0_FSCI: u8 = 0x0F;
+
+/// TA(1): the same bit rate is used in both directions.
+const TA_SAME_BIT_RATE: u8 = 0x80;
+/// TA(1): DS (card to reader) 212 and 424 kbps, DR (reader to card) 212
+/// and 424 kbps.  848 kbps is left out, the PN532 does not support it.
+const TA_DS_212: u8 = 0x10;
+const TA_DS_424: u8 = 0x20;
+const TA_DR_212: u8 = 0x01;
+const TA_DR_424: u8 = 0x02;
+
+/// TC(1): NAD and CID supported.
+const TC_NAD: u8 = 0x01;
+const TC_CID: u8 = 0x02;
+
+/// Values used when the ATS leaves out T0 or an interface byte.
+const DEFAULT_FSCI: u8 = 2;
+const DEFAULT_TA: u8 = 0x00;
+const DEFAULT_FWI: u8 = 4;
+const DEFAULT_TC: u8 = TC_CID;
+
+/// FSC for FSCI 0 to 8; higher values are RFU and read as 256.
+const FRAME_SIZES: [usize; 9] = [16, 24, 32, 40, 48, 64, 96, 128, 256];
+
+/// Carrier frequency fc, in Hz.
+const FC: u64 = 13_560_000;
+
+/// Time allowed on top of the frame waiting times for the PN532 and the
+/// link to the host.
+const EXCHANGE_MARGIN: Duration = Duration::from_millis(100);
+
+/// Longest wait for one exchange, whatever the ATS: the FWT of FWI 14,
+/// about 5 s, and the margin.  Waiting the full FWT for each frame of a
+/// chained answer would take over a minute at the smallest frame size.
+const MAX_RESPONSE_TIMEOUT: Duration = Duration::from_millis(5_050);
+
+/// A parsed ATS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ats {
+    /// FSCI, coding the largest frame the card accepts, see
+    /// [`Ats::max_frame_size`].
+    pub fsci: u8,
+    /// Bit rates the card can send at, 106 kbps included.
+    pub card_to_reader: Vec<BitRate>,
+    /// Bit rates the card can receive at, 106 kbps included.
+    pub reader_to_card: Vec<BitRate>,
+    /// Whether the card only takes the same bit rate in both directions.
+    pub same_bit_rate: bool,
+    /// FWI, coding the frame waiting time, see [`Ats::frame_waiting_time`].
+    pub fwi: u8,
+    /// SFGI, coding the start-up frame guard time, see
+    /// [`Ats::start_up_frame_guard_time`].
+    pub sfgi: u8,
+    pub nad_supported: bool,
+    pub cid_supported: bool,
+    pub historical_bytes: Vec<u8>,
+}
+
+impl Ats {
+    /// Parse an ATS starting with its length byte TL, as in
+    /// [`PassiveTarget::ats`](crate::pn532::PassiveTarget::ats).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let too_short = || Error::UnexpectedResponse("ATS is shorter than its length byte or interface bytes!");
+        let (&tl, _) = bytes.split_first().ok_or_else(too_short)?;
+        let ats = bytes.get(..tl as usize).ok_or_else(too_short)?;
+
+        let Some((&t0, mut rest)) = ats.get(1..).and_then(<[u8]>::split_first) else {
+            // Only TL: every parameter takes its default.
+            return Ok(Self::with_interface_bytes(DEFAULT_FSCI, DEFAULT_TA, DEFAULT_FWI << 4, DEFAULT_TC, &[]));
+        };
+        let mut interface_byte = |present: bool, default: u8| -> Result<u8> {
+            if !present {
+                return Ok(default);
+            }
+            let (&byte, next) = rest.split_first().ok_or_else(too_short)?;
+            rest = next;
+            Ok(byte)
+        };
+        let ta = interface_byte(t0 & T0_TA != 0, DEFAULT_TA)?;
+        let tb = interface_byte(t0 & T0_TB != 0, DEFAULT_FWI << 4)?;
+        let tc = interface_byte(t0 & T0_TC != 0, DEFAULT_TC)?;
+
+        Ok(Self::with_interface_bytes(t0 & T0_FSCI, ta, tb, tc, rest))
+    }
+
+    fn with_interface_bytes(fsci: u8, ta: u8, tb: u8, tc: u8, historical_bytes: &[u8]) -> Self {
+        let bit_rates = |rate_212: u8, rate_424: u8| {
+            let mut rates = vec![BitRate::Kbps106];
+            rates.extend([(rate_212, BitRate::Kbps212), (rate_424, BitRate::Kbps424)].into_iter().filter(|&(bit, _)| ta & bit != 0).map(|(_, rate)| rate));
+            rates
+        };
+        Self {
+            fsci,
+            card_to_reader: bit_rates(TA_DS_212, TA_DS_424),
+            reader_to_card: bit_rates(TA_DR_212, TA_DR_424),
+            same_bit_rate: ta & TA_SAME_BIT_RATE != 0,
+            fwi: tb >> 4,
+            sfgi: tb & 0x0F,
+            nad_supported: tc & TC_NAD != 0,
+            cid_supported: tc & TC_CID != 0,
+            historical_bytes: historical_bytes.to_vec(),
+        }
+    }
+
+    /// FSC: the largest frame the card accepts, in bytes.
+    pub fn max_frame_size(&self) -> usize {
+        FRAME_SIZES.get(self.fsci as usize).copied().unwrap_or(256)
+    }
+
+    /// FWT: how long the card may take to start answering a frame,
+    /// (256 × 16 / fc) × 2^FWI.  FWI 15 is RFU and read as the default 4.
+    pub fn frame_waiting_time(&self) -> Duration {
+        let fwi = if self.fwi == 15 { DEFAULT_FWI } else { self.fwi };
+        frame_time(fwi)
+    }
+
+    /// SFGT: how long the card needs after the ATS before it takes a frame,
+    /// (256 × 16 / fc) × 2^SFGI.  SFGI 0 and the RFU 15 mean none.
+    pub fn start_up_frame_guard_time(&self) -> Duration {
+        match self.sfgi {
+            0 | 15 => Duration::ZERO,
+            sfgi => frame_time(sfgi),
+        }
+    }
+
+    /// How long to wait for one InDataExchange with this card: the FWT for
+    /// each frame of an answer filling the PN532's buffer, plus a margin,
+    /// but no more than the longest FWT and the margin.  Cards asking for
+    /// long waiting time extensions may need more.
+    pub fn response_timeout(&self) -> Duration {
+        let frames = MAX_DATA_LEN / self.max_frame_size() + 1;
+        (self.frame_waiting_time() * frames as u32 + EXCHANGE_MARGIN).min(MAX_RESPONSE_TIMEOUT)
+    }
+}
+
+/// (256 × 16 / fc) × 2^`exponent`, as FWT and SFGT are coded.
+fn frame_time(exponent: u8) -> Duration {
+    Duration::from_nanos((4096 << exponent) * 1_000_000_000 / FC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_interface_byte() {
+        // A DESFire EV1: FSCI 5, 106 to 424 kbps, FWI 8, SFGI 1, CID.
+        let ats = Ats::from_bytes(&[0x06, 0x75, 0x77, 0x81, 0x02, 0x80]).unwrap();
+        assert_eq!(ats.max_frame_size(), 64);
+        assert_eq!(ats.card_to_reader, [BitRate::Kbps106, BitRate::Kbps212, BitRate::Kbps424]);
+        assert_eq!(ats.reader_to_card, [BitRate::Kbps106, BitRate::Kbps212, BitRate::Kbps424]);
+        assert!(!ats.same_bit_rate);
+        assert_eq!((ats.fwi, ats.sfgi), (8, 1));
+        assert!(ats.cid_supported && !ats.nad_supported);
+        assert_eq!(ats.historical_bytes, [0x80]);
+        assert_eq!(ats.frame_waiting_time(), frame_time(8));
+        assert_eq!(ats.start_up_frame_guard_time(), frame_time(1));
+    }
+
+    #[test]
+    fn defaults_missing_interface_bytes() {
+        let only_tl = Ats::from_bytes(&[0x01]).unwrap();
+        assert_eq!(only_tl, Ats::with_interface_bytes(DEFAULT_FSCI, DEFAULT_TA, DEFAULT_FWI << 4, DEFAULT_TC, &[]));
+        assert_eq!(only_tl.max_frame_size(), 32);
+        assert_eq!(only_tl.card_to_reader, [BitRate::Kbps106]);
+        assert_eq!(only_tl.fwi, DEFAULT_FWI);
+        assert!(only_tl.cid_supported);
+
+        // TB only: TA and TC take their defaults.
+        let tb_only = Ats::from_bytes(&[0x04, 0x28, 0xE0, 0x42]).unwrap();
+        assert_eq!(tb_only.max_frame_size(), 256);
+        assert_eq!(tb_only.reader_to_card, [BitRate::Kbps106]);
+        assert_eq!((tb_only.fwi, tb_only.sfgi), (14, 0));
+        assert!(tb_only.cid_supported);
+        assert_eq!(tb_only.historical_bytes, [0x42]);
+
+        // TA and TC only.
+        let no_tb = Ats::from_bytes(&[0x04, 0x50, 0x91, 0x01]).unwrap();
+        assert!(no_tb.same_bit_rate);
+        assert_eq!(no_tb.card_to_reader, [BitRate::Kbps106, BitRate::Kbps212]);
+        assert_eq!(no_tb.reader_to_card, [BitRate::Kbps106, BitRate::Kbps212]);
+        assert_eq!(no_tb.fwi, DEFAULT_FWI);
+        assert!(no_tb.nad_supported && !no_tb.cid_supported);
+        assert!(no_tb.historical_bytes.is_empty());
+    }
+
+    #[test]
+    fn rejects_truncated_ats() {
+        assert!(Ats::from_bytes(&[]).is_err());
+        // TL counts more bytes than there are.
+        assert!(Ats::from_bytes(&[0x06, 0x75, 0x77, 0x81]).is_err());
+        // T0 announces TA, TB and TC, TL leaves room for two.
+        assert!(Ats::from_bytes(&[0x04, 0x70, 0x77, 0x81]).is_err());
+    }
+
+    #[test]
+    fn fwi_15_is_rfu() {
+        let ats = Ats::from_bytes(&[0x03, 0x20, 0xF0]).unwrap();
+        assert_eq!(ats.fwi, 15);
+        assert_eq!(ats.frame_waiting_time(), frame_time(DEFAULT_FWI));
+    }
+
+    #[test]
+    fn response_timeout_is_capped() {
+        // FWI 4 and FSC 256: 2 frames of about 4.8 ms.
+        let short = Ats::from_bytes(&[0x03, 0x28, 0x40]).unwrap();
+        assert_eq!(short.response_timeout(), frame_time(4) * 2 + EXCHANGE_MARGIN);
+
+        // FWI 14 and FSC 16 would wait the 5 s FWT for 17 frames.
+        let long = Ats::from_bytes(&[0x03, 0x20, 0xE0]).unwrap();
+        assert_eq!(long.response_timeout(), MAX_RESPONSE_TIMEOUT);
+        assert!(frame_time(14) + EXCHANGE_MARGIN <= MAX_RESPONSE_TIMEOUT);
+    }
+}
@@ -0,0 +1,32 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Handle to cancel what a reader is waiting for from another thread, e.g.
+/// a [`PN532::read_passive_target`](crate::pn532::PN532::read_passive_target)
+/// with a long timeout on shutdown.
+///
+/// A cancel takes effect at the reader's next check, within some 10 ms: the
+/// wait in progress, or the next one if the reader is not waiting.  The
+/// command is aborted with an ACK frame and fails with
+/// [`Error::Cancelled`](crate::pn532::Error::Cancelled).
+#[derive(Debug, Clone, Default)]
+pub struct Canceller {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Canceller {
+    /// Cancel the current or next wait of the reader.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+
+    /// Whether a cancel is pending.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Consume a pending cancel, returning whether there was one.
+    pub(crate) fn take(&self) -> bool {
+        self.cancelled.swap(false, Ordering::AcqRel)
+    }
+}
@@ -0,0 +1,329 @@
+//! Recording of the frames exchanged with a PN532, to attach to bug reports
+//! and replay offline with [`Replay`].
+//!
+//! A capture file starts with the magic `PN532CAP` and a version byte (1),
+//! followed by one record per transfer:
+//!
+//! | bytes | content                                          |
+//! |-------|--------------------------------------------------|
+//! | 1     | direction: 0 host to PN532, 1 PN532 to host      |
+//! | 8     | time since the Unix epoch in µs, little endian   |
+//! | 2     | length of the data, little endian                |
+//! | n     | data: the frame written, or the bytes read       |
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::pn532::{AuthCache, Error, GpioCache, Hooks, Options, Result, Stats, Transport, PN532};
+
+const MAGIC: &[u8; 8] = b"PN532CAP";
+const VERSION: u8 = 1;
+
+/// Which way a captured transfer went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// A frame written by the host.
+    HostToPn532,
+    /// Bytes read from the PN532.
+    Pn532ToHost,
+}
+
+/// One transfer of a capture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureRecord {
+    /// Time since the Unix epoch.
+    pub timestamp: Duration,
+    pub direction: Direction,
+    pub data: Vec<u8>,
+}
+
+/// Writes the frames exchanged with a reader to a capture file.
+pub struct Capture {
+    writer: Box<dyn Write + Send>,
+}
+
+impl Capture {
+    /// Create (or truncate) the capture file at `path`.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Capture to `writer`.
+    pub fn new(writer: impl Write + Send + 'static) -> Result<Self> {
+        let mut writer: Box<dyn Write + Send> = Box::new(writer);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.flush()?;
+
+        Ok(Self { writer })
+    }
+
+    /// Append a record of `data` going in `direction`.  Records are flushed
+    /// right away so a capture survives a crash.
+    pub fn record(&mut self, direction: Direction, data: &[u8]) -> Result<()> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let direction = match direction {
+            Direction::HostToPn532 => 0,
+            Direction::Pn532ToHost => 1,
+        };
+        let len = u16::try_from(data.len()).unwrap_or(u16::MAX);
+
+        self.writer.write_all(&[direction])?;
+        self.writer.write_all(&(timestamp.as_micros() as u64).to_le_bytes())?;
+        self.writer.write_all(&len.to_le_bytes())?;
+        self.writer.write_all(&data[..len as usize])?;
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Read back all the records of a capture.
+pub fn read_capture(mut reader: impl Read) -> Result<Vec<CaptureRecord>> {
+    let mut header = [0; 9];
+    reader.read_exact(&mut header)?;
+    if &header[..8] != MAGIC || header[8] != VERSION {
+        return Err(invalid("not a version 1 PN532 capture").into());
+    }
+
+    let mut records = Vec::new();
+    loop {
+        let mut direction = [0; 1];
+        match reader.read_exact(&mut direction) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(records),
+            result => result?,
+        }
+        let direction = match direction[0] {
+            0 => Direction::HostToPn532,
+            1 => Direction::Pn532ToHost,
+            _ => return Err(invalid("invalid record direction").into()),
+        };
+        let mut timestamp = [0; 8];
+        reader.read_exact(&mut timestamp)?;
+        let mut len = [0; 2];
+        reader.read_exact(&mut len)?;
+        let mut data = vec![0; u16::from_le_bytes(len) as usize];
+        reader.read_exact(&mut data)?;
+
+        records.push(CaptureRecord {
+            timestamp: Duration::from_micros(u64::from_le_bytes(timestamp)),
+            direction,
+            data,
+        });
+    }
+}
+
+/// A reader replaying a capture instead of talking to a PN532: the bytes
+/// the PN532 sent are read back in order, and every frame written must be
+/// the one the host wrote when capturing.  Running the code of a bug report
+/// against it reproduces the exchange offline.
+///
+/// ```no_run
+/// # fn run() -> pn532_nfc::Result<()> {
+/// use pn532_nfc::pn532::capture::Replay;
+/// use pn532_nfc::PN532;
+///
+/// let mut pn532 = Replay::open("pn532.cap")?;
+/// println!("{}", pn532.get_firmware_version()?);
+/// assert!(pn532.is_finished());
+/// # Ok(())
+/// # }
+/// ```
+pub struct Replay {
+    options: Options,
+    auth_cache: AuthCache,
+    gpio_cache: GpioCache,
+    hooks: Hooks,
+    stats: Stats,
+    records: VecDeque<CaptureRecord>,
+}
+
+impl Replay {
+    /// Replay `records`, as [`read_capture`] returns them.
+    pub fn new(records: Vec<CaptureRecord>) -> Self {
+        Self {
+            options: Options::default(),
+            auth_cache: AuthCache::default(),
+            gpio_cache: GpioCache::default(),
+            hooks: Hooks::default(),
+            stats: Stats::default(),
+            records: records.into(),
+        }
+    }
+
+    /// Replay the capture file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(read_capture(BufReader::new(File::open(path)?))?))
+    }
+
+    /// Replay with `options` instead of the defaults, e.g. the ones of the
+    /// captured reader.
+    pub fn with_options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Whether every record was replayed.
+    pub fn is_finished(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    fn next_is_output(&self) -> bool {
+        self.records.front().is_some_and(|record| record.direction == Direction::Pn532ToHost)
+    }
+}
+
+impl Transport for Replay {
+    fn gpio_init(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn reset(&mut self, _pin: u8) -> Result<()> {
+        Ok(())
+    }
+
+    /// Read the next output of the PN532.  Where the capture has none, the
+    /// PN532 never answered.
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if !self.next_is_output() {
+            return Err(Error::Timeout);
+        }
+        let Some(record) = self.records.pop_front() else {
+            return Err(Error::Timeout);
+        };
+        let len = record.data.len().min(buf.len());
+        buf[..len].copy_from_slice(&record.data[..len]);
+        Ok(len)
+    }
+
+    /// Check `frame` is the next one the host wrote.
+    fn write_data(&mut self, frame: &[u8]) -> Result<()> {
+        match self.records.pop_front() {
+            Some(record) if record.direction == Direction::HostToPn532 && record.data == frame => Ok(()),
+            Some(record) => Err(Error::InvalidArgument(format!("wrote {:02X?}, the capture has {:?} {:02X?}", frame, record.direction, record.data))),
+            None => Err(Error::InvalidArgument(format!("wrote {:02X?} past the end of the capture", frame))),
+        }
+    }
+
+    /// The PN532 is ready when the capture has output next.
+    fn wait_ready(&mut self, _timeout: Duration) -> Result<bool> {
+        Ok(self.next_is_output())
+    }
+
+    fn wake_up(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl PN532 for Replay {
+    fn options(&self) -> &Options {
+        &self.options
+    }
+
+    fn auth_cache(&mut self) -> &mut AuthCache {
+        &mut self.auth_cache
+    }
+
+    fn gpio_cache(&mut self) -> &mut GpioCache {
+        &mut self.gpio_cache
+    }
+
+    fn hooks(&mut self) -> &mut Hooks {
+        &mut self.hooks
+    }
+
+    fn stats_mut(&mut self) -> &mut Stats {
+        &mut self.stats
+    }
+}
+
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::pn532::frame::ACK;
+
+    // GetFirmwareVersion and its answer, from UM0701-02.
+    const GET_FIRMWARE_VERSION: &[u8] = &[0x00, 0x00, 0xFF, 0x02, 0xFE, 0xD4, 0x02, 0x2A, 0x00];
+    const FIRMWARE_VERSION: &[u8] = &[0x00, 0x00, 0xFF, 0x06, 0xFA, 0xD5, 0x03, 0x32, 0x01, 0x06, 0x07, 0xE8, 0x00];
+
+    /// A writer whose bytes stay readable after the capture took it.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn capture_firmware_version() -> Vec<u8> {
+        let file = Shared::default();
+        let mut capture = Capture::new(file.clone()).unwrap();
+        capture.record(Direction::HostToPn532, GET_FIRMWARE_VERSION).unwrap();
+        capture.record(Direction::Pn532ToHost, ACK).unwrap();
+        capture.record(Direction::Pn532ToHost, FIRMWARE_VERSION).unwrap();
+        let bytes = file.0.lock().unwrap().clone();
+        bytes
+    }
+
+    #[test]
+    fn reads_back_records() {
+        let bytes = capture_firmware_version();
+        assert_eq!(&bytes[..9], b"PN532CAP\x01");
+
+        let records = read_capture(&bytes[..]).unwrap();
+        let transfers: Vec<_> = records.iter().map(|record| (record.direction, record.data.as_slice())).collect();
+        assert_eq!(transfers, [(Direction::HostToPn532, GET_FIRMWARE_VERSION), (Direction::Pn532ToHost, ACK), (Direction::Pn532ToHost, FIRMWARE_VERSION)]);
+        assert!(records.windows(2).all(|pair| pair[0].timestamp <= pair[1].timestamp));
+        assert!(records[0].timestamp > Duration::ZERO);
+    }
+
+    #[test]
+    fn rejects_bad_captures() {
+        let bytes = capture_firmware_version();
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(matches!(read_capture(&bad_magic[..]), Err(Error::Io(e)) if e.kind() == io::ErrorKind::InvalidData));
+        let mut bad_version = bytes.clone();
+        bad_version[8] = 2;
+        assert!(matches!(read_capture(&bad_version[..]), Err(Error::Io(e)) if e.kind() == io::ErrorKind::InvalidData));
+
+        // The direction of the first record follows the header.
+        let mut bad_direction = bytes.clone();
+        bad_direction[9] = 2;
+        assert!(matches!(read_capture(&bad_direction[..]), Err(Error::Io(e)) if e.kind() == io::ErrorKind::InvalidData));
+
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(matches!(read_capture(truncated), Err(Error::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof));
+    }
+
+    #[test]
+    fn replays_a_capture() {
+        let mut pn532 = Replay::new(read_capture(&capture_firmware_version()[..]).unwrap());
+        let version = pn532.get_firmware_version().unwrap();
+        assert_eq!((version.ic, version.ver, version.rev, version.support), (0x32, 0x01, 0x06, 0x07));
+        assert!(pn532.is_finished());
+    }
+
+    #[test]
+    fn replay_rejects_other_frames() {
+        let mut pn532 = Replay::new(read_capture(&capture_firmware_version()[..]).unwrap());
+        assert!(pn532.get_general_status().is_err());
+        assert!(!pn532.is_finished());
+    }
+}
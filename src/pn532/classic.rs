@@ -0,0 +1,554 @@
+//! MIFARE Classic sector trailers and authentication state.
+
+use std::collections::HashMap;
+
+use crate::pn532::{Error, Result, Sector, Uid, MIFARE_CMD_AUTH_A, MIFARE_CMD_AUTH_B};
+
+/// The two keys stored in a sector trailer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectorKeys {
+    pub key_a: [u8; 6],
+    pub key_b: [u8; 6],
+}
+
+/// Which of the two sector keys to authenticate with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum KeyType {
+    A,
+    B,
+}
+
+impl KeyType {
+    /// The authentication command, [`MIFARE_CMD_AUTH_A`] or
+    /// [`MIFARE_CMD_AUTH_B`].
+    pub fn auth_command(self) -> u8 {
+        match self {
+            KeyType::A => MIFARE_CMD_AUTH_A,
+            KeyType::B => MIFARE_CMD_AUTH_B,
+        }
+    }
+}
+
+/// A key and whether it is key A or B of its sector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SectorKey {
+    pub key_type: KeyType,
+    pub key: [u8; 6],
+}
+
+impl SectorKey {
+    pub fn a(key: [u8; 6]) -> Self {
+        Self { key_type: KeyType::A, key }
+    }
+
+    pub fn b(key: [u8; 6]) -> Self {
+        Self { key_type: KeyType::B, key }
+    }
+}
+
+/// The keys to authenticate each sector of a card with, tried in order:
+/// the same for every sector, with exceptions for some.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyMap {
+    default: Vec<SectorKey>,
+    sectors: HashMap<Sector, Vec<SectorKey>>,
+}
+
+impl KeyMap {
+    /// `key` for every sector.
+    pub fn new(key: SectorKey) -> Self {
+        Self { default: vec![key], sectors: HashMap::new() }
+    }
+
+    /// Key A, then key B of `keys` for every sector.
+    pub fn from_keys(keys: &SectorKeys) -> Self {
+        Self { default: vec![SectorKey::a(keys.key_a), SectorKey::b(keys.key_b)], sectors: HashMap::new() }
+    }
+
+    /// Also try `key` for the sectors without exceptions, after the others.
+    pub fn or_key(mut self, key: SectorKey) -> Self {
+        self.default.push(key);
+        self
+    }
+
+    /// Use `key` for `sector` instead.
+    pub fn with_sector(mut self, sector: Sector, key: SectorKey) -> Self {
+        self.insert(sector, key);
+        self
+    }
+
+    /// Use key A, then key B of `keys` for `sector` instead.
+    pub fn with_sector_keys(mut self, sector: Sector, keys: &SectorKeys) -> Self {
+        self.sectors.insert(sector, vec![SectorKey::a(keys.key_a), SectorKey::b(keys.key_b)]);
+        self
+    }
+
+    /// Use `key` for `sector` instead, returning the first key it replaces.
+    pub fn insert(&mut self, sector: Sector, key: SectorKey) -> Option<SectorKey> {
+        self.sectors.insert(sector, vec![key]).and_then(|keys| keys.first().copied())
+    }
+
+    /// The first key for `sector`, if any.
+    pub fn get(&self, sector: Sector) -> Option<SectorKey> {
+        self.keys(sector).first().copied()
+    }
+
+    /// The keys for `sector`, in the order to try them.
+    pub fn keys(&self, sector: Sector) -> &[SectorKey] {
+        self.sectors.get(&sector).unwrap_or(&self.default)
+    }
+}
+
+/// Check the integrity of the access bytes (bytes 6-8 of a sector trailer):
+/// every access bit C1-C3 must also be stored inverted. A trailer failing
+/// this check blocks the whole sector irreversibly.
+pub fn validate_access_bits(access: [u8; 3]) -> Result<()> {
+    let [b6, b7, b8] = access;
+    let c1_ok = b7 >> 4 == !b6 & 0x0F;
+    let c2_ok = b8 & 0x0F == !b6 >> 4;
+    let c3_ok = b8 >> 4 == !b7 & 0x0F;
+    if c1_ok && c2_ok && c3_ok {
+        Ok(())
+    } else {
+        Err(Error::InvalidArgument(format!("access bytes {:02X?} are not a valid encoding", access)))
+    }
+}
+
+/// Access conditions of a data block, or a group of 5 in the large sectors
+/// of a 4K card.  Key B only counts where it is not readable from the
+/// trailer; where it is, only key A is accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DataAccess {
+    /// Read, write, increment and decrement with key A or B (transport
+    /// configuration).
+    #[default]
+    Open,
+    /// Read with key A or B, nothing else.
+    ReadOnly,
+    /// Read with key A or B, write with key B.
+    WriteKeyB,
+    /// Value block: read and decrement with key A or B, write and increment
+    /// with key B.
+    ValueKeyB,
+    /// Value block: read and decrement with key A or B, nothing else.
+    ValueDecrementOnly,
+    /// Read and write with key B.
+    KeyBOnly,
+    /// Read with key B, nothing else.
+    ReadKeyB,
+    /// Nothing.
+    Locked,
+}
+
+impl DataAccess {
+    /// The access bits C1 C2 C3, as bits 2, 1 and 0.
+    pub fn bits(self) -> u8 {
+        match self {
+            DataAccess::Open => 0b000,
+            DataAccess::ReadOnly => 0b010,
+            DataAccess::WriteKeyB => 0b100,
+            DataAccess::ValueKeyB => 0b110,
+            DataAccess::ValueDecrementOnly => 0b001,
+            DataAccess::KeyBOnly => 0b011,
+            DataAccess::ReadKeyB => 0b101,
+            DataAccess::Locked => 0b111,
+        }
+    }
+
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b111 {
+            0b000 => DataAccess::Open,
+            0b010 => DataAccess::ReadOnly,
+            0b100 => DataAccess::WriteKeyB,
+            0b110 => DataAccess::ValueKeyB,
+            0b001 => DataAccess::ValueDecrementOnly,
+            0b011 => DataAccess::KeyBOnly,
+            0b101 => DataAccess::ReadKeyB,
+            _ => DataAccess::Locked,
+        }
+    }
+}
+
+/// Access conditions of the sector trailer: who may write key A, read and
+/// write the access bits, and read and write key B.  Key A is never
+/// readable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TrailerAccess {
+    /// Key A writes key A, key B and the access bits, and reads key B
+    /// (transport configuration).
+    #[default]
+    Transport,
+    /// Key A writes both keys and reads the access bits and key B; the
+    /// access bits are frozen.
+    KeyAWritesKeys,
+    /// Key A reads the access bits and key B, nothing is writable.
+    KeyAReadOnly,
+    /// Key B writes both keys and the access bits.
+    KeyBWritesAll,
+    /// Key B writes both keys; the access bits are frozen.
+    KeyBWritesKeys,
+    /// Key B writes the access bits; the keys are frozen.
+    KeyBWritesAccess,
+    /// Nothing is writable.
+    Locked,
+}
+
+impl TrailerAccess {
+    /// The access bits C1 C2 C3, as bits 2, 1 and 0.
+    pub fn bits(self) -> u8 {
+        match self {
+            TrailerAccess::KeyAWritesKeys => 0b000,
+            TrailerAccess::KeyAReadOnly => 0b010,
+            TrailerAccess::KeyBWritesKeys => 0b100,
+            TrailerAccess::Locked => 0b110,
+            TrailerAccess::Transport => 0b001,
+            TrailerAccess::KeyBWritesAll => 0b011,
+            TrailerAccess::KeyBWritesAccess => 0b101,
+        }
+    }
+
+    /// 0b111 locks the trailer like 0b110 and has no name of its own.
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b111 {
+            0b000 => TrailerAccess::KeyAWritesKeys,
+            0b010 => TrailerAccess::KeyAReadOnly,
+            0b100 => TrailerAccess::KeyBWritesKeys,
+            0b001 => TrailerAccess::Transport,
+            0b011 => TrailerAccess::KeyBWritesAll,
+            0b101 => TrailerAccess::KeyBWritesAccess,
+            _ => TrailerAccess::Locked,
+        }
+    }
+}
+
+/// The access conditions of a sector, stored in bytes 6-8 of its trailer.
+/// The default is the transport configuration, `FF 07 80`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AccessBits {
+    /// Data blocks 0 to 2 of the sector, or groups of 5 blocks in the large
+    /// sectors of a 4K card.
+    pub blocks: [DataAccess; 3],
+    pub trailer: TrailerAccess,
+}
+
+impl AccessBits {
+    /// Bytes 6-8 of the trailer: each access bit is stored plainly and
+    /// inverted.
+    pub fn encode(&self) -> [u8; 3] {
+        let bits = [self.blocks[0].bits(), self.blocks[1].bits(), self.blocks[2].bits(), self.trailer.bits()];
+        // Nibble of Cn, bit i for block i.
+        let nibble = |shift: u8| (0..4).fold(0u8, |nibble, i| nibble | ((bits[i] >> shift) & 1) << i);
+        let (c1, c2, c3) = (nibble(2), nibble(1), nibble(0));
+
+        [(!c2 & 0x0F) << 4 | (!c1 & 0x0F), c1 << 4 | (!c3 & 0x0F), c3 << 4 | c2]
+    }
+
+    /// Decode bytes 6-8 of a trailer, checking the inverted copies of the
+    /// bits match.  Access bits 0b111 of the trailer decode to
+    /// [`TrailerAccess::Locked`], like 0b110, so encoding them back changes
+    /// the bytes but not the conditions.
+    pub fn decode(access: [u8; 3]) -> Result<Self> {
+        validate_access_bits(access)?;
+        let [_, b7, b8] = access;
+        let (c1, c2, c3) = (b7 >> 4, b8 & 0x0F, b8 >> 4);
+        let bits = |i: u8| ((c1 >> i) & 1) << 2 | ((c2 >> i) & 1) << 1 | ((c3 >> i) & 1);
+
+        Ok(Self {
+            blocks: [DataAccess::from_bits(bits(0)), DataAccess::from_bits(bits(1)), DataAccess::from_bits(bits(2))],
+            trailer: TrailerAccess::from_bits(bits(3)),
+        })
+    }
+
+    /// Bytes 6-9 of the trailer, as [`PN532::write_sector_trailer`] takes
+    /// them, with general purpose byte `gpb`.
+    ///
+    /// [`PN532::write_sector_trailer`]: crate::pn532::PN532::write_sector_trailer
+    pub fn trailer_bytes(&self, gpb: u8) -> [u8; 4] {
+        let [b6, b7, b8] = self.encode();
+        [b6, b7, b8, gpb]
+    }
+}
+
+/// HLTA, halting the card before the gen1a backdoor.
+pub(crate) const CMD_HALT: [u8; 2] = [0x50, 0x00];
+/// The gen1a backdoor: a 7 bit 0x40, then 0x43, both without CRC.
+pub(crate) const CMD_GEN1A_UNLOCK1: u8 = 0x40;
+pub(crate) const CMD_GEN1A_UNLOCK2: u8 = 0x43;
+/// The 4 bit ACK of the card.
+const ACK: u8 = 0x0A;
+
+/// Check block 0 of a card with a `uid_len` byte UID.  With a 4 byte UID,
+/// its fifth byte, the BCC, must be the XOR of the UID, or the card can no
+/// longer be selected.  Block 0 of a 7 byte UID card has no BCC, the SAK
+/// and ATQA follow the UID.
+pub fn validate_manufacturer_block(block: &[u8; 16], uid_len: usize) -> Result<()> {
+    match uid_len {
+        4 => {}
+        7 | 10 => return Ok(()),
+        _ => return Err(Error::InvalidArgument(format!("UID must be 4, 7 or 10 bytes long, got {}", uid_len))),
+    }
+    let bcc = block[..4].iter().fold(0, |bcc, byte| bcc ^ byte);
+    if block[4] == bcc {
+        Ok(())
+    } else {
+        Err(Error::InvalidArgument(format!("BCC of UID {:02X?} is {:#04x}, got {:#04x}", &block[..4], bcc, block[4])))
+    }
+}
+
+/// Check the raw answer of a magic card is the 4 bit ACK.
+pub(crate) fn check_ack(answer: &[u8]) -> Result<()> {
+    match answer.first() {
+        Some(&ack) if ack & 0x0F == ACK => Ok(()),
+        _ => Err(Error::UnexpectedResponse("magic card did not ACK!")),
+    }
+}
+
+/// Build the 16 bytes of a sector trailer.
+pub(crate) fn sector_trailer(keys: &SectorKeys, access_bits: [u8; 4]) -> Result<[u8; 16]> {
+    validate_access_bits([access_bits[0], access_bits[1], access_bits[2]])?;
+
+    let mut trailer = [0; 16];
+    trailer[..6].copy_from_slice(&keys.key_a);
+    trailer[6..10].copy_from_slice(&access_bits);
+    trailer[10..].copy_from_slice(&keys.key_b);
+
+    Ok(trailer)
+}
+
+/// The sector a MIFARE Classic card is currently authenticated for, so that
+/// reading or writing a whole sector authenticates only once.
+///
+/// The card drops its authentication when another sector is authenticated,
+/// on any failed command and when it is deselected; the driver forgets the
+/// cached state in all those cases.
+///
+/// It also holds the logical number of the selected target, which the card
+/// operations address, and whether that target is still active.
+#[derive(Debug, Clone)]
+pub struct AuthCache {
+    authenticated: Option<Authentication>,
+    target: u8,
+    active: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Authentication {
+    uid: Uid,
+    sector: Sector,
+    key_number: u8,
+    key: [u8; 6],
+}
+
+impl AuthCache {
+    /// Whether `sector` of card `uid` is authenticated with this key.
+    pub(crate) fn is_authenticated(&self, uid: &Uid, sector: Sector, key_number: u8, key: &[u8]) -> bool {
+        self.authenticated.as_ref().is_some_and(|auth| {
+            auth.uid == *uid && auth.sector == sector && auth.key_number == key_number && auth.key == key
+        })
+    }
+
+    /// Remember a successful authentication, replacing the previous one.
+    pub(crate) fn record(&mut self, uid: &Uid, sector: Sector, key_number: u8, key: &[u8]) {
+        self.authenticated = <[u8; 6]>::try_from(key).ok().map(|key| Authentication {
+            uid: *uid,
+            sector,
+            key_number,
+            key,
+        });
+    }
+
+    /// Forget the authenticated sector.
+    pub fn clear(&mut self) {
+        self.authenticated = None;
+    }
+
+    /// Logical number of the selected target.
+    pub fn target(&self) -> u8 {
+        self.target
+    }
+
+    /// Whether the selected target is active, i.e. was activated or
+    /// selected and not deselected or released since.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub(crate) fn select(&mut self, target: u8) {
+        self.target = target;
+        self.active = true;
+    }
+
+    /// Note that `target`, or all targets if 0, was deselected or released.
+    pub(crate) fn deactivate(&mut self, target: u8) {
+        if target == 0x00 || target == self.target {
+            self.authenticated = None;
+            self.active = false;
+        }
+    }
+}
+
+impl Default for AuthCache {
+    fn default() -> Self {
+        Self { authenticated: None, target: 0x01, active: false }
+    }
+}
+
+/// A sector of a [`ClassicDump`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectorDump {
+    pub sector: Sector,
+    /// The key the sector was authenticated with, `None` if none of the
+    /// keys worked.
+    pub key: Option<SectorKey>,
+    /// Every block of the sector, trailer included, `None` where the access
+    /// conditions refused the read.  Key A reads as zeros, and so does key B
+    /// where it is not readable.
+    pub blocks: Vec<Option<[u8; 16]>>,
+    /// The access conditions, if the trailer was read and holds a valid
+    /// encoding.
+    pub access: Option<AccessBits>,
+}
+
+impl SectorDump {
+    /// Whether the sector could not be authenticated at all.
+    pub fn is_unreadable(&self) -> bool {
+        self.key.is_none()
+    }
+
+    /// Whether every block was read.
+    pub fn is_complete(&self) -> bool {
+        !self.blocks.is_empty() && self.blocks.iter().all(Option::is_some)
+    }
+
+    /// The trailer to write back, with the keys the read masked: key A is
+    /// the one the sector was authenticated with, key B must be readable.
+    /// `None` if either key is unknown.
+    pub(crate) fn restored_trailer(&self) -> Option<[u8; 16]> {
+        let (Some(key), Some(access), Some(Some(trailer))) = (self.key, self.access, self.blocks.last()) else {
+            return None;
+        };
+        let key_b_readable = matches!(access.trailer,
+            TrailerAccess::Transport | TrailerAccess::KeyAWritesKeys | TrailerAccess::KeyAReadOnly);
+        if key.key_type != KeyType::A || !key_b_readable {
+            return None;
+        }
+
+        let mut trailer = *trailer;
+        trailer[..6].copy_from_slice(&key.key);
+        Some(trailer)
+    }
+}
+
+/// Every sector of a MIFARE Classic card, read by
+/// [`PN532::dump_classic`](crate::pn532::PN532::dump_classic).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassicDump {
+    pub uid: Uid,
+    pub sectors: Vec<SectorDump>,
+}
+
+impl ClassicDump {
+    /// The sectors none of the keys opened.
+    pub fn unreadable_sectors(&self) -> impl Iterator<Item = Sector> + '_ {
+        self.sectors.iter().filter(|sector| sector.is_unreadable()).map(|sector| sector.sector)
+    }
+
+    /// Whether every block of every sector was read.
+    pub fn is_complete(&self) -> bool {
+        self.sectors.iter().all(SectorDump::is_complete)
+    }
+}
+
+/// What [`PN532::detect_magic`](crate::pn532::PN532::detect_magic) found out
+/// about a card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MagicCard {
+    /// The card answers the gen1a backdoor.
+    pub gen1a: bool,
+    /// The card accepts block 0 writes, `None` if that was not probed.
+    pub gen2: Option<bool>,
+}
+
+impl MagicCard {
+    /// Whether the card is a clone, as far as probed.
+    pub fn is_magic(&self) -> bool {
+        self.gen1a || self.gen2 == Some(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn access_bits_round_trip() {
+        for position in 0..4 {
+            for bits in 0..8 {
+                let mut access = AccessBits::default();
+                match position {
+                    3 => access.trailer = TrailerAccess::from_bits(bits),
+                    block => access.blocks[block] = DataAccess::from_bits(bits),
+                }
+                let encoded = access.encode();
+                validate_access_bits(encoded).unwrap();
+                assert_eq!(AccessBits::decode(encoded).unwrap(), access, "bits {:03b} of position {}", bits, position);
+            }
+        }
+    }
+
+    #[test]
+    fn access_bits_of_every_condition() {
+        for bits in 0..8 {
+            assert_eq!(DataAccess::from_bits(bits).bits(), bits);
+        }
+        for bits in 0..7 {
+            assert_eq!(TrailerAccess::from_bits(bits).bits(), bits);
+        }
+        // 0b111 locks the trailer like 0b110.
+        assert_eq!(TrailerAccess::from_bits(0b111), TrailerAccess::Locked);
+    }
+
+    #[test]
+    fn transport_access_bits() {
+        let transport = [0xFF, 0x07, 0x80];
+        assert_eq!(AccessBits::default().encode(), transport);
+        assert_eq!(AccessBits::decode(transport).unwrap(), AccessBits::default());
+        assert_eq!(AccessBits::default().trailer_bytes(0x69), [0xFF, 0x07, 0x80, 0x69]);
+    }
+
+    #[test]
+    fn checks_the_bcc_of_4_byte_uids() {
+        let mut block = [0; 16];
+        block[..8].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF, 0x22, 0x08, 0x04, 0x00]);
+        validate_manufacturer_block(&block, 4).unwrap();
+        block[4] = 0x23;
+        assert!(validate_manufacturer_block(&block, 4).is_err());
+    }
+
+    #[test]
+    fn accepts_block_0_of_7_byte_uids() {
+        // UID, SAK and ATQA of a MIFARE Classic EV1 with a 7 byte UID: the
+        // fifth byte is UID, not a BCC.
+        let block = [0x04, 0x51, 0x8C, 0xA2, 0x98, 0x5C, 0x80, 0x08, 0x44, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        validate_manufacturer_block(&block, 7).unwrap();
+        assert!(validate_manufacturer_block(&block, 4).is_err());
+        assert!(validate_manufacturer_block(&block, 5).is_err());
+    }
+
+    #[test]
+    fn rejects_mismatched_inverted_bits() {
+        for byte in 0..3 {
+            for bit in 0..8 {
+                let mut access = [0xFF, 0x07, 0x80];
+                access[byte] ^= 1 << bit;
+                assert!(validate_access_bits(access).is_err(), "{:02X?}", access);
+                assert!(AccessBits::decode(access).is_err(), "{:02X?}", access);
+            }
+        }
+    }
+}
@@ -0,0 +1,1833 @@
+//! Typed PN532 commands.
+//!
+//! Each request type knows its command code, how to lay out its parameters
+//! and how to parse the response, so a wrong parameter layout is a compile
+//! error rather than a confused chip.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::pn532::frame::MAX_DATA_LEN;
+use crate::pn532::{DepTarget, Error, Result, Status};
+
+/// Largest response a frame can carry after the response code: the data of
+/// an extended information frame minus TFI and response code.
+pub(crate) const MAX_RESPONSE_LEN: usize = MAX_DATA_LEN - 2;
+
+/// PN532 command codes (UM0701-02 §7).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Command {
+    Diagnose =              0x00,
+    GetFirmwareVersion =    0x02,
+    GetGeneralStatus =      0x04,
+    ReadRegister =          0x06,
+    WriteRegister =         0x08,
+    ReadGpio =              0x0C,
+    WriteGpio =             0x0E,
+    SetSerialBaudRate =     0x10,
+    SetParameters =         0x12,
+    SamConfiguration =      0x14,
+    PowerDown =             0x16,
+    RfConfiguration =       0x32,
+    RfRegulationTest =      0x58,
+    InJumpForDep =          0x56,
+    InJumpForPsl =          0x46,
+    InListPassiveTarget =   0x4A,
+    InAtr =                 0x50,
+    InPsl =                 0x4E,
+    InDataExchange =        0x40,
+    InCommunicateThru =     0x42,
+    InDeselect =            0x44,
+    InRelease =             0x52,
+    InSelect =              0x54,
+    InAutoPoll =            0x60,
+    TgInitAsTarget =        0x8C,
+    TgSetGeneralBytes =     0x92,
+    TgGetData =             0x86,
+    TgSetData =             0x8E,
+    TgSetMetaData =         0x94,
+    TgGetInitiatorCommand = 0x88,
+    TgResponseToInitiator = 0x90,
+    TgGetTargetStatus =     0x8A,
+}
+
+impl Command {
+    const ALL: [Command; 32] = [
+        Command::Diagnose, Command::GetFirmwareVersion, Command::GetGeneralStatus,
+        Command::ReadRegister, Command::WriteRegister, Command::ReadGpio, Command::WriteGpio,
+        Command::SetSerialBaudRate, Command::SetParameters, Command::SamConfiguration,
+        Command::PowerDown, Command::RfConfiguration, Command::RfRegulationTest,
+        Command::InJumpForDep, Command::InJumpForPsl, Command::InListPassiveTarget,
+        Command::InAtr, Command::InPsl, Command::InDataExchange, Command::InCommunicateThru,
+        Command::InDeselect, Command::InRelease, Command::InSelect, Command::InAutoPoll,
+        Command::TgInitAsTarget, Command::TgSetGeneralBytes, Command::TgGetData,
+        Command::TgSetData, Command::TgSetMetaData, Command::TgGetInitiatorCommand,
+        Command::TgResponseToInitiator, Command::TgGetTargetStatus,
+    ];
+
+    /// The command code sent to the PN532.
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// The code the PN532 answers this command with.
+    pub fn response_code(self) -> u8 {
+        self as u8 + 1
+    }
+
+    /// Maximum number of response bytes following the response code, as
+    /// documented for each command in UM0701-02.
+    pub fn max_response_len(self) -> usize {
+        match self {
+            Command::GetFirmwareVersion => 4,
+            // Err, Field, NbTg, two 4 byte target descriptions and SAM status.
+            Command::GetGeneralStatus => 12,
+            Command::ReadGpio => 3,
+            // Status, Tg, NFCID3t, DIDt, BSt, BRt, TO, PPt and up to 48 general bytes.
+            Command::InJumpForDep | Command::InJumpForPsl => 65,
+            // Status and ATR_RES.
+            Command::InAtr => 64,
+            // Status, BRit.
+            Command::TgGetTargetStatus => 2,
+            Command::PowerDown | Command::InPsl | Command::InDeselect | Command::InRelease |
+            Command::InSelect | Command::TgSetGeneralBytes | Command::TgSetData |
+            Command::TgSetMetaData | Command::TgResponseToInitiator => 1,
+            Command::WriteRegister | Command::WriteGpio | Command::SetSerialBaudRate |
+            Command::SetParameters | Command::SamConfiguration | Command::RfConfiguration |
+            Command::RfRegulationTest => 0,
+            Command::Diagnose | Command::ReadRegister | Command::InListPassiveTarget |
+            Command::InDataExchange | Command::InCommunicateThru | Command::InAutoPoll |
+            Command::TgInitAsTarget | Command::TgGetData |
+            Command::TgGetInitiatorCommand => MAX_RESPONSE_LEN,
+        }
+    }
+
+    /// Look up the command with the given code.
+    pub fn from_code(code: u8) -> Option<Command> {
+        Self::ALL.iter().copied().find(|command| command.code() == code)
+    }
+}
+
+impl From<Command> for u8 {
+    fn from(command: Command) -> u8 {
+        command.code()
+    }
+}
+
+/// A typed PN532 command.
+pub trait Request {
+    /// The parsed response.
+    type Response;
+
+    /// The command this request is sent as.
+    const COMMAND: Command;
+
+    /// Parameter bytes following the command code.
+    fn params(&self) -> Vec<u8>;
+
+    /// Parse the response bytes following the response code.
+    fn parse(&self, response: Vec<u8>) -> Result<Self::Response>;
+}
+
+/// Version information reported by GetFirmwareVersion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FirmwareVersion {
+    /// IC version, 0x32 for the PN532.
+    pub ic: u8,
+    /// Firmware version.
+    pub ver: u8,
+    /// Firmware revision.
+    pub rev: u8,
+    /// Bit field of the supported protocols.
+    pub support: u8,
+}
+
+impl FirmwareVersion {
+    /// Whether the firmware supports ISO/IEC 14443 type A.
+    pub fn iso14443a(&self) -> bool {
+        self.support & 0x01 != 0
+    }
+
+    /// Whether the firmware supports ISO/IEC 14443 type B.
+    pub fn iso14443b(&self) -> bool {
+        self.support & 0x02 != 0
+    }
+
+    /// Whether the firmware supports ISO 18092 (NFCIP-1).
+    pub fn iso18092(&self) -> bool {
+        self.support & 0x04 != 0
+    }
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PN5{:02x} firmware {}.{}", self.ic, self.ver, self.rev)?;
+        let protocols = [
+            (self.iso14443a(), "ISO14443A"),
+            (self.iso14443b(), "ISO14443B"),
+            (self.iso18092(), "ISO18092"),
+        ];
+        let mut sep = " (";
+        for (_, name) in protocols.iter().filter(|(supported, _)| *supported) {
+            write!(f, "{}{}", sep, name)?;
+            sep = ", ";
+        }
+        if sep == ", " {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+/// A self test run by Diagnose (UM0701-02 §7.2.1).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnoseTest {
+    /// 0x00: the PN532 echoes `data`, at most 262 bytes, back to the host.
+    CommunicationLine(Vec<u8>),
+    /// 0x01: checksum of the ROM.
+    Rom,
+    /// 0x02: non destructive test of the XRAM and IDATA.
+    Ram,
+    /// 0x04: send 128 FeliCa polling requests at 212 or 424 kbps and count
+    /// the ones a target did not answer.
+    Polling(BitRate),
+    /// 0x05: act as a target echoing every frame received after
+    /// `reply_delay` steps of 0.5 ms, with the given CIU_TxMode and
+    /// CIU_RxMode register values.  The test runs until the next command.
+    EchoBack { reply_delay: u8, tx_mode: u8, rx_mode: u8 },
+    /// 0x06: check the selected DEP target or ISO/IEC 14443-4 card is still
+    /// in the field.
+    Attention,
+    /// 0x07: check the continuity of the antenna's transmission paths, with
+    /// the detection `threshold` written to the Andet_Control register
+    /// (`andet_bot`, `andet_up`, `andet_ithl[1:0]`, `andet_ithh[1:0]`,
+    /// `andet_en` from bit 7 down).
+    Antenna { threshold: u8 },
+}
+
+impl DiagnoseTest {
+    /// The NumTst byte sent to the PN532.
+    pub fn code(&self) -> u8 {
+        match self {
+            DiagnoseTest::CommunicationLine(_) => 0x00,
+            DiagnoseTest::Rom => 0x01,
+            DiagnoseTest::Ram => 0x02,
+            DiagnoseTest::Polling(_) => 0x04,
+            DiagnoseTest::EchoBack { .. } => 0x05,
+            DiagnoseTest::Attention => 0x06,
+            DiagnoseTest::Antenna { .. } => 0x07,
+        }
+    }
+}
+
+/// Outcome of a [`DiagnoseTest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DiagnoseResult {
+    Passed,
+    /// The test failed with this result byte: 0xFF for the ROM and RAM
+    /// tests and a wrong echo, the status of the exchange for the attention
+    /// test.
+    Failed(u8),
+    /// Number of the 128 polling requests that got no answer.
+    PollingFailures(u8),
+    /// The echo back test is running.
+    Running,
+}
+
+/// Diagnose: run a self test.
+#[derive(Debug, Clone)]
+pub struct Diagnose {
+    pub test: DiagnoseTest,
+}
+
+impl Request for Diagnose {
+    type Response = DiagnoseResult;
+    const COMMAND: Command = Command::Diagnose;
+
+    fn params(&self) -> Vec<u8> {
+        let mut params = vec![self.test.code()];
+        match &self.test {
+            DiagnoseTest::CommunicationLine(data) => params.extend_from_slice(data),
+            DiagnoseTest::Polling(bit_rate) => params.push(bit_rate.code()),
+            DiagnoseTest::EchoBack { reply_delay, tx_mode, rx_mode } => params.extend([*reply_delay, *tx_mode, *rx_mode]),
+            DiagnoseTest::Antenna { threshold } => params.push(*threshold),
+            DiagnoseTest::Rom | DiagnoseTest::Ram | DiagnoseTest::Attention => {}
+        }
+        params
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<DiagnoseResult> {
+        match (&self.test, &response[..]) {
+            (DiagnoseTest::CommunicationLine(data), [_, echo @ ..]) if echo == &data[..] => Ok(DiagnoseResult::Passed),
+            (DiagnoseTest::CommunicationLine(_), _) => Ok(DiagnoseResult::Failed(0xFF)),
+            (DiagnoseTest::EchoBack { .. }, _) => Ok(DiagnoseResult::Running),
+            (DiagnoseTest::Polling(_), [fails, ..]) => Ok(DiagnoseResult::PollingFailures(*fails)),
+            (_, [0x00, ..]) => Ok(DiagnoseResult::Passed),
+            (_, [result, ..]) => Ok(DiagnoseResult::Failed(*result)),
+            (_, []) => Err(Error::UnexpectedResponse("Diagnose response is empty!")),
+        }
+    }
+}
+
+/// GetFirmwareVersion: read the IC and firmware versions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GetFirmwareVersion;
+
+impl Request for GetFirmwareVersion {
+    type Response = FirmwareVersion;
+    const COMMAND: Command = Command::GetFirmwareVersion;
+
+    fn params(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<FirmwareVersion> {
+        match response[..] {
+            [ic, ver, rev, support, ..] => Ok(FirmwareVersion { ic, ver, rev, support }),
+            _ => Err(Error::UnexpectedResponse("GetFirmwareVersion response is too short!")),
+        }
+    }
+}
+
+/// GetGeneralStatus: read the last error, the RF field and the targets the
+/// PN532 is handling.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GetGeneralStatus;
+
+impl Request for GetGeneralStatus {
+    type Response = GeneralStatus;
+    const COMMAND: Command = Command::GetGeneralStatus;
+
+    fn params(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<GeneralStatus> {
+        let too_short = || Error::UnexpectedResponse("GetGeneralStatus response is too short!");
+        let [err, field, nb_tg, ref rest @ ..] = response[..] else {
+            return Err(too_short());
+        };
+        if nb_tg > 2 {
+            return Err(Error::UnexpectedResponse("GetGeneralStatus reports more than 2 targets!"));
+        }
+        let bit_rate = |code| BitRate::from_code(code).ok_or(Error::UnexpectedResponse("GetGeneralStatus reports an unknown bit rate!"));
+        let targets_len = 4 * nb_tg as usize;
+        let sam_status = *rest.get(targets_len).ok_or_else(too_short)?;
+        let targets = rest[..targets_len].chunks_exact(4)
+            .map(|target| Ok(SelectedTarget {
+                number: target[0],
+                rx_bit_rate: bit_rate(target[1])?,
+                tx_bit_rate: bit_rate(target[2])?,
+                modulation: Modulation::from(target[3]),
+            }))
+            .collect::<Result<_>>()?;
+
+        Ok(GeneralStatus {
+            last_error: (err != 0).then(|| Status::from(err)),
+            field: field != 0,
+            targets,
+            sam_status,
+        })
+    }
+}
+
+/// State of the PN532 reported by GetGeneralStatus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneralStatus {
+    /// Error of the last command, or `None` if it succeeded.
+    pub last_error: Option<Status>,
+    /// Whether an external RF field is detected, in target mode.
+    pub field: bool,
+    /// The targets currently handled as initiator, at most 2.
+    pub targets: Vec<SelectedTarget>,
+    /// SAM status bit field, see UM0701-02 §7.2.3.
+    pub sam_status: u8,
+}
+
+/// A target handled by the PN532, as reported by GetGeneralStatus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SelectedTarget {
+    /// Logical number of the target.
+    pub number: u8,
+    /// Bit rate of the reception from the target.
+    pub rx_bit_rate: BitRate,
+    /// Bit rate of the transmission to the target.
+    pub tx_bit_rate: BitRate,
+    pub modulation: Modulation,
+}
+
+/// Modulation of a target reported by GetGeneralStatus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Modulation {
+    /// 0x00: MIFARE, ISO/IEC 14443-3 type A or B, or ISO/IEC 18092 passive
+    /// at 106 kbps.
+    Iso14443,
+    /// 0x10: FeliCa or ISO/IEC 18092 passive at 212 or 424 kbps.
+    Felica,
+    /// 0x01: ISO/IEC 18092 active mode.
+    Active,
+    /// 0x02: Innovision Jewel.
+    Jewel,
+    /// A code not documented in UM0701-02.
+    Unknown(u8),
+}
+
+impl From<u8> for Modulation {
+    fn from(code: u8) -> Self {
+        match code {
+            0x00 => Modulation::Iso14443,
+            0x10 => Modulation::Felica,
+            0x01 => Modulation::Active,
+            0x02 => Modulation::Jewel,
+            code => Modulation::Unknown(code),
+        }
+    }
+}
+
+/// How the PN532 uses an attached SAM (UM0701-02 §7.2.10).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SamMode {
+    /// No SAM is used.
+    #[default]
+    Normal,
+    /// The PN532 and the SAM are seen as a single contactless SAM card by
+    /// an external reader.
+    VirtualCard,
+    /// The host talks to the SAM through the PN532.
+    WiredCard,
+    /// The PN532 and the SAM are both seen as cards by an external reader.
+    DualCard,
+}
+
+impl SamMode {
+    /// The Mode byte sent to the PN532.
+    pub fn code(self) -> u8 {
+        match self {
+            SamMode::Normal => 0x01,
+            SamMode::VirtualCard => 0x02,
+            SamMode::WiredCard => 0x03,
+            SamMode::DualCard => 0x04,
+        }
+    }
+}
+
+/// SAMConfiguration: select how the PN532 uses an attached SAM.
+#[derive(Debug, Clone, Copy)]
+pub struct SamConfiguration {
+    pub mode: SamMode,
+    /// Virtual card timeout in units of 50 ms.
+    pub timeout: u8,
+    /// Whether the PN532 drives its IRQ pin.
+    pub use_irq: bool,
+}
+
+impl Request for SamConfiguration {
+    type Response = ();
+    const COMMAND: Command = Command::SamConfiguration;
+
+    fn params(&self) -> Vec<u8> {
+        vec![self.mode.code(), self.timeout, self.use_irq as u8]
+    }
+
+    fn parse(&self, _response: Vec<u8>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Baud rate and modulation used to look for passive targets (BrTy).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BaudModulation {
+    /// 106 kbps ISO/IEC 14443 type A, e.g. MIFARE and NTAG.
+    #[default]
+    Iso14443aType106,
+    /// 212 kbps FeliCa.
+    Felica212,
+    /// 424 kbps FeliCa.
+    Felica424,
+    /// 106 kbps ISO/IEC 14443-3 type B.
+    Iso14443b106,
+    /// 106 kbps Innovision Jewel.
+    Jewel106,
+}
+
+impl BaudModulation {
+    /// The BrTy byte sent to the PN532.
+    pub fn code(self) -> u8 {
+        match self {
+            BaudModulation::Iso14443aType106 => 0x00,
+            BaudModulation::Felica212 => 0x01,
+            BaudModulation::Felica424 => 0x02,
+            BaudModulation::Iso14443b106 => 0x03,
+            BaudModulation::Jewel106 => 0x04,
+        }
+    }
+}
+
+/// Initiator data of a 106 kbps type B InListPassiveTarget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TypeBPolling {
+    /// Application Family Identifier the cards must match, 0x00 for all.
+    pub afi: u8,
+    /// Probabilistic instead of timeslot anti-collision.
+    pub probabilistic: bool,
+}
+
+impl TypeBPolling {
+    /// The InitiatorData bytes: AFI and polling method.
+    pub fn initiator_data(self) -> Vec<u8> {
+        vec![self.afi, u8::from(self.probabilistic)]
+    }
+}
+
+/// A target found by InListPassiveTarget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    /// Logical number the PN532 assigned to the target.
+    pub number: u8,
+    /// Target data, whose layout depends on the baud rate and modulation.
+    pub data: Vec<u8>,
+}
+
+/// InListPassiveTarget: detect up to `max_targets` targets in passive mode.
+#[derive(Debug, Clone)]
+pub struct InListPassiveTarget {
+    /// 1 or 2.
+    pub max_targets: u8,
+    pub baud: BaudModulation,
+    /// Initiator data, required by some modulations.
+    pub initiator_data: Vec<u8>,
+}
+
+impl InListPassiveTarget {
+    /// Number of target data bytes of the first target in `data`.
+    fn target_len(&self, data: &[u8]) -> Result<usize> {
+        let too_short = || Error::UnexpectedResponse("InListPassiveTarget response is too short!");
+        let len = match self.baud {
+            // 106 kbps type A: SENS_RES (2), SEL_RES, NFCIDLength, NFCID1, [ATS].
+            BaudModulation::Iso14443aType106 => {
+                let uid_len = *data.get(3).ok_or_else(too_short)? as usize;
+                let sel_res = data[2];
+                if sel_res & 0x20 != 0 {
+                    let ats_len = *data.get(4 + uid_len).ok_or_else(too_short)? as usize;
+                    4 + uid_len + ats_len
+                } else {
+                    4 + uid_len
+                }
+            }
+            // 212/424 kbps FeliCa: POL_RES, whose first byte is its own length.
+            BaudModulation::Felica212 | BaudModulation::Felica424 => *data.first().ok_or_else(too_short)? as usize,
+            // 106 kbps type B: ATQB (12), ATTRIB_RES length, ATTRIB_RES.
+            BaudModulation::Iso14443b106 => 13 + *data.get(12).ok_or_else(too_short)? as usize,
+            // 106 kbps Innovision Jewel: SENS_RES (2), JEWELID (4).
+            BaudModulation::Jewel106 => 6,
+        };
+
+        Ok(len.min(data.len()))
+    }
+}
+
+impl Request for InListPassiveTarget {
+    type Response = Vec<Target>;
+    const COMMAND: Command = Command::InListPassiveTarget;
+
+    fn params(&self) -> Vec<u8> {
+        let mut params = vec![self.max_targets, self.baud.code()];
+        params.extend_from_slice(&self.initiator_data);
+        params
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<Vec<Target>> {
+        let count = *response.first()
+            .ok_or(Error::UnexpectedResponse("InListPassiveTarget response is empty!"))?;
+        let mut targets = Vec::with_capacity(count as usize);
+        let mut rest = &response[1..];
+        for _ in 0..count {
+            let (&number, data) = rest.split_first()
+                .ok_or(Error::UnexpectedResponse("InListPassiveTarget response is too short!"))?;
+            let len = self.target_len(data)?;
+            targets.push(Target { number, data: data[..len].to_owned() });
+            rest = &data[len..];
+        }
+
+        Ok(targets)
+    }
+}
+
+/// Status byte bit telling that the target's answer did not fit in the
+/// response, and continues in the response to another InDataExchange.
+pub(crate) const STATUS_MI: u8 = 0x40;
+/// Status byte bits holding the error code.
+pub(crate) const STATUS_ERROR: u8 = 0x3F;
+
+/// InDataExchange: exchange data with an activated target.
+///
+/// The response is the data returned by the target; an error code in the
+/// status byte is reported as [`Error::Status`].  An answer longer than the
+/// PN532's buffer is chained over several responses, of which this only
+/// returns the first; [`PN532::in_data_exchange`](crate::pn532::PN532::in_data_exchange)
+/// collects all of them.
+#[derive(Debug, Clone)]
+pub struct InDataExchange {
+    /// Logical number of the target.
+    pub target: u8,
+    /// Data sent to the target.
+    pub data: Vec<u8>,
+}
+
+impl InDataExchange {
+    /// Parse one part of an answer, returning its data and whether more
+    /// follows.
+    pub(crate) fn parse_part(&self, response: Vec<u8>) -> Result<(Vec<u8>, bool)> {
+        match response.split_first() {
+            Some((&status, _)) if status & STATUS_ERROR != 0 => Err(Error::status(status & STATUS_ERROR)),
+            Some((&status, data)) => Ok((data.to_owned(), status & STATUS_MI != 0)),
+            None => Err(Error::UnexpectedResponse("InDataExchange response is empty!")),
+        }
+    }
+}
+
+impl Request for InDataExchange {
+    type Response = Vec<u8>;
+    const COMMAND: Command = Command::InDataExchange;
+
+    fn params(&self) -> Vec<u8> {
+        let mut params = vec![self.target];
+        params.extend_from_slice(&self.data);
+        params
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<Vec<u8>> {
+        self.parse_part(response).map(|(data, _)| data)
+    }
+}
+
+/// InCommunicateThru: send raw data to the activated target, for commands
+/// the PN532 does not know about such as NTAG21x FAST_READ. The PN532 adds
+/// and checks the CRC.
+///
+/// The response is the data returned by the target; a non-zero status byte
+/// is reported as [`Error::Status`].
+#[derive(Debug, Clone)]
+pub struct InCommunicateThru {
+    /// Data sent to the target.
+    pub data: Vec<u8>,
+}
+
+impl Request for InCommunicateThru {
+    type Response = Vec<u8>;
+    const COMMAND: Command = Command::InCommunicateThru;
+
+    fn params(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<Vec<u8>> {
+        match response.split_first() {
+            Some((0x00, data)) => Ok(data.to_owned()),
+            Some((&status, _)) => Err(Error::status(status)),
+            None => Err(Error::UnexpectedResponse("InCommunicateThru response is empty!")),
+        }
+    }
+}
+
+/// Bit rate between the PN532 and a target (BRit / BRti).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BitRate {
+    #[default]
+    Kbps106,
+    Kbps212,
+    Kbps424,
+}
+
+impl BitRate {
+    /// The BRit / BRti byte sent to the PN532.
+    pub fn code(self) -> u8 {
+        match self {
+            BitRate::Kbps106 => 0x00,
+            BitRate::Kbps212 => 0x01,
+            BitRate::Kbps424 => 0x02,
+        }
+    }
+
+    /// The bit rate of a BRit / BRti byte.
+    pub fn from_code(code: u8) -> Option<BitRate> {
+        match code {
+            0x00 => Some(BitRate::Kbps106),
+            0x01 => Some(BitRate::Kbps212),
+            0x02 => Some(BitRate::Kbps424),
+            _ => None,
+        }
+    }
+}
+
+/// InJumpForDEP: activate an NFCIP-1 target in active or passive mode,
+/// ready for DEP exchanges with InDataExchange.
+#[derive(Debug, Clone)]
+pub struct InJumpForDep {
+    /// Active instead of passive communication mode.
+    pub active: bool,
+    /// Bit rate of the activation.
+    pub baud: BitRate,
+    /// Passive mode only: the ID of the target to activate at 106 kbps
+    /// (4 bytes), the polling request payload at 212 and 424 kbps
+    /// (5 bytes, mandatory).
+    pub passive_initiator_data: Option<Vec<u8>>,
+    /// NFCID3i sent in ATR_REQ, or `None` to let the PN532 pick one.
+    pub nfcid3i: Option<[u8; 10]>,
+    /// General bytes sent in ATR_REQ, at most 48.
+    pub general_bytes: Option<Vec<u8>>,
+}
+
+impl Request for InJumpForDep {
+    type Response = DepTarget;
+    const COMMAND: Command = Command::InJumpForDep;
+
+    fn params(&self) -> Vec<u8> {
+        let next = u8::from(self.passive_initiator_data.is_some())
+            | u8::from(self.nfcid3i.is_some()) << 1
+            | u8::from(self.general_bytes.is_some()) << 2;
+        let mut params = vec![u8::from(self.active), self.baud.code(), next];
+        if let Some(data) = &self.passive_initiator_data {
+            params.extend_from_slice(data);
+        }
+        if let Some(nfcid3i) = &self.nfcid3i {
+            params.extend_from_slice(nfcid3i);
+        }
+        if let Some(general_bytes) = &self.general_bytes {
+            params.extend_from_slice(general_bytes);
+        }
+        params
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<DepTarget> {
+        check_status(&response, "InJumpForDEP response is empty!")?;
+        if response.len() < 17 {
+            return Err(Error::UnexpectedResponse("InJumpForDEP response is too short!"));
+        }
+        let mut nfcid3t = [0; 10];
+        nfcid3t.copy_from_slice(&response[2..12]);
+        Ok(DepTarget {
+            target_number: response[1],
+            nfcid3t,
+            did: response[12],
+            send_bit_rates: response[13],
+            receive_bit_rates: response[14],
+            timeout: response[15],
+            optional_parameters: response[16],
+            general_bytes: response[17..].to_owned(),
+        })
+    }
+}
+
+/// InATR: activate a target, sending RATS to an ISO/IEC 14443-4 type A card
+/// or ATR_REQ to an NFCIP-1 target.
+///
+/// The response is the ATS or ATR_RES.
+#[derive(Debug, Clone)]
+pub struct InAtr {
+    /// Logical number of the target.
+    pub target: u8,
+    /// NFCID3i sent in ATR_REQ, or `None` to let the PN532 pick one.
+    pub nfcid3i: Option<[u8; 10]>,
+    /// General bytes sent in ATR_REQ.
+    pub general_bytes: Option<Vec<u8>>,
+}
+
+impl Request for InAtr {
+    type Response = Vec<u8>;
+    const COMMAND: Command = Command::InAtr;
+
+    fn params(&self) -> Vec<u8> {
+        let next = u8::from(self.nfcid3i.is_some()) | u8::from(self.general_bytes.is_some()) << 1;
+        let mut params = vec![self.target, next];
+        if let Some(nfcid3i) = &self.nfcid3i {
+            params.extend_from_slice(nfcid3i);
+        }
+        if let Some(general_bytes) = &self.general_bytes {
+            params.extend_from_slice(general_bytes);
+        }
+        params
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<Vec<u8>> {
+        check_status(&response, "InATR response is empty!")?;
+        Ok(response[1..].to_owned())
+    }
+}
+
+/// InPSL: change the bit rates used with an activated ISO/IEC 14443-4 or
+/// NFCIP-1 target.
+#[derive(Debug, Clone, Copy)]
+pub struct InPsl {
+    /// Logical number of the target.
+    pub target: u8,
+    /// Bit rate from the PN532 to the target.
+    pub initiator_to_target: BitRate,
+    /// Bit rate from the target to the PN532.
+    pub target_to_initiator: BitRate,
+}
+
+impl Request for InPsl {
+    type Response = ();
+    const COMMAND: Command = Command::InPsl;
+
+    fn params(&self) -> Vec<u8> {
+        vec![self.target, self.initiator_to_target.code(), self.target_to_initiator.code()]
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<()> {
+        check_status(&response, "InPSL response is empty!")
+    }
+}
+
+/// Check the status byte that starts the response of most In*/Tg* commands.
+fn check_status(response: &[u8], command: &'static str) -> Result<()> {
+    match response.first() {
+        Some(0x00) => Ok(()),
+        Some(&status) => Err(Error::status(status)),
+        None => Err(Error::UnexpectedResponse(command)),
+    }
+}
+
+/// InDeselect: deselect a target, or all of them if `target` is 0, keeping
+/// its information for a later InSelect.
+#[derive(Debug, Clone, Copy)]
+pub struct InDeselect {
+    pub target: u8,
+}
+
+impl Request for InDeselect {
+    type Response = ();
+    const COMMAND: Command = Command::InDeselect;
+
+    fn params(&self) -> Vec<u8> {
+        vec![self.target]
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<()> {
+        check_status(&response, "InDeselect response is empty!")
+    }
+}
+
+/// InRelease: release a target, or all of them if `target` is 0.
+#[derive(Debug, Clone, Copy)]
+pub struct InRelease {
+    pub target: u8,
+}
+
+impl Request for InRelease {
+    type Response = ();
+    const COMMAND: Command = Command::InRelease;
+
+    fn params(&self) -> Vec<u8> {
+        vec![self.target]
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<()> {
+        check_status(&response, "InRelease response is empty!")
+    }
+}
+
+/// Bit rate of the RF regulation test transmission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TxSpeed {
+    #[default]
+    Kbps106,
+    Kbps212,
+    Kbps424,
+    Kbps848,
+}
+
+/// Modulation of the RF regulation test transmission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TxFraming {
+    #[default]
+    Mifare,
+    Felica,
+}
+
+/// Bit rate and framing of the RF regulation test (TxMode).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TxMode {
+    pub speed: TxSpeed,
+    pub framing: TxFraming,
+}
+
+impl TxMode {
+    /// The TxMode byte sent to the PN532.
+    pub fn code(self) -> u8 {
+        let speed = match self.speed {
+            TxSpeed::Kbps106 => 0x00,
+            TxSpeed::Kbps212 => 0x10,
+            TxSpeed::Kbps424 => 0x20,
+            TxSpeed::Kbps848 => 0x30,
+        };
+        let framing = match self.framing {
+            TxFraming::Mifare => 0x00,
+            TxFraming::Felica => 0x02,
+        };
+        speed | framing
+    }
+}
+
+/// RFRegulationTest: transmit pseudo random data until the next command.
+/// The PN532 never answers it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RfRegulationTest {
+    pub mode: TxMode,
+}
+
+impl Request for RfRegulationTest {
+    type Response = ();
+    const COMMAND: Command = Command::RfRegulationTest;
+
+    fn params(&self) -> Vec<u8> {
+        vec![self.mode.code()]
+    }
+
+    fn parse(&self, _response: Vec<u8>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A timeout of RFConfiguration CfgItem 0x02: none, or 100 µs times a power
+/// of two from 100 µs up to 3.28 s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RfTimeout(u8);
+
+impl RfTimeout {
+    /// Wait forever.
+    pub const NONE: RfTimeout = RfTimeout(0x00);
+
+    /// The timeout coded as `code`, at most 0x10.
+    pub fn from_code(code: u8) -> Option<RfTimeout> {
+        (code <= 0x10).then_some(RfTimeout(code))
+    }
+
+    /// The shortest timeout of at least `timeout`, capped at 3.28 s.
+    pub fn at_least(timeout: Duration) -> RfTimeout {
+        (0x01..=0x10).map(RfTimeout).find(|t| t.duration().is_some_and(|d| d >= timeout)).unwrap_or(RfTimeout(0x10))
+    }
+
+    /// The byte sent to the PN532.
+    pub fn code(self) -> u8 {
+        self.0
+    }
+
+    /// The timeout, or `None` for no timeout.
+    pub fn duration(self) -> Option<Duration> {
+        match self.0 {
+            0 => None,
+            n => Some(Duration::from_micros(100 << (n - 1))),
+        }
+    }
+}
+
+/// InSelect: make a target the current one, which InCommunicateThru
+/// addresses.
+#[derive(Debug, Clone, Copy)]
+pub struct InSelect {
+    pub target: u8,
+}
+
+impl Request for InSelect {
+    type Response = ();
+    const COMMAND: Command = Command::InSelect;
+
+    fn params(&self) -> Vec<u8> {
+        vec![self.target]
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<()> {
+        check_status(&response, "InSelect response is empty!")
+    }
+}
+
+/// RFConfiguration: set one of the RF configuration items.
+#[derive(Debug, Clone)]
+pub struct RfConfiguration {
+    /// Configuration item (CfgItem).
+    pub item: u8,
+    /// Item data (ConfigurationData).
+    pub data: Vec<u8>,
+}
+
+impl Request for RfConfiguration {
+    type Response = ();
+    const COMMAND: Command = Command::RfConfiguration;
+
+    fn params(&self) -> Vec<u8> {
+        let mut params = vec![self.item];
+        params.extend_from_slice(&self.data);
+        params
+    }
+
+    fn parse(&self, _response: Vec<u8>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Events that wake the PN532 up from power down (WakeUpEnable).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WakeupSources {
+    pub i2c: bool,
+    pub gpio: bool,
+    pub spi: bool,
+    pub hsu: bool,
+    /// An external RF field is detected.
+    pub rf_level_detector: bool,
+    pub int1: bool,
+    pub int0: bool,
+}
+
+impl WakeupSources {
+    /// Any activity on the host interfaces.
+    pub const HOST: WakeupSources = WakeupSources {
+        i2c: true,
+        gpio: false,
+        spi: true,
+        hsu: true,
+        rf_level_detector: false,
+        int1: false,
+        int0: false,
+    };
+
+    /// The WakeUpEnable byte sent to the PN532.
+    pub fn bits(self) -> u8 {
+        [
+            (self.i2c, 0x80),
+            (self.gpio, 0x40),
+            (self.spi, 0x20),
+            (self.hsu, 0x10),
+            (self.rf_level_detector, 0x08),
+            (self.int1, 0x02),
+            (self.int0, 0x01),
+        ].iter().filter(|(set, _)| *set).fold(0, |bits, (_, bit)| bits | bit)
+    }
+}
+
+/// PowerDown: put the PN532 into power down mode.
+#[derive(Debug, Clone, Copy)]
+pub struct PowerDown {
+    /// Bit field of the events that wake the PN532 up (WakeUpEnable).
+    pub wakeup_enable: u8,
+    /// Whether to raise the IRQ pin on wake up, if given.
+    pub generate_irq: Option<bool>,
+}
+
+impl Request for PowerDown {
+    type Response = ();
+    const COMMAND: Command = Command::PowerDown;
+
+    fn params(&self) -> Vec<u8> {
+        let mut params = vec![self.wakeup_enable];
+        if let Some(generate_irq) = self.generate_irq {
+            params.push(generate_irq as u8);
+        }
+        params
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<()> {
+        check_status(&response, "PowerDown response is empty!")
+    }
+}
+
+/// ReadGPIO: read the P3, P7 and I0/I1 ports.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReadGpio;
+
+impl Request for ReadGpio {
+    type Response = [u8; 3];
+    const COMMAND: Command = Command::ReadGpio;
+
+    fn params(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<[u8; 3]> {
+        match response[..] {
+            [p3, p7, i, ..] => Ok([p3, p7, i]),
+            _ => Err(Error::UnexpectedResponse("ReadGPIO response is too short!")),
+        }
+    }
+}
+
+/// ReadRegister: read SFR or XRAM registers, see
+/// [`registers`](crate::pn532::registers).
+#[derive(Debug, Clone)]
+pub struct ReadRegister {
+    pub addresses: Vec<u16>,
+}
+
+impl Request for ReadRegister {
+    type Response = Vec<u8>;
+    const COMMAND: Command = Command::ReadRegister;
+
+    fn params(&self) -> Vec<u8> {
+        self.addresses.iter().flat_map(|address| address.to_be_bytes()).collect()
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<Vec<u8>> {
+        if response.len() < self.addresses.len() {
+            return Err(Error::UnexpectedResponse("ReadRegister response is too short!"));
+        }
+        Ok(response)
+    }
+}
+
+/// WriteRegister: write SFR or XRAM registers, as address and value pairs.
+#[derive(Debug, Clone)]
+pub struct WriteRegister {
+    pub writes: Vec<(u16, u8)>,
+}
+
+impl Request for WriteRegister {
+    type Response = ();
+    const COMMAND: Command = Command::WriteRegister;
+
+    fn params(&self) -> Vec<u8> {
+        self.writes.iter().flat_map(|&(address, value)| {
+            let [high, low] = address.to_be_bytes();
+            [high, low, value]
+        }).collect()
+    }
+
+    fn parse(&self, _response: Vec<u8>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Baud rate of the PN532's high speed UART (HSU).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SerialBaudRate {
+    Baud9600,
+    Baud19200,
+    Baud38400,
+    Baud57600,
+    /// The PN532's rate after power on.
+    #[default]
+    Baud115200,
+    Baud230400,
+    Baud460800,
+    Baud921600,
+    Baud1288000,
+}
+
+impl SerialBaudRate {
+    /// The BR byte sent to the PN532.
+    pub fn code(self) -> u8 {
+        match self {
+            SerialBaudRate::Baud9600 => 0x00,
+            SerialBaudRate::Baud19200 => 0x01,
+            SerialBaudRate::Baud38400 => 0x02,
+            SerialBaudRate::Baud57600 => 0x03,
+            SerialBaudRate::Baud115200 => 0x04,
+            SerialBaudRate::Baud230400 => 0x05,
+            SerialBaudRate::Baud460800 => 0x06,
+            SerialBaudRate::Baud921600 => 0x07,
+            SerialBaudRate::Baud1288000 => 0x08,
+        }
+    }
+
+    /// The rate in bits per second.
+    pub fn bits_per_second(self) -> u32 {
+        match self {
+            SerialBaudRate::Baud9600 => 9_600,
+            SerialBaudRate::Baud19200 => 19_200,
+            SerialBaudRate::Baud38400 => 38_400,
+            SerialBaudRate::Baud57600 => 57_600,
+            SerialBaudRate::Baud115200 => 115_200,
+            SerialBaudRate::Baud230400 => 230_400,
+            SerialBaudRate::Baud460800 => 460_800,
+            SerialBaudRate::Baud921600 => 921_600,
+            SerialBaudRate::Baud1288000 => 1_288_000,
+        }
+    }
+}
+
+/// SetSerialBaudRate: change the baud rate of the HSU link.  The PN532
+/// switches once the host ACKs the response.
+#[derive(Debug, Clone, Copy)]
+pub struct SetSerialBaudRate {
+    pub baud: SerialBaudRate,
+}
+
+impl Request for SetSerialBaudRate {
+    type Response = ();
+    const COMMAND: Command = Command::SetSerialBaudRate;
+
+    fn params(&self) -> Vec<u8> {
+        vec![self.baud.code()]
+    }
+
+    fn parse(&self, _response: Vec<u8>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Internal flags of the PN532 set by SetParameters (UM0701-02 §7.2.9).
+/// The default is the PN532's power on state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ParametersFlags {
+    /// fNADUsed: send the NAD byte as DEP or ISO/IEC 14443-4 initiator.
+    pub nad_used: bool,
+    /// fDIDUsed: send the DID (or CID) byte as DEP or ISO/IEC 14443-4
+    /// initiator.
+    pub did_used: bool,
+    /// fAutomaticATR_RES: answer ATR_REQ without the host as target.
+    pub automatic_atr_res: bool,
+    /// fAutomaticRATS: send RATS when an ISO/IEC 14443-4 card is selected.
+    pub automatic_rats: bool,
+    /// fISO14443-4_PICC: allow emulating an ISO/IEC 14443-4 card.
+    pub iso14443_4_picc: bool,
+    /// fRemovePrePostAmble: leave the preamble and postamble out of frames.
+    pub remove_pre_post_amble: bool,
+}
+
+impl ParametersFlags {
+    /// The Flags byte sent to the PN532.
+    pub fn bits(self) -> u8 {
+        [
+            (self.nad_used, 0x01),
+            (self.did_used, 0x02),
+            (self.automatic_atr_res, 0x04),
+            (self.automatic_rats, 0x10),
+            (self.iso14443_4_picc, 0x20),
+            (self.remove_pre_post_amble, 0x40),
+        ].iter().filter(|(set, _)| *set).fold(0, |bits, (_, bit)| bits | bit)
+    }
+
+    /// The flags of a Flags byte, ignoring the RFU bits.
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            nad_used: bits & 0x01 != 0,
+            did_used: bits & 0x02 != 0,
+            automatic_atr_res: bits & 0x04 != 0,
+            automatic_rats: bits & 0x10 != 0,
+            iso14443_4_picc: bits & 0x20 != 0,
+            remove_pre_post_amble: bits & 0x40 != 0,
+        }
+    }
+}
+
+impl Default for ParametersFlags {
+    fn default() -> Self {
+        Self::from_bits(0x34)
+    }
+}
+
+/// SetParameters: set the internal flags of the PN532.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SetParameters {
+    pub flags: ParametersFlags,
+}
+
+impl Request for SetParameters {
+    type Response = ();
+    const COMMAND: Command = Command::SetParameters;
+
+    fn params(&self) -> Vec<u8> {
+        vec![self.flags.bits()]
+    }
+
+    fn parse(&self, _response: Vec<u8>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// WriteGPIO: drive the P3 and P7 ports. A port byte is only applied when its
+/// validation bit (0x80) is set.
+#[derive(Debug, Clone, Copy)]
+pub struct WriteGpio {
+    pub p3: u8,
+    pub p7: u8,
+}
+
+impl Request for WriteGpio {
+    type Response = ();
+    const COMMAND: Command = Command::WriteGpio;
+
+    fn params(&self) -> Vec<u8> {
+        vec![self.p3, self.p7]
+    }
+
+    fn parse(&self, _response: Vec<u8>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Which activations the PN532 accepts as target (TgInitAsTarget Mode).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TargetModeFlags {
+    /// PassiveOnly: refuse active communication mode.
+    pub passive_only: bool,
+    /// DEPOnly: only accept an ATR_REQ as first command.
+    pub dep_only: bool,
+    /// PICCOnly: only accept a RATS as first command, as ISO/IEC 14443-4
+    /// card.  Needs the `iso14443_4_picc` [`ParametersFlags`].
+    pub picc_only: bool,
+}
+
+impl TargetModeFlags {
+    /// The Mode byte sent to the PN532.
+    pub fn bits(self) -> u8 {
+        [
+            (self.passive_only, 0x01),
+            (self.dep_only, 0x02),
+            (self.picc_only, 0x04),
+        ].iter().filter(|(set, _)| *set).fold(0, |bits, (_, bit)| bits | bit)
+    }
+}
+
+/// What the PN532 answers a 106 kbps passive activation with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MifareParams {
+    /// SENS_RES (ATQA), LSB first.
+    pub sens_res: [u8; 2],
+    /// The last 3 bytes of the single size NFCID1; the PN532 sets the first.
+    pub nfcid1t: [u8; 3],
+    /// SEL_RES (SAK): 0x40 for DEP, 0x20 for ISO/IEC 14443-4 card
+    /// emulation, 0x60 for both.
+    pub sel_res: u8,
+}
+
+impl MifareParams {
+    /// The MifareParams bytes sent to the PN532.
+    pub fn bytes(&self) -> [u8; 6] {
+        let [s0, s1] = self.sens_res;
+        let [n0, n1, n2] = self.nfcid1t;
+        [s0, s1, n0, n1, n2, self.sel_res]
+    }
+}
+
+/// What the PN532 answers a 212/424 kbps FeliCa polling request with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FelicaParams {
+    pub nfcid2t: [u8; 8],
+    pub pad: [u8; 8],
+    /// Returned in POL_RES when the polling request asks for it.
+    pub system_code: [u8; 2],
+}
+
+impl FelicaParams {
+    /// The FeliCaParams bytes sent to the PN532.
+    pub fn bytes(&self) -> [u8; 18] {
+        let mut bytes = [0; 18];
+        bytes[..8].copy_from_slice(&self.nfcid2t);
+        bytes[8..16].copy_from_slice(&self.pad);
+        bytes[16..].copy_from_slice(&self.system_code);
+        bytes
+    }
+}
+
+/// How the PN532 presents itself as target, for
+/// [`PN532::tg_init_as_target`](crate::pn532::PN532::tg_init_as_target).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TargetConfig {
+    pub mode: TargetModeFlags,
+    pub mifare: MifareParams,
+    pub felica: FelicaParams,
+    /// NFCID3 used in the ATR_RES.
+    pub nfcid3t: [u8; 10],
+    /// General bytes used in the ATR_RES (max. 47 bytes).
+    pub general_bytes: Vec<u8>,
+    /// Historical bytes used in the ATS in ISO/IEC14443-4 PICC emulation
+    /// (max. 48 bytes).
+    pub historical_bytes: Vec<u8>,
+}
+
+impl TargetConfig {
+    pub fn mode(mut self, mode: TargetModeFlags) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn mifare(mut self, mifare: MifareParams) -> Self {
+        self.mifare = mifare;
+        self
+    }
+
+    pub fn felica(mut self, felica: FelicaParams) -> Self {
+        self.felica = felica;
+        self
+    }
+
+    pub fn nfcid3t(mut self, nfcid3t: [u8; 10]) -> Self {
+        self.nfcid3t = nfcid3t;
+        self
+    }
+
+    pub fn general_bytes(mut self, general_bytes: &[u8]) -> Self {
+        self.general_bytes = general_bytes.to_owned();
+        self
+    }
+
+    pub fn historical_bytes(mut self, historical_bytes: &[u8]) -> Self {
+        self.historical_bytes = historical_bytes.to_owned();
+        self
+    }
+
+    /// Check the limits the PN532 enforces.
+    pub fn validate(&self) -> Result<()> {
+        if self.general_bytes.len() > 47 {
+            return Err(Error::InvalidArgument(format!("at most 47 general bytes, got {}", self.general_bytes.len())));
+        }
+        if self.historical_bytes.len() > 48 {
+            return Err(Error::InvalidArgument(format!("at most 48 historical bytes, got {}", self.historical_bytes.len())));
+        }
+        if self.mode.dep_only && self.mode.picc_only {
+            return Err(Error::InvalidArgument("DEPOnly and PICCOnly exclude each other".to_owned()));
+        }
+        Ok(())
+    }
+}
+
+/// How an initiator activated the PN532 as target, and its first command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActivationResult {
+    pub baud: BitRate,
+    /// Activated as ISO/IEC 14443-4 card (RATS received).
+    pub iso14443_4_picc: bool,
+    /// Activated as DEP target (ATR_REQ received).
+    pub dep: bool,
+    /// [`Modulation::Iso14443`] for MIFARE framing, or
+    /// [`Modulation::Active`] or [`Modulation::Felica`].
+    pub framing: Modulation,
+    /// The first valid frame received from the initiator.
+    pub initiator_command: Vec<u8>,
+}
+
+/// TgInitAsTarget: configure the PN532 as a target and wait for an initiator.
+#[derive(Debug, Clone)]
+pub struct TgInitAsTarget {
+    pub config: TargetConfig,
+}
+
+impl Request for TgInitAsTarget {
+    type Response = ActivationResult;
+    const COMMAND: Command = Command::TgInitAsTarget;
+
+    fn params(&self) -> Vec<u8> {
+        let config = &self.config;
+        let mut params = vec![config.mode.bits()];
+        params.extend_from_slice(&config.mifare.bytes());
+        params.extend_from_slice(&config.felica.bytes());
+        params.extend_from_slice(&config.nfcid3t);
+        params.push(config.general_bytes.len() as u8);
+        params.extend_from_slice(&config.general_bytes);
+        params.push(config.historical_bytes.len() as u8);
+        params.extend_from_slice(&config.historical_bytes);
+        params
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<ActivationResult> {
+        let Some((&mode, command)) = response.split_first() else {
+            return Err(Error::UnexpectedResponse("TgInitAsTarget response is empty!"));
+        };
+        let baud = BitRate::from_code(mode >> 4 & 0x07)
+            .ok_or(Error::UnexpectedResponse("TgInitAsTarget mode has an unknown baud rate!"))?;
+        let framing = match mode & 0x03 {
+            0x00 => Modulation::Iso14443,
+            0x01 => Modulation::Active,
+            0x02 => Modulation::Felica,
+            framing => Modulation::Unknown(framing),
+        };
+        Ok(ActivationResult {
+            baud,
+            iso14443_4_picc: mode & 0x08 != 0,
+            dep: mode & 0x04 != 0,
+            framing,
+            initiator_command: command.to_owned(),
+        })
+    }
+}
+
+/// TgGetData: fetch the data the initiator sent the PN532 as DEP target or
+/// ISO/IEC 14443-4 card.
+///
+/// The response is the data and whether more follows (MI bit), to be
+/// fetched with another TgGetData.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TgGetData;
+
+impl Request for TgGetData {
+    type Response = (Vec<u8>, bool);
+    const COMMAND: Command = Command::TgGetData;
+
+    fn params(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<(Vec<u8>, bool)> {
+        match response.split_first() {
+            Some((&status, _)) if status & STATUS_ERROR != 0 => Err(Error::status(status & STATUS_ERROR)),
+            Some((&status, data)) => Ok((data.to_owned(), status & STATUS_MI != 0)),
+            None => Err(Error::UnexpectedResponse("TgGetData response is empty!")),
+        }
+    }
+}
+
+/// TgSetData: send the answer to the initiator, at most
+/// [`TgSetData::MAX_LEN`] bytes.  The PN532 handles the RF chaining.
+#[derive(Debug, Clone)]
+pub struct TgSetData {
+    pub data: Vec<u8>,
+}
+
+impl TgSetData {
+    /// Longest answer a TgSetData carries.
+    pub const MAX_LEN: usize = 262;
+}
+
+impl Request for TgSetData {
+    type Response = ();
+    const COMMAND: Command = Command::TgSetData;
+
+    fn params(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<()> {
+        check_status(&response, "TgSetData response is empty!")
+    }
+}
+
+/// TgGetInitiatorCommand: fetch the raw frame the initiator sent the PN532
+/// as target, without any protocol handling.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TgGetInitiatorCommand;
+
+impl Request for TgGetInitiatorCommand {
+    type Response = Vec<u8>;
+    const COMMAND: Command = Command::TgGetInitiatorCommand;
+
+    fn params(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<Vec<u8>> {
+        check_status(&response, "TgGetInitiatorCommand response is empty!")?;
+        Ok(response[1..].to_owned())
+    }
+}
+
+/// TgResponseToInitiator: send a raw answer to the initiator, at most
+/// [`TgResponseToInitiator::MAX_LEN`] bytes.  Answers over 254 bytes go in
+/// an extended information frame.
+#[derive(Debug, Clone)]
+pub struct TgResponseToInitiator {
+    pub data: Vec<u8>,
+}
+
+impl TgResponseToInitiator {
+    /// Longest answer a TgResponseToInitiator carries.
+    pub const MAX_LEN: usize = 262;
+}
+
+impl Request for TgResponseToInitiator {
+    type Response = ();
+    const COMMAND: Command = Command::TgResponseToInitiator;
+
+    fn params(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<()> {
+        check_status(&response, "TgResponseToInitiator response is empty!")
+    }
+}
+
+/// State of the PN532 as target, reported by TgGetTargetStatus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TargetState {
+    /// 0x00: waiting for an initiator, or released by it, as NFCIP-1 target.
+    Idle,
+    /// 0x01: activated as NFCIP-1 target.
+    Activated,
+    /// 0x02: deselected by the initiator as NFCIP-1 target.
+    Deselected,
+    /// 0x80: released by the PCD as ISO/IEC 14443-4 card; the RF field is
+    /// gone.
+    PiccReleased,
+    /// 0x81: activated as ISO/IEC 14443-4 card.
+    PiccActivated,
+    /// 0x82: deselected by the PCD as ISO/IEC 14443-4 card.
+    PiccDeselected,
+    /// A code not documented in UM0701-02.
+    Unknown(u8),
+}
+
+impl From<u8> for TargetState {
+    fn from(code: u8) -> Self {
+        match code {
+            0x00 => TargetState::Idle,
+            0x01 => TargetState::Activated,
+            0x02 => TargetState::Deselected,
+            0x80 => TargetState::PiccReleased,
+            0x81 => TargetState::PiccActivated,
+            0x82 => TargetState::PiccDeselected,
+            code => TargetState::Unknown(code),
+        }
+    }
+}
+
+/// Result of TgGetTargetStatus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TargetStatus {
+    pub state: TargetState,
+    /// Bit rate from the initiator, when activated as NFCIP-1 target.
+    pub initiator_bit_rate: Option<BitRate>,
+    /// Bit rate to the initiator, when activated as NFCIP-1 target.
+    pub target_bit_rate: Option<BitRate>,
+}
+
+impl TargetStatus {
+    /// Whether an initiator has the PN532 activated, as NFCIP-1 target or
+    /// ISO/IEC 14443-4 card.
+    pub fn is_activated(&self) -> bool {
+        matches!(self.state, TargetState::Activated | TargetState::PiccActivated)
+    }
+}
+
+/// TgGetTargetStatus: get the state of the PN532 as target.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TgGetTargetStatus;
+
+impl Request for TgGetTargetStatus {
+    type Response = TargetStatus;
+    const COMMAND: Command = Command::TgGetTargetStatus;
+
+    fn params(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn parse(&self, response: Vec<u8>) -> Result<TargetStatus> {
+        let Some(&state) = response.first() else {
+            return Err(Error::UnexpectedResponse("TgGetTargetStatus response is empty!"));
+        };
+        let state = TargetState::from(state);
+        // BRit is only meaningful once activated as NFCIP-1 target.
+        let bit_rates = match (state, response.get(1)) {
+            (TargetState::Activated, Some(&brit)) => (BitRate::from_code(brit >> 4 & 0x07), BitRate::from_code(brit & 0x07)),
+            _ => (None, None),
+        };
+        Ok(TargetStatus {
+            state,
+            initiator_bit_rate: bit_rates.0,
+            target_bit_rate: bit_rates.1,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(baud: BaudModulation, response: &[u8]) -> Result<Vec<Target>> {
+        InListPassiveTarget { max_targets: 2, baud, initiator_data: Vec::new() }.parse(response.to_vec())
+    }
+
+    #[test]
+    fn lists_type_a_targets() {
+        // MIFARE Classic 1K: SENS_RES 00 04, SEL_RES 08, 4-byte NFCID1.
+        let classic = list(BaudModulation::Iso14443aType106, &[0x01, 0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF]).unwrap();
+        assert_eq!(classic, [Target { number: 1, data: vec![0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF] }]);
+
+        // DESFire: SEL_RES 20 is followed by the ATS, whose TL counts itself.
+        let desfire = [0x03, 0x44, 0x20, 0x07, 0x04, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x06, 0x75, 0x77, 0x81, 0x02, 0x80];
+        let mut response = vec![0x01, 0x01];
+        response.extend_from_slice(&desfire);
+        let targets = list(BaudModulation::Iso14443aType106, &response).unwrap();
+        assert_eq!(targets, [Target { number: 1, data: desfire.to_vec() }]);
+    }
+
+    #[test]
+    fn lists_two_targets() {
+        let first = [0x00, 0x04, 0x08, 0x04, 0x01, 0x02, 0x03, 0x04];
+        let second = [0x00, 0x44, 0x00, 0x07, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A];
+        let mut response = vec![0x02, 0x01];
+        response.extend_from_slice(&first);
+        response.push(0x02);
+        response.extend_from_slice(&second);
+        let targets = list(BaudModulation::Iso14443aType106, &response).unwrap();
+        assert_eq!(targets, [
+            Target { number: 1, data: first.to_vec() },
+            Target { number: 2, data: second.to_vec() },
+        ]);
+    }
+
+    #[test]
+    fn lists_type_b_and_felica_targets() {
+        // ATQB (12 bytes), ATTRIB_RES length 1, ATTRIB_RES.
+        let type_b = [0x50, 0x01, 0x02, 0x03, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x71, 0x85, 0x01, 0x00];
+        let mut response = vec![0x01, 0x01];
+        response.extend_from_slice(&type_b);
+        assert_eq!(list(BaudModulation::Iso14443b106, &response).unwrap(), [Target { number: 1, data: type_b.to_vec() }]);
+
+        // POL_RES: length 0x12, response code 01, NFCID2t, PAD; the
+        // second target follows right after.
+        let pol_res = [0x12, 0x01, 0x01, 0x2E, 0x3D, 0x4C, 0x5B, 0x6A, 0x79, 0x88, 0x00, 0xF0, 0x00, 0x00, 0x00, 0x01, 0x43, 0x00];
+        let mut response = vec![0x02, 0x01];
+        response.extend_from_slice(&pol_res);
+        response.push(0x02);
+        response.extend_from_slice(&pol_res);
+        let targets = list(BaudModulation::Felica212, &response).unwrap();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[1], Target { number: 2, data: pol_res.to_vec() });
+    }
+
+    #[test]
+    fn rejects_truncated_target_lists() {
+        for response in [&[][..], &[0x01], &[0x01, 0x01, 0x00, 0x04, 0x08], &[0x02, 0x01, 0x00, 0x04, 0x08, 0x00]] {
+            assert!(matches!(list(BaudModulation::Iso14443aType106, response), Err(Error::UnexpectedResponse(_))), "{response:02X?}");
+        }
+        // SEL_RES announces an ATS the response does not hold.
+        let no_ats = [0x01, 0x01, 0x00, 0x44, 0x20, 0x04, 0x01, 0x02, 0x03, 0x04];
+        assert!(matches!(list(BaudModulation::Iso14443aType106, &no_ats), Err(Error::UnexpectedResponse(_))));
+        assert!(matches!(list(BaudModulation::Iso14443b106, &[0x01, 0x01, 0x50]), Err(Error::UnexpectedResponse(_))));
+    }
+
+    #[test]
+    fn parses_general_status() {
+        // Last error 0x01 (timeout), one target at 106 kbps type A, SAM ok.
+        let status = GetGeneralStatus.parse(vec![0x01, 0x00, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00]).unwrap();
+        assert_eq!(status, GeneralStatus {
+            last_error: Some(Status::Timeout),
+            field: false,
+            targets: vec![SelectedTarget {
+                number: 1,
+                rx_bit_rate: BitRate::Kbps106,
+                tx_bit_rate: BitRate::Kbps106,
+                modulation: Modulation::Iso14443,
+            }],
+            sam_status: 0x00,
+        });
+
+        let idle = GetGeneralStatus.parse(vec![0x00, 0x01, 0x00, 0x80]).unwrap();
+        assert_eq!((idle.last_error, idle.field, idle.targets.len(), idle.sam_status), (None, true, 0, 0x80));
+
+        for response in [vec![0x00, 0x00], vec![0x00, 0x00, 0x01, 0x01, 0x00, 0x00, 0x00], vec![0x00, 0x00, 0x03, 0x00]] {
+            assert!(matches!(GetGeneralStatus.parse(response), Err(Error::UnexpectedResponse(_))));
+        }
+        let unknown_rate = vec![0x00, 0x00, 0x01, 0x01, 0x07, 0x00, 0x00, 0x00];
+        assert!(matches!(GetGeneralStatus.parse(unknown_rate), Err(Error::UnexpectedResponse(_))));
+    }
+
+    #[test]
+    fn parses_dep_activation() {
+        let request = InJumpForDep {
+            active: true,
+            baud: BitRate::Kbps424,
+            passive_initiator_data: None,
+            nfcid3i: None,
+            general_bytes: None,
+        };
+        // Status, Tg, ATR_RES: NFCID3t (10), DIDt, BSt, BRt, TO, PPt, Gt.
+        let mut response = vec![0x00, 0x01];
+        response.extend(0xA0..0xAA);
+        response.extend_from_slice(&[0x00, 0x00, 0x00, 0x0E, 0x32, 0x46, 0x66, 0x6D]);
+        let target = request.parse(response).unwrap();
+        assert_eq!(target, DepTarget {
+            target_number: 1,
+            nfcid3t: [0xA0, 0xA1, 0xA2, 0xA3, 0xA4, 0xA5, 0xA6, 0xA7, 0xA8, 0xA9],
+            did: 0x00,
+            send_bit_rates: 0x00,
+            receive_bit_rates: 0x00,
+            timeout: 0x0E,
+            optional_parameters: 0x32,
+            general_bytes: vec![0x46, 0x66, 0x6D],
+        });
+
+        assert_eq!(request.parse(vec![0x01]).unwrap_err().chip_status(), Some(Status::Timeout));
+        assert!(matches!(request.parse(vec![0x00, 0x01, 0xA0]), Err(Error::UnexpectedResponse(_))));
+    }
+
+    #[test]
+    fn parses_target_activation_mode() {
+        let request = TgInitAsTarget { config: TargetConfig::default() };
+        let cases: [(u8, BitRate, bool, bool, Modulation); 4] = [
+            // 106 kbps passive, ATR_REQ received.
+            (0x04, BitRate::Kbps106, false, true, Modulation::Iso14443),
+            // 424 kbps active DEP.
+            (0x25, BitRate::Kbps424, false, true, Modulation::Active),
+            // 212 kbps FeliCa framing.
+            (0x16, BitRate::Kbps212, false, true, Modulation::Felica),
+            // 106 kbps, RATS received as ISO/IEC 14443-4 card.
+            (0x08, BitRate::Kbps106, true, false, Modulation::Iso14443),
+        ];
+        for (mode, baud, iso14443_4_picc, dep, framing) in cases {
+            let activation = request.parse(vec![mode, 0xE0, 0x80]).unwrap();
+            assert_eq!(activation, ActivationResult {
+                baud,
+                iso14443_4_picc,
+                dep,
+                framing,
+                initiator_command: vec![0xE0, 0x80],
+            }, "mode {mode:02X}");
+        }
+
+        assert!(matches!(request.parse(Vec::new()), Err(Error::UnexpectedResponse(_))));
+        assert!(matches!(request.parse(vec![0x30]), Err(Error::UnexpectedResponse(_))));
+    }
+
+    #[test]
+    fn parses_target_status() {
+        let activated = TgGetTargetStatus.parse(vec![0x01, 0x12]).unwrap();
+        assert_eq!(activated, TargetStatus {
+            state: TargetState::Activated,
+            initiator_bit_rate: Some(BitRate::Kbps212),
+            target_bit_rate: Some(BitRate::Kbps424),
+        });
+        assert!(activated.is_activated());
+
+        // BRit is ignored outside NFCIP-1 activation.
+        let picc = TgGetTargetStatus.parse(vec![0x81, 0x22]).unwrap();
+        assert_eq!((picc.state, picc.initiator_bit_rate, picc.target_bit_rate), (TargetState::PiccActivated, None, None));
+        assert!(picc.is_activated());
+
+        let released = TgGetTargetStatus.parse(vec![0x80]).unwrap();
+        assert!(!released.is_activated());
+        assert_eq!(TgGetTargetStatus.parse(vec![0x42]).unwrap().state, TargetState::Unknown(0x42));
+        assert!(matches!(TgGetTargetStatus.parse(Vec::new()), Err(Error::UnexpectedResponse(_))));
+    }
+
+    #[test]
+    fn rounds_rf_timeouts_up() {
+        assert_eq!(RfTimeout::NONE.duration(), None);
+        assert_eq!(RfTimeout::from_code(0x01).unwrap().duration(), Some(Duration::from_micros(100)));
+        assert_eq!(RfTimeout::from_code(0x0B).unwrap().duration(), Some(Duration::from_micros(102_400)));
+        assert_eq!(RfTimeout::from_code(0x10).unwrap().duration(), Some(Duration::from_micros(3_276_800)));
+        assert_eq!(RfTimeout::from_code(0x11), None);
+
+        assert_eq!(RfTimeout::at_least(Duration::ZERO).code(), 0x01);
+        assert_eq!(RfTimeout::at_least(Duration::from_micros(100)).code(), 0x01);
+        assert_eq!(RfTimeout::at_least(Duration::from_micros(101)).code(), 0x02);
+        assert_eq!(RfTimeout::at_least(Duration::from_millis(100)).code(), 0x0B);
+        assert_eq!(RfTimeout::at_least(Duration::from_secs(10)).code(), 0x10);
+        for code in 0x01..=0x10 {
+            let timeout = RfTimeout::from_code(code).unwrap();
+            assert_eq!(RfTimeout::at_least(timeout.duration().unwrap()), timeout);
+        }
+    }
+}
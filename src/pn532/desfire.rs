@@ -0,0 +1,839 @@
+//! MIFARE DESFire (EV1) commands over an [`ApduChannel`]: version, application
+//! and file listing, application selection, authentication, and reading and
+//! writing data, value and record files.
+//!
+//! Native commands are sent wrapped in ISO/IEC 7816-4 APDUs: CLA 0x90, the
+//! command code as INS, its data, and Le 0x00.  The card answers with its
+//! data followed by SW1 0x91 and its status as SW2.  Answers that do not
+//! fit one frame end with status 0xAF and go on with an ADDITIONAL_FRAME
+//! command.
+//!
+//! Authentication needs the `des` feature for the legacy DES/2K3DES keys
+//! and the `aes` feature for the EV1 AES keys.
+//!
+//! After AES authentication the EV1 secure messaging applies: every command
+//! updates a CMAC chained through the session, and every answer carries the
+//! first 8 bytes of its CMAC, which is checked.  Files with MACed or
+//! enciphered communication are only accessible in such a session; with
+//! legacy authentication, only plain files are.
+
+#[cfg(any(feature = "des", feature = "aes"))]
+use cipher::generic_array::GenericArray;
+#[cfg(any(feature = "des", feature = "aes"))]
+use cipher::{BlockDecrypt, BlockEncrypt, BlockSizeUser, KeyInit};
+
+use crate::pn532::apdu::{Apdu, ApduChannel};
+use crate::pn532::session::IsoDepTag;
+use crate::pn532::{Error, PN532, Result};
+
+const CLA: u8 = 0x90;
+
+const CMD_GET_VERSION: u8 =           0x60;
+const CMD_GET_APPLICATION_IDS: u8 =   0x6A;
+const CMD_SELECT_APPLICATION: u8 =    0x5A;
+const CMD_GET_FILE_IDS: u8 =          0x6F;
+const CMD_GET_FILE_SETTINGS: u8 =     0xF5;
+const CMD_READ_DATA: u8 =             0xBD;
+const CMD_GET_VALUE: u8 =             0x6C;
+const CMD_READ_RECORDS: u8 =          0xBB;
+const CMD_WRITE_DATA: u8 =            0x3D;
+const CMD_WRITE_RECORD: u8 =          0x3B;
+const CMD_COMMIT_TRANSACTION: u8 =    0xC7;
+#[cfg(feature = "des")]
+const CMD_AUTHENTICATE_LEGACY: u8 =   0x0A;
+#[cfg(feature = "aes")]
+const CMD_AUTHENTICATE_AES: u8 =      0xAA;
+const ADDITIONAL_FRAME: u8 =          0xAF;
+
+const SW1: u8 = 0x91;
+const OPERATION_OK: u8 = 0x00;
+
+/// Most data sent in one frame, the rest follows in additional frames.
+/// Keeps the wrapped frame within the 64 bytes the card accepts.
+const MAX_FRAME_DATA: usize = 52;
+
+/// The PICC level application, selected after activation, holding the
+/// card master key.
+pub const PICC_APPLICATION: u32 = 0x000000;
+
+/// Largest application ID, AIDs are 3 bytes.
+pub const MAX_APPLICATION_ID: u32 = 0xFF_FFFF;
+
+/// Version of the hardware or software of a card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PartVersion {
+    pub vendor: u8,
+    pub product_type: u8,
+    pub product_subtype: u8,
+    pub major: u8,
+    pub minor: u8,
+    /// 2^(n/2) bytes, more if the lowest bit is set.
+    pub storage_size: u8,
+    pub protocol: u8,
+}
+
+impl PartVersion {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            vendor: bytes[0],
+            product_type: bytes[1],
+            product_subtype: bytes[2],
+            major: bytes[3],
+            minor: bytes[4],
+            storage_size: bytes[5],
+            protocol: bytes[6],
+        }
+    }
+}
+
+/// Answer to GET_VERSION.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DesfireVersion {
+    pub hardware: PartVersion,
+    pub software: PartVersion,
+    pub uid: [u8; 7],
+    pub batch: [u8; 5],
+    /// Production week and year, in BCD.
+    pub production_week: u8,
+    pub production_year: u8,
+}
+
+impl DesfireVersion {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 28 {
+            return Err(Error::UnexpectedResponse("DESFire GET_VERSION response is too short!"));
+        }
+        let mut uid = [0; 7];
+        uid.copy_from_slice(&bytes[14..21]);
+        let mut batch = [0; 5];
+        batch.copy_from_slice(&bytes[21..26]);
+
+        Ok(Self {
+            hardware: PartVersion::from_bytes(&bytes[..7]),
+            software: PartVersion::from_bytes(&bytes[7..14]),
+            uid,
+            batch,
+            production_week: bytes[26],
+            production_year: bytes[27],
+        })
+    }
+
+    /// Bytes of storage, as told by the software storage size, or `None` if
+    /// the size does not fit a `u32`.
+    pub fn storage_bytes(&self) -> Option<u32> {
+        1u32.checked_shl(u32::from(self.software.storage_size >> 1))
+    }
+}
+
+/// Kind of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FileType {
+    StandardData,
+    BackupData,
+    Value,
+    LinearRecord,
+    CyclicRecord,
+    Unknown(u8),
+}
+
+impl From<u8> for FileType {
+    fn from(file_type: u8) -> Self {
+        match file_type {
+            0x00 => FileType::StandardData,
+            0x01 => FileType::BackupData,
+            0x02 => FileType::Value,
+            0x03 => FileType::LinearRecord,
+            0x04 => FileType::CyclicRecord,
+            other => FileType::Unknown(other),
+        }
+    }
+}
+
+/// How a file's data travels: plain, followed by a MAC, or enciphered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CommunicationMode {
+    Plain,
+    Maced,
+    Enciphered,
+}
+
+impl From<u8> for CommunicationMode {
+    fn from(settings: u8) -> Self {
+        match settings & 0x03 {
+            0x01 => CommunicationMode::Maced,
+            0x03 => CommunicationMode::Enciphered,
+            _ => CommunicationMode::Plain,
+        }
+    }
+}
+
+/// Answer to GET_FILE_SETTINGS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSettings {
+    pub file_type: FileType,
+    pub communication: CommunicationMode,
+    /// Read, write, read-write and change keys, a nibble each from the top.
+    /// Key 0xE is free access, 0xF no access.
+    pub access_rights: u16,
+    /// The settings of the file type: size of data files; limits, value
+    /// and limited credit of value files; record size, max and current
+    /// record count of record files.
+    pub parameters: Vec<u8>,
+}
+
+impl FileSettings {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let [file_type, communication, rights0, rights1, ref parameters @ ..] = bytes[..] else {
+            return Err(Error::UnexpectedResponse("DESFire GET_FILE_SETTINGS response is too short!"));
+        };
+
+        Ok(Self {
+            file_type: file_type.into(),
+            communication: communication.into(),
+            access_rights: u16::from_le_bytes([rights0, rights1]),
+            parameters: parameters.to_vec(),
+        })
+    }
+
+    /// Size in bytes of a data file, or of the records of a record file.
+    pub fn size(&self) -> Option<u32> {
+        match (self.file_type, &self.parameters[..]) {
+            (FileType::StandardData | FileType::BackupData | FileType::LinearRecord | FileType::CyclicRecord, [s0, s1, s2, ..]) => {
+                Some(u32::from_le_bytes([*s0, *s1, *s2, 0]))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A MIFARE DESFire card.
+pub struct DesfireTag<'r, P: PN532 + ?Sized> {
+    channel: ApduChannel<'r, P>,
+    /// The key number authenticated with.
+    authenticated: Option<u8>,
+    session_key: Option<[u8; 16]>,
+    /// EV1 secure messaging, after AES authentication.
+    #[cfg(feature = "aes")]
+    messaging: Option<SecureMessaging>,
+}
+
+impl<'r, P: PN532 + ?Sized> DesfireTag<'r, P> {
+    pub fn new(tag: IsoDepTag<'r, P>) -> Self {
+        Self {
+            channel: ApduChannel::new(tag),
+            authenticated: None,
+            session_key: None,
+            #[cfg(feature = "aes")]
+            messaging: None,
+        }
+    }
+
+    /// The ISO-DEP handle, e.g. to send other APDUs.
+    pub fn into_inner(self) -> IsoDepTag<'r, P> {
+        self.channel.into_inner()
+    }
+
+    /// The key number authenticated with, if any.
+    pub fn authenticated_key(&self) -> Option<u8> {
+        self.authenticated
+    }
+
+    /// The session key agreed on by the last authentication: 2K3DES (a DES
+    /// key twice for a DES key) or AES.
+    pub fn session_key(&self) -> Option<[u8; 16]> {
+        self.session_key
+    }
+
+    pub fn get_version(&mut self) -> Result<DesfireVersion> {
+        DesfireVersion::from_bytes(&self.transceive(CMD_GET_VERSION, &[])?)
+    }
+
+    /// The IDs of the applications on the card.
+    pub fn application_ids(&mut self) -> Result<Vec<u32>> {
+        let ids = self.transceive(CMD_GET_APPLICATION_IDS, &[])?;
+        Ok(ids.chunks_exact(3).map(|aid| u32::from_le_bytes([aid[0], aid[1], aid[2], 0])).collect())
+    }
+
+    /// Select application `aid`, or [`PICC_APPLICATION`].  Drops the
+    /// authentication.
+    pub fn select_application(&mut self, aid: u32) -> Result<()> {
+        if aid > MAX_APPLICATION_ID {
+            return Err(Error::InvalidArgument(format!("AIDs are 3 bytes, got {:#x}", aid)));
+        }
+        self.end_session();
+        let [a0, a1, a2, _] = aid.to_le_bytes();
+        self.transceive(CMD_SELECT_APPLICATION, &[a0, a1, a2])?;
+        Ok(())
+    }
+
+    /// The file numbers of the selected application.
+    pub fn file_ids(&mut self) -> Result<Vec<u8>> {
+        self.transceive(CMD_GET_FILE_IDS, &[])
+    }
+
+    pub fn file_settings(&mut self, file: u8) -> Result<FileSettings> {
+        FileSettings::from_bytes(&self.transceive(CMD_GET_FILE_SETTINGS, &[file])?)
+    }
+
+    /// Read `len` bytes at `offset` of data file `file`, or up to its end if
+    /// `len` is 0, with the communication mode of the file.
+    pub fn read_data(&mut self, file: u8, offset: u32, len: u32, mode: CommunicationMode) -> Result<Vec<u8>> {
+        let params = file_range(file, offset, len)?;
+        self.read(CMD_READ_DATA, &params, mode, (len > 0).then_some(len as usize))
+    }
+
+    /// The value of value file `file`, with the communication mode of the
+    /// file.
+    pub fn get_value(&mut self, file: u8, mode: CommunicationMode) -> Result<i32> {
+        match self.read(CMD_GET_VALUE, &[file], mode, Some(4))?[..] {
+            [v0, v1, v2, v3] => Ok(i32::from_le_bytes([v0, v1, v2, v3])),
+            _ => Err(Error::UnexpectedResponse("DESFire GET_VALUE response is not 4 bytes!")),
+        }
+    }
+
+    /// Read `count` records from record `offset` on of record file `file`,
+    /// or all of them if `count` is 0, the oldest first, with the
+    /// communication mode of the file.
+    pub fn read_records(&mut self, file: u8, offset: u32, count: u32, mode: CommunicationMode) -> Result<Vec<u8>> {
+        let params = file_range(file, offset, count)?;
+        self.read(CMD_READ_RECORDS, &params, mode, None)
+    }
+
+    /// Write `data` at `offset` of data file `file`, with the communication
+    /// mode of the file.  Backup files need
+    /// [`DesfireTag::commit_transaction`].
+    pub fn write_data(&mut self, file: u8, offset: u32, data: &[u8], mode: CommunicationMode) -> Result<()> {
+        let header = file_range(file, offset, data.len() as u32)?;
+        self.write(CMD_WRITE_DATA, &header, data, mode)
+    }
+
+    /// Write `data` at `offset` of the record being appended to record file
+    /// `file`, with the communication mode of the file.  Needs
+    /// [`DesfireTag::commit_transaction`].
+    pub fn write_record(&mut self, file: u8, offset: u32, data: &[u8], mode: CommunicationMode) -> Result<()> {
+        let header = file_range(file, offset, data.len() as u32)?;
+        self.write(CMD_WRITE_RECORD, &header, data, mode)
+    }
+
+    /// Validate the writes to backup, value and record files of the
+    /// selected application.
+    pub fn commit_transaction(&mut self) -> Result<()> {
+        self.transceive(CMD_COMMIT_TRANSACTION, &[])?;
+        Ok(())
+    }
+
+    /// Authenticate with DES or 2K3DES key `key_number` of the selected
+    /// application (AUTHENTICATE, 0x0A).  A DES key is given as its 8 bytes
+    /// twice.  A refused key fails with [`Error::CardAuthentication`].
+    #[cfg(feature = "des")]
+    pub fn authenticate_legacy(&mut self, key_number: u8, key: &[u8; 16]) -> Result<()> {
+        self.end_session();
+        let cipher = des::TdesEde2::new(GenericArray::from_slice(key));
+        let rnd_a: [u8; 8] = random()?;
+
+        // Legacy mode deciphers to send and enciphers to receive, with the
+        // chaining reset for each message.
+        let challenge = self.handshake_frame(CMD_AUTHENTICATE_LEGACY, &[key_number], ADDITIONAL_FRAME)?;
+        let rnd_b = decrypt_exact::<_, 8>(&cipher, &mut [0; 8], &challenge)?;
+        let mut token = rnd_a.to_vec();
+        token.extend(rotate_left(&rnd_b));
+        legacy_send(&cipher, &mut token);
+
+        let answer = self.handshake_frame(ADDITIONAL_FRAME, &token, OPERATION_OK)?;
+        if rotate_left(&rnd_a) != decrypt_exact::<_, 8>(&cipher, &mut [0; 8], &answer)? {
+            return Err(Error::CardAuthentication);
+        }
+
+        let mut session_key = session_key(&rnd_a[..4], &rnd_b[..4], &rnd_a[4..], &rnd_b[4..]);
+        if key[..8] == key[8..] {
+            // Single DES: the session key too.
+            session_key.copy_within(..8, 8);
+        }
+        self.authenticated = Some(key_number);
+        self.session_key = Some(session_key);
+        Ok(())
+    }
+
+    /// Authenticate with AES key `key_number` of the selected application
+    /// (AUTHENTICATE_AES, 0xAA), starting the secure messaging.  A refused
+    /// key fails with [`Error::CardAuthentication`].
+    #[cfg(feature = "aes")]
+    pub fn authenticate_aes(&mut self, key_number: u8, key: &[u8; 16]) -> Result<()> {
+        self.end_session();
+        let cipher = aes::Aes128::new(GenericArray::from_slice(key));
+        let rnd_a: [u8; 16] = random()?;
+
+        // Each message is chained on the last block of the previous one.
+        let mut iv = [0; 16];
+        let challenge = self.handshake_frame(CMD_AUTHENTICATE_AES, &[key_number], ADDITIONAL_FRAME)?;
+        let rnd_b = decrypt_exact::<_, 16>(&cipher, &mut iv, &challenge)?;
+        let mut token = rnd_a.to_vec();
+        token.extend(rotate_left(&rnd_b));
+        cbc_encrypt(&cipher, &mut iv, &mut token);
+
+        let answer = self.handshake_frame(ADDITIONAL_FRAME, &token, OPERATION_OK)?;
+        if rotate_left(&rnd_a) != decrypt_exact::<_, 16>(&cipher, &mut iv, &answer)? {
+            return Err(Error::CardAuthentication);
+        }
+
+        let session_key = session_key(&rnd_a[..4], &rnd_b[..4], &rnd_a[12..], &rnd_b[12..]);
+        self.authenticated = Some(key_number);
+        self.session_key = Some(session_key);
+        self.messaging = Some(SecureMessaging::new(&session_key));
+        Ok(())
+    }
+
+    /// Send one authentication frame and check the card answers with
+    /// `expected` status, as refused keys answer with an error status.
+    #[cfg(any(feature = "des", feature = "aes"))]
+    fn handshake_frame(&mut self, command: u8, data: &[u8], expected: u8) -> Result<Vec<u8>> {
+        match self.command(command, data) {
+            Ok((status, answer)) if status == expected => Ok(answer),
+            Ok(_) | Err(Error::Desfire(_)) => Err(Error::CardAuthentication),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Forget the authentication, as the card does on errors and
+    /// application selection.
+    fn end_session(&mut self) {
+        self.authenticated = None;
+        self.session_key = None;
+        #[cfg(feature = "aes")]
+        {
+            self.messaging = None;
+        }
+    }
+
+    /// Read with `command` in `mode`.  An enciphered answer holds `len`
+    /// bytes of data if known.
+    fn read(&mut self, command: u8, params: &[u8], mode: CommunicationMode, len: Option<usize>) -> Result<Vec<u8>> {
+        match mode {
+            CommunicationMode::Plain => self.transceive(command, params),
+            // The MAC of a MACed file is the one of every answer.
+            CommunicationMode::Maced => {
+                self.require_messaging()?;
+                self.transceive(command, params)
+            }
+            CommunicationMode::Enciphered => self.read_enciphered(command, params, len),
+        }
+    }
+
+    #[cfg(feature = "aes")]
+    fn read_enciphered(&mut self, command: u8, params: &[u8], len: Option<usize>) -> Result<Vec<u8>> {
+        self.require_messaging()?;
+        // Taken out for the exchange; an error ends the session anyway.
+        let Some(mut messaging) = self.messaging.take() else {
+            return Err(no_messaging());
+        };
+        messaging.cmac(&[&[command], params].concat());
+        let answer = self.exchange_frames(command, params)?;
+        let data = messaging.decipher(&answer, len)?;
+        self.messaging = Some(messaging);
+        Ok(data)
+    }
+
+    #[cfg(not(feature = "aes"))]
+    fn read_enciphered(&mut self, _command: u8, _params: &[u8], _len: Option<usize>) -> Result<Vec<u8>> {
+        Err(no_messaging())
+    }
+
+    /// Write `data` after `header` with `command` in `mode`.
+    fn write(&mut self, command: u8, header: &[u8], data: &[u8], mode: CommunicationMode) -> Result<()> {
+        let payload = [header, data].concat();
+        match mode {
+            CommunicationMode::Plain => self.transceive(command, &payload).map(drop),
+            _ => self.write_secure(command, header, &payload, mode),
+        }
+    }
+
+    /// A MACed write appends the CMAC of the command, an enciphered one
+    /// enciphers the data and its CRC32.
+    #[cfg(feature = "aes")]
+    fn write_secure(&mut self, command: u8, header: &[u8], payload: &[u8], mode: CommunicationMode) -> Result<()> {
+        self.require_messaging()?;
+        let Some(mut messaging) = self.messaging.take() else {
+            return Err(no_messaging());
+        };
+        let command_bytes = [&[command], payload].concat();
+        let frame = if mode == CommunicationMode::Maced {
+            [payload, &messaging.cmac(&command_bytes)].concat()
+        } else {
+            let data = &payload[header.len()..];
+            [header, &messaging.encipher(data, &crc32(&command_bytes))].concat()
+        };
+        let answer = self.exchange_frames(command, &frame)?;
+        messaging.verify(&answer)?;
+        self.messaging = Some(messaging);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "aes"))]
+    fn write_secure(&mut self, _command: u8, _header: &[u8], _payload: &[u8], _mode: CommunicationMode) -> Result<()> {
+        Err(no_messaging())
+    }
+
+    /// Fail unless an AES session runs the secure messaging.
+    fn require_messaging(&self) -> Result<()> {
+        #[cfg(feature = "aes")]
+        if self.messaging.is_some() {
+            return Ok(());
+        }
+        Err(no_messaging())
+    }
+
+    /// Send `command` and collect the answer across additional frames, with
+    /// the CMAC of an AES session updated and checked.
+    fn transceive(&mut self, command: u8, data: &[u8]) -> Result<Vec<u8>> {
+        #[cfg(feature = "aes")]
+        if let Some(mut messaging) = self.messaging.take() {
+            messaging.cmac(&[&[command], data].concat());
+            let answer = self.exchange_frames(command, data)?;
+            let answer = messaging.verify(&answer)?;
+            // Selecting an application ends the session.
+            if command != CMD_SELECT_APPLICATION {
+                self.messaging = Some(messaging);
+            }
+            return Ok(answer);
+        }
+
+        self.exchange_frames(command, data)
+    }
+
+    /// Send `command` with `data`, split into additional frames if needed,
+    /// and collect the answer across additional frames.
+    fn exchange_frames(&mut self, command: u8, data: &[u8]) -> Result<Vec<u8>> {
+        let mut chunks = data.chunks(MAX_FRAME_DATA);
+        let (mut status, mut answer) = self.command(command, chunks.next().unwrap_or(&[]))?;
+        for chunk in chunks {
+            if status != ADDITIONAL_FRAME {
+                return Err(Error::UnexpectedResponse("DESFire card did not ask for the rest of the data!"));
+            }
+            (status, answer) = self.command(ADDITIONAL_FRAME, chunk)?;
+        }
+        while status == ADDITIONAL_FRAME {
+            let (next_status, next) = self.command(ADDITIONAL_FRAME, &[])?;
+            answer.extend_from_slice(&next);
+            status = next_status;
+        }
+
+        Ok(answer)
+    }
+
+    /// Send one wrapped frame, returning the status and data of the answer.
+    /// Statuses other than OK and ADDITIONAL_FRAME are errors, and end the
+    /// session.
+    fn command(&mut self, command: u8, data: &[u8]) -> Result<(u8, Vec<u8>)> {
+        if data.len() > 0xFF {
+            return Err(Error::InvalidArgument(format!("a DESFire frame carries at most 255 bytes, got {}", data.len())));
+        }
+        let answer = self.channel.transceive(&Apdu::new(CLA, command, 0x00, 0x00).data(data).le(0x100))?;
+        if answer.status.sw1() != SW1 {
+            return Err(Error::UnexpectedResponse("DESFire response status word is not 91xx!"));
+        }
+        let status = answer.status.sw2();
+        if status != OPERATION_OK && status != ADDITIONAL_FRAME {
+            self.end_session();
+            return Err(Error::Desfire(status));
+        }
+
+        Ok((status, answer.data))
+    }
+}
+
+/// The EV1 secure messaging of an AES session: the session key and the IV
+/// chained through every command and answer.
+#[cfg(feature = "aes")]
+struct SecureMessaging {
+    cipher: aes::Aes128,
+    iv: [u8; 16],
+}
+
+#[cfg(feature = "aes")]
+impl SecureMessaging {
+    fn new(session_key: &[u8; 16]) -> Self {
+        Self { cipher: aes::Aes128::new(GenericArray::from_slice(session_key)), iv: [0; 16] }
+    }
+
+    /// The CMAC of `data`, chained on the IV, which it becomes.  Returns the
+    /// first 8 bytes, as sent.
+    fn cmac(&mut self, data: &[u8]) -> [u8; 8] {
+        let mut l = [0; 16];
+        self.cipher.encrypt_block(GenericArray::from_mut_slice(&mut l));
+        let k1 = cmac_subkey(l);
+        let k2 = cmac_subkey(k1);
+
+        let mut blocks = data.to_vec();
+        let complete = !data.is_empty() && data.len() % 16 == 0;
+        if !complete {
+            blocks.push(0x80);
+            blocks.resize((blocks.len() + 15) / 16 * 16, 0x00);
+        }
+        let last = blocks.len() - 16;
+        let subkey = if complete { k1 } else { k2 };
+        blocks[last..].iter_mut().zip(subkey).for_each(|(b, k)| *b ^= k);
+        cbc_encrypt(&self.cipher, &mut self.iv, &mut blocks);
+
+        let mut mac = [0; 8];
+        mac.copy_from_slice(&self.iv[..8]);
+        mac
+    }
+
+    /// Check the CMAC ending `answer`, computed over its data and the OK
+    /// status, and return the data.
+    fn verify(&mut self, answer: &[u8]) -> Result<Vec<u8>> {
+        let Some(len) = answer.len().checked_sub(8) else {
+            return Err(Error::UnexpectedResponse("DESFire response has no CMAC!"));
+        };
+        let (data, mac) = answer.split_at(len);
+        if self.cmac(&[data, &[OPERATION_OK]].concat()) != mac {
+            return Err(Error::UnexpectedResponse("DESFire response CMAC does not match!"));
+        }
+        Ok(data.to_vec())
+    }
+
+    /// Encipher `data` followed by `crc`, padded with zeros.
+    fn encipher(&mut self, data: &[u8], crc: &[u8; 4]) -> Vec<u8> {
+        let mut plain = [data, crc].concat();
+        plain.resize((plain.len() + 15) / 16 * 16, 0x00);
+        cbc_encrypt(&self.cipher, &mut self.iv, &mut plain);
+        plain
+    }
+
+    /// Decipher an answer: data, CRC32 of the data and the OK status, zero
+    /// padding.  Without `len`, the data ends where the CRC matches.
+    fn decipher(&mut self, answer: &[u8], len: Option<usize>) -> Result<Vec<u8>> {
+        if answer.is_empty() || answer.len() % 16 != 0 {
+            return Err(Error::UnexpectedResponse("DESFire enciphered response is not whole blocks!"));
+        }
+        let mut plain = answer.to_vec();
+        cbc_decrypt(&self.cipher, &mut self.iv, &mut plain);
+
+        let crc_matches = |n: usize| {
+            plain.get(n..n + 4).is_some_and(|crc| crc == crc32(&[&plain[..n], &[OPERATION_OK]].concat()))
+                && plain[n + 4..].iter().all(|&b| b == 0x00)
+        };
+        let len = match len {
+            Some(len) => Some(len).filter(|&len| crc_matches(len)),
+            None => (0..=plain.len() - 4).rev().take(16).find(|&n| crc_matches(n)),
+        };
+        let Some(len) = len else {
+            return Err(Error::UnexpectedResponse("DESFire enciphered response CRC does not match!"));
+        };
+        plain.truncate(len);
+        Ok(plain)
+    }
+}
+
+/// File number, 3 byte offset and 3 byte length of READ_DATA,
+/// READ_RECORDS, WRITE_DATA and WRITE_RECORD.
+fn file_range(file: u8, offset: u32, len: u32) -> Result<[u8; 7]> {
+    if offset > MAX_APPLICATION_ID || len > MAX_APPLICATION_ID {
+        return Err(Error::InvalidArgument(format!("DESFire offsets and lengths are 3 bytes, got {} and {}", offset, len)));
+    }
+    let [o0, o1, o2, _] = offset.to_le_bytes();
+    let [l0, l1, l2, _] = len.to_le_bytes();
+    Ok([file, o0, o1, o2, l0, l1, l2])
+}
+
+fn no_messaging() -> Error {
+    Error::InvalidArgument("MACed and enciphered communication need an AES session".to_owned())
+}
+
+/// The session key: 4 bytes of each half of RndA and RndB, interleaved.
+#[cfg(any(feature = "des", feature = "aes"))]
+fn session_key(a0: &[u8], b0: &[u8], a1: &[u8], b1: &[u8]) -> [u8; 16] {
+    let mut key = [0; 16];
+    for (dst, src) in key.chunks_exact_mut(4).zip([a0, b0, &a1[a1.len() - 4..], &b1[b1.len() - 4..]]) {
+        dst.copy_from_slice(&src[..4]);
+    }
+    key
+}
+
+/// The CRC32 of EV1 secure messaging: IEEE 802.3 without the final XOR.
+#[cfg(feature = "aes")]
+fn crc32(data: &[u8]) -> [u8; 4] {
+    let crc = data.iter().fold(0xFFFF_FFFFu32, |crc, &byte| {
+        (0..8).fold(crc ^ u32::from(byte), |crc, _| if crc & 1 != 0 { crc >> 1 ^ 0xEDB8_8320 } else { crc >> 1 })
+    });
+    crc.to_le_bytes()
+}
+
+/// Derive a CMAC subkey: shift left by one bit, XOR 0x87 if a bit fell off.
+#[cfg(feature = "aes")]
+fn cmac_subkey(key: [u8; 16]) -> [u8; 16] {
+    let key = u128::from_be_bytes(key);
+    let shifted = key << 1 ^ if key >> 127 == 1 { 0x87 } else { 0 };
+    shifted.to_be_bytes()
+}
+
+#[cfg(any(feature = "des", feature = "aes"))]
+fn random<const N: usize>() -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    getrandom::getrandom(&mut bytes).map_err(|e| Error::Io(e.into()))?;
+    Ok(bytes)
+}
+
+#[cfg(any(feature = "des", feature = "aes"))]
+fn rotate_left(bytes: &[u8]) -> Vec<u8> {
+    let mut rotated = bytes.to_vec();
+    rotated.rotate_left(1);
+    rotated
+}
+
+/// Encipher `data` in place in CBC mode, chained on `iv`, which ends up as
+/// the last block.
+#[cfg(any(feature = "des", feature = "aes"))]
+fn cbc_encrypt<C: BlockEncrypt>(cipher: &C, iv: &mut [u8], data: &mut [u8]) {
+    for block in data.chunks_exact_mut(C::block_size()) {
+        block.iter_mut().zip(iv.iter()).for_each(|(b, iv)| *b ^= iv);
+        cipher.encrypt_block(GenericArray::from_mut_slice(block));
+        iv.copy_from_slice(block);
+    }
+}
+
+/// Decipher `data` in place in CBC mode, chained on `iv`, which ends up as
+/// the last cipher block.
+#[cfg(any(feature = "des", feature = "aes"))]
+fn cbc_decrypt<C: BlockDecrypt>(cipher: &C, iv: &mut [u8], data: &mut [u8]) {
+    for block in data.chunks_exact_mut(C::block_size()) {
+        let mut cipher_block = [0; 16];
+        let cipher_block = &mut cipher_block[..block.len()];
+        cipher_block.copy_from_slice(block);
+        cipher.decrypt_block(GenericArray::from_mut_slice(block));
+        block.iter_mut().zip(iv.iter()).for_each(|(b, iv)| *b ^= iv);
+        iv.copy_from_slice(cipher_block);
+    }
+}
+
+/// Decipher exactly `N` bytes of a handshake, see [`cbc_decrypt`].
+#[cfg(any(feature = "des", feature = "aes"))]
+fn decrypt_exact<C: BlockDecrypt + BlockSizeUser, const N: usize>(cipher: &C, iv: &mut [u8], data: &[u8]) -> Result<[u8; N]> {
+    if data.len() != N {
+        return Err(Error::CardAuthentication);
+    }
+    let mut plain = [0; N];
+    plain.copy_from_slice(data);
+    cbc_decrypt(cipher, iv, &mut plain);
+    Ok(plain)
+}
+
+/// The legacy send mode: each block XORed with the previous result, then
+/// deciphered.
+#[cfg(feature = "des")]
+fn legacy_send<C: BlockDecrypt>(cipher: &C, data: &mut [u8]) {
+    let mut previous = [0; 8];
+    for block in data.chunks_exact_mut(8) {
+        block.iter_mut().zip(previous).for_each(|(b, p)| *b ^= p);
+        cipher.decrypt_block(GenericArray::from_mut_slice(block));
+        previous.copy_from_slice(block);
+    }
+}
+
+#[cfg(all(test, feature = "aes"))]
+mod tests {
+    use super::*;
+
+    /// Key and message of the AES-128 examples of NIST SP 800-38B, D.1.
+    const NIST_KEY: [u8; 16] = [0x2B, 0x7E, 0x15, 0x16, 0x28, 0xAE, 0xD2, 0xA6, 0xAB, 0xF7, 0x15, 0x88, 0x09, 0xCF, 0x4F, 0x3C];
+    const NIST_MESSAGE: [u8; 64] = [
+        0x6B, 0xC1, 0xBE, 0xE2, 0x2E, 0x40, 0x9F, 0x96, 0xE9, 0x3D, 0x7E, 0x11, 0x73, 0x93, 0x17, 0x2A,
+        0xAE, 0x2D, 0x8A, 0x57, 0x1E, 0x03, 0xAC, 0x9C, 0x9E, 0xB7, 0x6F, 0xAC, 0x45, 0xAF, 0x8E, 0x51,
+        0x30, 0xC8, 0x1C, 0x46, 0xA3, 0x5C, 0xE4, 0x11, 0xE5, 0xFB, 0xC1, 0x19, 0x1A, 0x0A, 0x52, 0xEF,
+        0xF6, 0x9F, 0x24, 0x45, 0xDF, 0x4F, 0x9B, 0x17, 0xAD, 0x2B, 0x41, 0x7B, 0xE6, 0x6C, 0x37, 0x10,
+    ];
+
+    #[test]
+    fn storage_bytes() {
+        // A DESFire EV1 8K: hardware, software, UID, batch, production date.
+        let mut version = [0x04, 0x01, 0x01, 0x01, 0x00, 0x1A, 0x05, 0x04, 0x01, 0x01, 0x01, 0x04, 0x1A, 0x05].to_vec();
+        version.extend_from_slice(&[0; 14]);
+        let mut version = DesfireVersion::from_bytes(&version).unwrap();
+        assert_eq!(version.storage_bytes(), Some(8192));
+        version.software.storage_size = 0x3E;
+        assert_eq!(version.storage_bytes(), Some(1 << 31));
+        version.software.storage_size = 0x40;
+        assert_eq!(version.storage_bytes(), None);
+        version.software.storage_size = 0xFF;
+        assert_eq!(version.storage_bytes(), None);
+    }
+
+    #[test]
+    fn cmac_subkeys() {
+        let l = [0x7D, 0xF7, 0x6B, 0x0C, 0x1A, 0xB8, 0x99, 0xB3, 0x3E, 0x42, 0xF0, 0x47, 0xB9, 0x1B, 0x54, 0x6F];
+        let k1 = [0xFB, 0xEE, 0xD6, 0x18, 0x35, 0x71, 0x33, 0x66, 0x7C, 0x85, 0xE0, 0x8F, 0x72, 0x36, 0xA8, 0xDE];
+        let k2 = [0xF7, 0xDD, 0xAC, 0x30, 0x6A, 0xE2, 0x66, 0xCC, 0xF9, 0x0B, 0xC1, 0x1E, 0xE4, 0x6D, 0x51, 0x3B];
+        assert_eq!(cmac_subkey(l), k1);
+        assert_eq!(cmac_subkey(k1), k2);
+    }
+
+    #[test]
+    fn cmac_nist_vectors() {
+        let vectors: [(usize, [u8; 16]); 4] = [
+            (0, [0xBB, 0x1D, 0x69, 0x29, 0xE9, 0x59, 0x37, 0x28, 0x7F, 0xA3, 0x7D, 0x12, 0x9B, 0x75, 0x67, 0x46]),
+            (16, [0x07, 0x0A, 0x16, 0xB4, 0x6B, 0x4D, 0x41, 0x44, 0xF7, 0x9B, 0xDD, 0x9D, 0xD0, 0x4A, 0x28, 0x7C]),
+            (40, [0xDF, 0xA6, 0x67, 0x47, 0xDE, 0x9A, 0xE6, 0x30, 0x30, 0xCA, 0x32, 0x61, 0x14, 0x97, 0xC8, 0x27]),
+            (64, [0x51, 0xF0, 0xBE, 0xBF, 0x7E, 0x3B, 0x9D, 0x92, 0xFC, 0x49, 0x74, 0x17, 0x79, 0x36, 0x3C, 0xFE]),
+        ];
+        for (len, tag) in vectors {
+            let mut messaging = SecureMessaging::new(&NIST_KEY);
+            assert_eq!(messaging.cmac(&NIST_MESSAGE[..len]), tag[..8], "message of {} bytes", len);
+            // The whole tag is the IV of the next command.
+            assert_eq!(messaging.iv, tag, "message of {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn verify_checks_the_cmac() {
+        let mut sender = SecureMessaging::new(&NIST_KEY);
+        let data = [0x01, 0x02, 0x03];
+        let answer = [&data[..], &sender.cmac(&[0x01, 0x02, 0x03, OPERATION_OK])].concat();
+        assert_eq!(SecureMessaging::new(&NIST_KEY).verify(&answer).unwrap(), data);
+
+        let mut tampered = answer.clone();
+        tampered[0] ^= 0x01;
+        assert!(SecureMessaging::new(&NIST_KEY).verify(&tampered).is_err());
+        assert!(SecureMessaging::new(&NIST_KEY).verify(&answer[..7]).is_err());
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        // The IEEE 802.3 check value 0xCBF43926, without the final XOR.
+        assert_eq!(crc32(b"123456789"), 0x340B_C6D9u32.to_le_bytes());
+        assert_eq!(crc32(&[]), [0xFF; 4]);
+    }
+
+    /// Encipher `data` as the card does an answer.
+    fn enciphered_answer(data: &[u8]) -> Vec<u8> {
+        let crc = crc32(&[data, &[OPERATION_OK]].concat());
+        SecureMessaging::new(&NIST_KEY).encipher(data, &crc)
+    }
+
+    #[test]
+    fn decipher_round_trip() {
+        // Trailing zeros of the data look like padding, and must be kept.
+        let mut long = vec![0x00; 20];
+        long[0] = 0xA5;
+        let answers = [vec![], vec![0x42], vec![0x11, 0x22, 0x00, 0x00], vec![0x00; 12], long];
+        for data in &answers {
+            let answer = enciphered_answer(data);
+            assert_eq!(answer.len() % 16, 0);
+            assert_eq!(SecureMessaging::new(&NIST_KEY).decipher(&answer, Some(data.len())).unwrap(), *data, "{:02X?}", data);
+            assert_eq!(SecureMessaging::new(&NIST_KEY).decipher(&answer, None).unwrap(), *data, "{:02X?}", data);
+        }
+    }
+
+    #[test]
+    fn decipher_rejects_bad_answers() {
+        let answer = enciphered_answer(&[0x11, 0x22, 0x00, 0x00]);
+        assert!(SecureMessaging::new(&NIST_KEY).decipher(&answer, Some(3)).is_err());
+        assert!(SecureMessaging::new(&NIST_KEY).decipher(&answer[..15], None).is_err());
+        let mut tampered = answer;
+        tampered[0] ^= 0x01;
+        assert!(SecureMessaging::new(&NIST_KEY).decipher(&tampered, None).is_err());
+    }
+}
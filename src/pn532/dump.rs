@@ -0,0 +1,94 @@
+//! Writing a dump back to a tag: which blocks or pages a restore may touch,
+//! and what it changed or would change.
+
+use std::fmt;
+
+use crate::pn532::{Block, NtagVariant, Page};
+
+/// Every page of an NTAG21x or Ultralight EV1 tag, read by
+/// [`PN532::dump_ntag`](crate::pn532::PN532::dump_ntag).  PWD and PACK read
+/// as zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NtagDump {
+    pub variant: NtagVariant,
+    pub pages: Vec<[u8; 4]>,
+}
+
+/// What a restore may write besides the data blocks or user pages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RestoreOptions {
+    /// Only compare, write nothing.
+    pub dry_run: bool,
+    /// Also write MIFARE Classic block 0, or NTAG pages 0 to 3 (UID, static
+    /// lock bytes and capability container).  Only magic tags accept the
+    /// UID, block 0 is written as on a gen2 card, and lock bits cannot be
+    /// cleared again.
+    pub manufacturer: bool,
+    /// Also write MIFARE Classic sector trailers, or the NTAG dynamic lock
+    /// and configuration pages.  A trailer is only restored when the dump
+    /// knows both keys.  PWD and PACK are never written.
+    pub trailers: bool,
+}
+
+impl RestoreOptions {
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn manufacturer(mut self, manufacturer: bool) -> Self {
+        self.manufacturer = manufacturer;
+        self
+    }
+
+    pub fn trailers(mut self, trailers: bool) -> Self {
+        self.trailers = trailers;
+        self
+    }
+}
+
+/// A MIFARE Classic block or an NTAG page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DumpAddress {
+    Block(Block),
+    Page(Page),
+}
+
+impl fmt::Display for DumpAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DumpAddress::Block(block) => write!(f, "{}", block),
+            DumpAddress::Page(page) => write!(f, "{}", page),
+        }
+    }
+}
+
+/// A block or page whose content differs from the dump.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestoreChange {
+    pub address: DumpAddress,
+    /// What the tag holds, `None` if it could not be read.
+    pub current: Option<Vec<u8>>,
+    /// What the dump holds.
+    pub data: Vec<u8>,
+}
+
+/// Outcome of a restore.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestoreReport {
+    /// Whether this was a dry run, `changes` were then not written.
+    pub dry_run: bool,
+    /// The blocks or pages written, or to write in a dry run.
+    pub changes: Vec<RestoreChange>,
+    /// The blocks or pages left alone: excluded by the options, missing
+    /// from the dump or in a sector none of the keys opened.
+    pub skipped: Vec<DumpAddress>,
+}
+
+impl RestoreReport {
+    /// Whether the tag already matched the dump.
+    pub fn is_unchanged(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
@@ -0,0 +1,198 @@
+use thiserror::Error;
+
+use crate::pn532::frame::FrameError;
+use crate::pn532::{Block, Command, Sector, SelfTestReport, Status, StatusWord};
+
+/// Errors returned by the PN532 driver.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The SPI transport failed.
+    #[error("SPI error: {0}")]
+    Spi(#[from] rppal::spi::Error),
+    /// A GPIO pin (CS, IRQ or reset) could not be driven.
+    #[error("GPIO error: {0}")]
+    Gpio(#[from] rppal::gpio::Error),
+    /// Reading or writing a file failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// The response frame is malformed.
+    #[error("invalid frame: {0}")]
+    Frame(#[from] FrameError),
+    /// The PN532 rejected the command frame with its syntax error frame: the
+    /// frame was well formed but its content was not (e.g. an unknown
+    /// command or wrong parameters), so the card was never involved.
+    #[error("the PN532 rejected the command frame as malformed")]
+    SyntaxError,
+    /// The PN532 did not acknowledge the command frame.
+    #[error("did not receive expected ACK from PN532")]
+    Ack,
+    /// The response is well formed but not what the command expects.
+    #[error("unexpected response: {0}")]
+    UnexpectedResponse(&'static str),
+    /// The PN532 reported an error in the status byte of its response.
+    #[error("PN532 error: {0}")]
+    Status(Status),
+    /// The PN532 did not answer within the timeout.
+    #[error("timed out waiting for the PN532")]
+    Timeout,
+    /// A parameter is out of the range accepted by the PN532.
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+    /// The card refused the key for a MIFARE Classic sector.
+    #[error("authentication of {0} failed")]
+    Authentication(Sector),
+    /// A plain block write targeted a MIFARE Classic sector trailer.
+    #[error("{0} is a sector trailer, use write_sector_trailer")]
+    SectorTrailer(Block),
+    /// A plain block write targeted MIFARE Classic block 0, which only magic
+    /// clone cards accept.
+    #[error("block 0 is the manufacturer block, use magic_gen1a_write_block0 or magic_gen2_write_block0")]
+    ManufacturerBlock,
+    /// The card refused the key of a 3DES or AES authentication, or its
+    /// answer did not prove it knows the key.
+    #[error("card authentication failed")]
+    CardAuthentication,
+    /// A FeliCa card refused a command with these status flags.
+    #[error("FeliCa card error, status flags {status1:#04x} {status2:#04x}")]
+    Felica { status1: u8, status2: u8 },
+    /// A MIFARE DESFire card refused a command with this status.
+    #[error("DESFire card error, status {0:#04x}")]
+    Desfire(u8),
+    /// An ISO/IEC 7816-4 card answered an APDU with this status.
+    #[error("APDU error: {0}")]
+    Apdu(StatusWord),
+    /// The reader is in read-only mode and the operation would write to a tag.
+    #[error("writing is disabled on this reader")]
+    WriteDisabled,
+    /// The PN532 is busy with another command.
+    #[error("PN532 is busy")]
+    Busy,
+    /// The PN532 stopped answering and the
+    /// [`Watchdog`](crate::pn532::Watchdog) reset it.  The command was not
+    /// executed, and the selected targets and GPIO states are lost.
+    #[error("the PN532 stopped answering and was reset")]
+    ChipReset,
+    /// The PN532 failed the self test run when the reader was initialized.
+    #[error("PN532 self test failed: {0}")]
+    SelfTest(Box<SelfTestReport>),
+    /// The operation was cancelled before it completed.
+    #[error("operation cancelled")]
+    Cancelled,
+    /// `source` occurred while executing the command with code `command`.
+    /// The parameters sent and the response (the raw frame, or its data when
+    /// the frame was valid but could not be parsed) are only recorded when
+    /// [`Options::error_context`](crate::pn532::Options::error_context) is on.
+    /// `attempts` counts the tries under the
+    /// [`RetryPolicy`](crate::pn532::RetryPolicy) of the command.
+    /// Use [`Error::root`] to match on the underlying error.
+    #[error("{} failed{}: {source}{}", command_name(*.command), retried(*.attempts), exchange(.sent, .response))]
+    Command {
+        command: u8,
+        attempts: u32,
+        sent: Option<Vec<u8>>,
+        response: Option<Vec<u8>>,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Build the error for a PN532 status byte.
+    pub(crate) fn status(code: u8) -> Self {
+        Error::Status(Status::from(code))
+    }
+
+    /// Wrap this error with the command that caused it. An error that already
+    /// has a context only gets the bytes it is missing.
+    pub(crate) fn in_command(self, command: u8, sent: Option<&[u8]>, response: Option<&[u8]>) -> Self {
+        match self {
+            Error::Command { command, attempts, sent: recorded_sent, response: recorded_response, source } => Error::Command {
+                command,
+                attempts,
+                sent: recorded_sent.or_else(|| sent.map(<[u8]>::to_vec)),
+                response: recorded_response.or_else(|| response.map(<[u8]>::to_vec)),
+                source,
+            },
+            source => Error::Command {
+                command,
+                attempts: 1,
+                sent: sent.map(<[u8]>::to_vec),
+                response: response.map(<[u8]>::to_vec),
+                source: Box::new(source),
+            },
+        }
+    }
+
+    /// Record that the command failed after `attempts` tries.
+    pub(crate) fn after_attempts(mut self, count: u32) -> Self {
+        if let Error::Command { attempts, .. } = &mut self {
+            *attempts = count;
+        }
+        self
+    }
+
+    /// Whether trying the command again may succeed: the ACK or the
+    /// response got corrupted on the way.
+    pub fn is_transient(&self) -> bool {
+        match self.root() {
+            Error::Ack => true,
+            Error::Frame(e) => e.is_corruption(),
+            _ => false,
+        }
+    }
+
+    /// Whether the SPI bus or a GPIO pin failed, rather than the PN532.
+    pub fn is_transport(&self) -> bool {
+        matches!(self.root(), Error::Spi(_) | Error::Gpio(_))
+    }
+
+    /// How many times the command was tried before failing.
+    pub fn attempts(&self) -> u32 {
+        match self {
+            Error::Command { attempts, .. } => *attempts,
+            _ => 1,
+        }
+    }
+
+    /// The underlying error, without the command context.
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Command { source, .. } => source.root(),
+            _ => self,
+        }
+    }
+
+    /// The PN532 status code, if this error was reported by the chip.
+    pub fn chip_status(&self) -> Option<Status> {
+        match self.root() {
+            Error::Status(status) => Some(*status),
+            _ => None,
+        }
+    }
+}
+
+fn command_name(code: u8) -> String {
+    match Command::from_code(code) {
+        Some(command) => format!("{:?}", command),
+        None => format!("command {:#04x}", code),
+    }
+}
+
+fn retried(attempts: u32) -> String {
+    if attempts > 1 {
+        format!(" after {} attempts", attempts)
+    } else {
+        String::new()
+    }
+}
+
+fn exchange(sent: &Option<Vec<u8>>, response: &Option<Vec<u8>>) -> String {
+    let mut exchange = String::new();
+    if let Some(sent) = sent {
+        exchange += &format!(" (sent {:02X?}", sent);
+        if let Some(response) = response {
+            exchange += &format!(", received {:02X?}", response);
+        }
+        exchange += ")";
+    }
+    exchange
+}
@@ -0,0 +1,93 @@
+//! FeliCa Read and Write Without Encryption frames, exchanged with a card
+//! found by [`PN532::felica_poll`](crate::pn532::PN532::felica_poll).
+//!
+//! The PN532 carries FeliCa frames as is: each starts with its own length
+//! byte, then the command code and the IDm of the card.
+
+use crate::pn532::{Error, Result};
+
+pub(crate) const CMD_READ_WITHOUT_ENCRYPTION: u8 = 0x06;
+pub(crate) const CMD_WRITE_WITHOUT_ENCRYPTION: u8 = 0x08;
+
+/// Most services a command addresses.
+pub const MAX_SERVICES: usize = 16;
+
+/// A block of a FeliCa card: its number in the service at index `service`
+/// of the service code list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FelicaBlock {
+    pub service: u8,
+    pub number: u16,
+}
+
+impl FelicaBlock {
+    /// Block `number` of the first service.
+    pub fn new(number: u16) -> Self {
+        Self { service: 0, number }
+    }
+
+    /// The block list element: 2 bytes for block numbers up to 255, 3
+    /// otherwise.
+    fn push_element(self, frame: &mut Vec<u8>) {
+        match u8::try_from(self.number) {
+            Ok(number) => frame.extend_from_slice(&[0x80 | self.service, number]),
+            Err(_) => {
+                let [lo, hi] = self.number.to_le_bytes();
+                frame.extend_from_slice(&[self.service, lo, hi]);
+            }
+        }
+    }
+}
+
+/// Build the frame of command `code` for the card `idm`: length byte,
+/// code, IDm, service code list, block list and `data`.
+pub(crate) fn command(code: u8, idm: &[u8; 8], service_codes: &[u16], blocks: &[FelicaBlock], data: &[u8]) -> Result<Vec<u8>> {
+    if service_codes.is_empty() || service_codes.len() > MAX_SERVICES {
+        return Err(Error::InvalidArgument(format!("a FeliCa command addresses 1 to {} services, got {}", MAX_SERVICES, service_codes.len())));
+    }
+    if let Some(block) = blocks.iter().find(|block| usize::from(block.service) >= service_codes.len()) {
+        return Err(Error::InvalidArgument(format!("block {} is in service {}, there are {}", block.number, block.service, service_codes.len())));
+    }
+    if blocks.is_empty() || blocks.len() > u8::MAX as usize {
+        return Err(Error::InvalidArgument(format!("a FeliCa command addresses 1 to 255 blocks, got {}", blocks.len())));
+    }
+
+    let mut frame = vec![0, code];
+    frame.extend_from_slice(idm);
+    frame.push(service_codes.len() as u8);
+    for code in service_codes {
+        frame.extend_from_slice(&code.to_le_bytes());
+    }
+    frame.push(blocks.len() as u8);
+    for block in blocks {
+        block.push_element(&mut frame);
+    }
+    frame.extend_from_slice(data);
+    frame[0] = u8::try_from(frame.len())
+        .map_err(|_| Error::InvalidArgument(format!("a FeliCa frame is at most 255 bytes, got {}", frame.len())))?;
+
+    Ok(frame)
+}
+
+/// Check the answer to command `code` from card `idm`: length byte,
+/// response code, IDm and status flags.  Returns what follows them.
+pub(crate) fn parse_response<'a>(code: u8, idm: &[u8; 8], answer: &'a [u8]) -> Result<&'a [u8]> {
+    let [_, response_code, ref rest @ ..] = answer[..] else {
+        return Err(Error::UnexpectedResponse("FeliCa response is too short!"));
+    };
+    if response_code != code + 1 {
+        return Err(Error::UnexpectedResponse("FeliCa response code does not match the command!"));
+    }
+    let (Some(card), Some(&[status1, status2])) = (rest.get(..8), rest.get(8..10)) else {
+        return Err(Error::UnexpectedResponse("FeliCa response is too short!"));
+    };
+    if card != idm {
+        return Err(Error::UnexpectedResponse("FeliCa response comes from another card!"));
+    }
+    if status1 != 0x00 {
+        return Err(Error::Felica { status1, status2 });
+    }
+
+    Ok(&rest[10..])
+}
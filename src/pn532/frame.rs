@@ -0,0 +1,417 @@
+//! The PN532 frame format (UM0701-02 §6.2.1), independent of any transport.
+//!
+//! A normal information frame is
+//! `00 00 FF LEN LCS TFI PD0..PDn DCS 00`, where LEN counts TFI and the data,
+//! `LEN + LCS` and `TFI + PD0 + .. + PDn + DCS` are 0 modulo 256, and TFI is
+//! D4 from host to PN532 and D5 the other way.
+//!
+//! Longer data goes in an extended information frame,
+//! `00 00 FF FF FF LENM LENL LCS TFI PD0..PDn DCS 00`, where
+//! `LENM + LENL + LCS` is 0 modulo 256.  Both sides only use it for data over
+//! 255 bytes.
+
+use std::fmt;
+use std::result;
+
+use thiserror::Error as ThisError;
+
+use crate::pn532::{Command, Error, Result, Status, HOSTTOPN532, PN532TOHOST};
+
+pub const PREAMBLE: u8 = 0x00;
+pub const STARTCODE1: u8 = 0x00;
+pub const STARTCODE2: u8 = 0xFF;
+pub const POSTAMBLE: u8 = 0x00;
+
+/// ACK frame, sent by the PN532 after a valid command frame, or by the host
+/// to abort the command being executed.
+pub const ACK: &[u8] = &[0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00];
+/// NACK frame, sent by the host to have the PN532 repeat its last response.
+pub const NACK: &[u8] = &[0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00];
+
+/// Data of the syntax error frame, sent by the PN532 when a command frame is
+/// valid but its content is not.
+const ERROR_DATA: u8 = 0x7F;
+
+/// Preamble, start code, LEN, LCS, DCS and postamble.
+pub const FRAME_OVERHEAD: usize = 7;
+/// Preamble, start code, the fixed FF FF, LENM, LENL, LCS, DCS and
+/// postamble.
+pub const EXTENDED_FRAME_OVERHEAD: usize = 10;
+/// Most data (TFI included) a normal information frame carries.
+pub const MAX_NORMAL_DATA_LEN: usize = 255;
+/// Most data (TFI included) the PN532 firmware handles in a frame.
+pub const MAX_DATA_LEN: usize = 265;
+/// Longest frame, an extended information frame with `MAX_DATA_LEN` bytes
+/// of data.
+pub const MAX_FRAME_LEN: usize = MAX_DATA_LEN + EXTENDED_FRAME_OVERHEAD;
+
+impl FrameError {
+    /// Whether the frame was damaged on the way, so that asking the PN532
+    /// to send it again with a NACK may help.
+    pub fn is_corruption(&self) -> bool {
+        !matches!(self, FrameError::UnexpectedAck)
+    }
+}
+
+/// A frame decoded by [`decode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Frame<'a> {
+    Ack,
+    Nack,
+    /// A normal or extended information frame: TFI followed by the packet
+    /// data.
+    Information(&'a [u8]),
+    /// The syntax error frame, by which the PN532 rejects a command frame
+    /// whose checksums are right but whose content is not.
+    Error,
+}
+
+/// Why bytes received from the PN532 are not a valid frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ThisError)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum FrameError {
+    /// No `00 FF` start code, e.g. the line is stuck high or low.
+    #[error("no 00 FF start code")]
+    StartCode,
+    /// The bytes end before LEN and LCS.
+    #[error("no frame length")]
+    MissingLength,
+    /// LEN (or LENM and LENL) and LCS do not sum to 0.
+    #[error("length checksum {lcs:#04x} does not match length {len}")]
+    LengthChecksum { len: u16, lcs: u8 },
+    /// The bytes end before the data and DCS announced by LEN.
+    #[error("frame truncated: needs {expected} bytes, got {actual}")]
+    Truncated { expected: usize, actual: usize },
+    /// The data and DCS do not sum to 0; holds their sum.
+    #[error("data checksum is off by {0:#04x}")]
+    DataChecksum(u8),
+    /// An ACK or NACK frame where a response was expected.
+    #[error("ACK or NACK frame instead of a response")]
+    UnexpectedAck,
+}
+
+/// The checksum making `bytes` sum to 0 modulo 256.
+pub fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0_u8, |sum, byte| sum.wrapping_add(*byte)).wrapping_neg()
+}
+
+/// Length of the frame carrying `data_len` bytes of data.
+pub fn frame_len(data_len: usize) -> usize {
+    if data_len > MAX_NORMAL_DATA_LEN {
+        data_len + EXTENDED_FRAME_OVERHEAD
+    } else {
+        data_len + FRAME_OVERHEAD
+    }
+}
+
+/// Encode `data` (TFI and packet data, 1 to `MAX_DATA_LEN` bytes) into the
+/// start of `frame`, which must hold [`frame_len`] bytes.  Data over 255
+/// bytes goes in an extended information frame.  Returns the frame length.
+pub fn encode_into(data: &[u8], frame: &mut [u8]) -> Result<usize> {
+    if data.is_empty() || data.len() > MAX_DATA_LEN {
+        return Err(Error::InvalidArgument(format!("frame data must be 1 to {} bytes, got {}", MAX_DATA_LEN, data.len())));
+    }
+    let frame_len = frame_len(data.len());
+    if frame.len() < frame_len {
+        return Err(Error::InvalidArgument(format!("a {} byte frame does not fit in {} bytes", frame_len, frame.len())));
+    }
+
+    frame[0] = PREAMBLE;
+    frame[1] = STARTCODE1;
+    frame[2] = STARTCODE2;
+    let start = if data.len() > MAX_NORMAL_DATA_LEN {
+        let [lenm, lenl] = (data.len() as u16).to_be_bytes();
+        frame[3] = 0xFF;
+        frame[4] = 0xFF;
+        frame[5] = lenm;
+        frame[6] = lenl;
+        frame[7] = checksum(&[lenm, lenl]);
+        8
+    } else {
+        let len = data.len() as u8;
+        frame[3] = len;
+        frame[4] = len.wrapping_neg();
+        5
+    };
+    frame[start..start + data.len()].copy_from_slice(data);
+    frame[start + data.len()] = checksum(data);
+    frame[start + data.len() + 1] = POSTAMBLE;
+
+    Ok(frame_len)
+}
+
+/// Encode `data` (TFI and packet data, 1 to `MAX_DATA_LEN` bytes) as an
+/// information frame.
+pub fn encode(data: &[u8]) -> Result<Vec<u8>> {
+    let mut frame = vec![0; frame_len(data.len())];
+    encode_into(data, &mut frame)?;
+    Ok(frame)
+}
+
+/// Decode the frame at the start of `bytes`.  Any number of 0x00 bytes may
+/// precede the start code, and bytes after the frame are ignored.
+pub fn decode(bytes: &[u8]) -> Result<Frame<'_>> {
+    Ok(decode_frame(bytes)?)
+}
+
+fn decode_frame(bytes: &[u8]) -> result::Result<Frame<'_>, FrameError> {
+    // Swallow all the 0x00 values that preceed 0xFF.
+    let offset = bytes.iter().take_while(|&&byte| byte == 0x00).count();
+    if offset == 0 || bytes.get(offset) != Some(&STARTCODE2) {
+        return Err(FrameError::StartCode);
+    }
+    let header = offset + 1;
+    let (len, start) = match bytes[header..] {
+        [0x00, 0xFF, ..] => return Ok(Frame::Ack),
+        [0xFF, 0x00, ..] => return Ok(Frame::Nack),
+        [0xFF, 0xFF, lenm, lenl, lcs, ..] => {
+            // Check length & length checksum match.
+            if lenm.wrapping_add(lenl).wrapping_add(lcs) != 0 {
+                return Err(FrameError::LengthChecksum { len: u16::from_be_bytes([lenm, lenl]), lcs });
+            }
+            (u16::from_be_bytes([lenm, lenl]) as usize, header + 5)
+        }
+        [0xFF, 0xFF, ..] => return Err(FrameError::MissingLength),
+        [len, lcs, ..] => {
+            if len.wrapping_add(lcs) != 0 {
+                return Err(FrameError::LengthChecksum { len: len.into(), lcs });
+            }
+            (len as usize, header + 2)
+        }
+        _ => return Err(FrameError::MissingLength),
+    };
+
+    // Data and DCS.
+    let end = start + len + 1;
+    let body = bytes.get(start..end).ok_or(FrameError::Truncated { expected: end, actual: bytes.len() })?;
+    // Check frame checksum value matches bytes.
+    let sum = body.iter().fold(0_u8, |sum, byte| sum.wrapping_add(*byte));
+    if sum != 0 {
+        return Err(FrameError::DataChecksum(sum));
+    }
+
+    match &body[..len] {
+        [ERROR_DATA] => Ok(Frame::Error),
+        data => Ok(Frame::Information(data)),
+    }
+}
+
+/// A frame shown the way a protocol analyzer would, for logs: direction,
+/// command name, length and the decoded parameters or status, e.g.
+/// `>> InDataExchange tg=1 MIFARE READ blk=4 (5 bytes)`.
+///
+/// Bytes that are not a valid frame are shown raw with the decoding error.
+pub struct Decoded<'a>(pub &'a [u8]);
+
+impl fmt::Display for Decoded<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let data = match decode_frame(self.0) {
+            Ok(Frame::Ack) => return write!(f, "ACK"),
+            Ok(Frame::Nack) => return write!(f, "NACK"),
+            Ok(Frame::Error) => return write!(f, "<< syntax error"),
+            Ok(Frame::Information(data)) => data,
+            Err(e) => return write!(f, "invalid frame ({}): {:02X?}", e, self.0),
+        };
+
+        let (direction, code, params) = match data {
+            [HOSTTOPN532, code, params @ ..] => (">>", *code, params),
+            [PN532TOHOST, code, params @ ..] => ("<<", code.wrapping_sub(1), params),
+            _ => return write!(f, "?? {:02X?}", data),
+        };
+        write!(f, "{} ", direction)?;
+        match Command::from_code(code) {
+            Some(command) => write!(f, "{:?}", command)?,
+            None => write!(f, "command {:#04x}", code)?,
+        }
+        if direction == ">>" {
+            describe_params(f, Command::from_code(code), params)?;
+        } else {
+            describe_response(f, Command::from_code(code), params)?;
+        }
+
+        write!(f, " ({} bytes)", params.len())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Decoded<'_> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{}", self.to_string().as_str())
+    }
+}
+
+fn describe_params(f: &mut fmt::Formatter, command: Option<Command>, params: &[u8]) -> fmt::Result {
+    match (command, params) {
+        (Some(Command::InDataExchange), [target, data @ ..]) => {
+            write!(f, " tg={}", target)?;
+            describe_card_command(f, data)
+        }
+        (Some(Command::InCommunicateThru), data) => describe_card_command(f, data),
+        (Some(Command::InListPassiveTarget), [max_targets, baud, ..]) => write!(f, " max_tg={} brty={:#04x}", max_targets, baud),
+        (Some(Command::SamConfiguration), [mode, timeout, rest @ ..]) => {
+            write!(f, " mode={} timeout={}", mode, timeout)?;
+            match rest {
+                [irq, ..] => write!(f, " irq={}", irq),
+                [] => Ok(()),
+            }
+        }
+        (Some(Command::InRelease | Command::InDeselect | Command::InSelect), [target, ..]) => write!(f, " tg={}", target),
+        (_, []) => Ok(()),
+        (_, params) => write!(f, " {:02X?}", params),
+    }
+}
+
+fn describe_card_command(f: &mut fmt::Formatter, data: &[u8]) -> fmt::Result {
+    match data {
+        [0x30, block] => write!(f, " MIFARE READ blk={}", block),
+        [0xA0, block, ..] => write!(f, " MIFARE WRITE blk={}", block),
+        [0x60, block, ..] => write!(f, " MIFARE AUTH_A blk={}", block),
+        [0x61, block, ..] => write!(f, " MIFARE AUTH_B blk={}", block),
+        [0xA2, page, ..] => write!(f, " ULTRALIGHT WRITE page={}", page),
+        [0x3A, start, end] => write!(f, " NTAG FAST_READ pages={}..={}", start, end),
+        [] => Ok(()),
+        data => write!(f, " {:02X?}", data),
+    }
+}
+
+fn describe_response(f: &mut fmt::Formatter, command: Option<Command>, response: &[u8]) -> fmt::Result {
+    let has_status = matches!(command, Some(
+        Command::InDataExchange | Command::InCommunicateThru | Command::InAtr | Command::InPsl |
+        Command::InDeselect | Command::InRelease | Command::InSelect | Command::InJumpForDep |
+        Command::InJumpForPsl | Command::PowerDown | Command::TgGetData | Command::TgSetData |
+        Command::TgSetMetaData | Command::TgSetGeneralBytes | Command::TgResponseToInitiator |
+        Command::TgGetInitiatorCommand
+    ));
+    match response.first() {
+        Some(&status) if has_status && status & 0x3F == 0 => {
+            write!(f, " ok")?;
+            if status & 0x40 != 0 {
+                write!(f, " MI")?;
+            }
+            Ok(())
+        }
+        Some(&status) if has_status => write!(f, " {}", Status::from(status & 0x3F)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // Examples from UM0701-02.
+    const GET_FIRMWARE_VERSION: &[u8] = &[0x00, 0x00, 0xFF, 0x02, 0xFE, 0xD4, 0x02, 0x2A, 0x00];
+    const FIRMWARE_VERSION: &[u8] = &[0x00, 0x00, 0xFF, 0x06, 0xFA, 0xD5, 0x03, 0x32, 0x01, 0x06, 0x07, 0xE8, 0x00];
+    const SAM_CONFIGURATION: &[u8] = &[0x00, 0x00, 0xFF, 0x05, 0xFB, 0xD4, 0x14, 0x01, 0x14, 0x01, 0x02, 0x00];
+    const ERROR: &[u8] = &[0x00, 0x00, 0xFF, 0x01, 0xFF, 0x7F, 0x81, 0x00];
+
+    #[test]
+    fn encodes_datasheet_frames() {
+        assert_eq!(encode(&[0xD4, 0x02]).unwrap(), GET_FIRMWARE_VERSION);
+        assert_eq!(encode(&[0xD4, 0x14, 0x01, 0x14, 0x01]).unwrap(), SAM_CONFIGURATION);
+    }
+
+    #[test]
+    fn decodes_datasheet_frames() {
+        assert_eq!(decode(FIRMWARE_VERSION).unwrap(), Frame::Information(&[0xD5, 0x03, 0x32, 0x01, 0x06, 0x07]));
+        assert_eq!(decode(GET_FIRMWARE_VERSION).unwrap(), Frame::Information(&[0xD4, 0x02]));
+        assert_eq!(decode(ACK).unwrap(), Frame::Ack);
+        assert_eq!(decode(NACK).unwrap(), Frame::Nack);
+        assert_eq!(decode(ERROR).unwrap(), Frame::Error);
+    }
+
+    #[test]
+    fn checksums_wrap() {
+        // The data sums to well over 255.
+        let data = [0xFF; 255];
+        let frame = encode(&data).unwrap();
+        assert_eq!(frame[3..5], [0xFF, 0x01]);
+        assert_eq!(decode(&frame).unwrap(), Frame::Information(&data));
+    }
+
+    #[test]
+    fn extended_frames() {
+        let data: Vec<u8> = (0..=255).chain(0..9).collect();
+        let frame = encode(&data).unwrap();
+        assert_eq!(frame[..8], [0x00, 0x00, 0xFF, 0xFF, 0xFF, 0x01, 0x09, 0xF6]);
+        assert_eq!(frame.len(), MAX_FRAME_LEN);
+        assert_eq!(decode(&frame).unwrap(), Frame::Information(&data));
+
+        // 255 bytes still fit in a normal frame.
+        assert_eq!(encode(&data[..255]).unwrap()[3..5], [0xFF, 0x01]);
+
+        let mut bad_lcs = frame.clone();
+        bad_lcs[7] = 0xF7;
+        assert!(matches!(decode(&bad_lcs), Err(Error::Frame(FrameError::LengthChecksum { len: 265, lcs: 0xF7 }))));
+        assert!(matches!(decode(&frame[..7]), Err(Error::Frame(FrameError::MissingLength))));
+        assert!(matches!(decode(&frame[..200]), Err(Error::Frame(FrameError::Truncated { expected: 274, actual: 200 }))));
+    }
+
+    #[test]
+    fn describes_frames() {
+        let read = encode(&[0xD4, 0x40, 0x01, 0x30, 0x04]).unwrap();
+        assert_eq!(Decoded(&read).to_string(), ">> InDataExchange tg=1 MIFARE READ blk=4 (3 bytes)");
+        let failed = encode(&[0xD5, 0x41, 0x14]).unwrap();
+        assert_eq!(Decoded(&failed).to_string(), "<< InDataExchange MIFARE authentication error (status 0x14) (1 bytes)");
+        assert_eq!(Decoded(FIRMWARE_VERSION).to_string(), "<< GetFirmwareVersion (4 bytes)");
+        assert_eq!(Decoded(ACK).to_string(), "ACK");
+        assert_eq!(Decoded(&[0x00, 0xFF, 0x02]).to_string(), "invalid frame (no frame length): [00, FF, 02]");
+    }
+
+    #[test]
+    fn skips_extra_preamble_and_trailing_bytes() {
+        let mut bytes = vec![0x00, 0x00, 0x00];
+        bytes.extend_from_slice(FIRMWARE_VERSION);
+        bytes.extend_from_slice(&[0xAA, 0xBB]);
+        assert_eq!(decode(&bytes).unwrap(), Frame::Information(&[0xD5, 0x03, 0x32, 0x01, 0x06, 0x07]));
+    }
+
+    #[test]
+    fn rejects_bad_frames() {
+        let mut bad_lcs = FIRMWARE_VERSION.to_vec();
+        bad_lcs[4] = 0xFB;
+        let mut bad_dcs = FIRMWARE_VERSION.to_vec();
+        bad_dcs[11] = 0xE9;
+
+        let cases: [(&[u8], FrameError); 7] = [
+            (&[], FrameError::StartCode),
+            (&[0x00, 0x00], FrameError::StartCode),
+            (&[0xFF, 0x06, 0xFA], FrameError::StartCode),
+            (&[0x00, 0x00, 0xFF, 0x06], FrameError::MissingLength),
+            (&FIRMWARE_VERSION[..8], FrameError::Truncated { expected: 12, actual: 8 }),
+            (&bad_lcs, FrameError::LengthChecksum { len: 6, lcs: 0xFB }),
+            (&bad_dcs, FrameError::DataChecksum(0x01)),
+        ];
+        for (bytes, expected) in cases {
+            match decode(bytes) {
+                Err(Error::Frame(e)) => assert_eq!(e, expected, "{:02X?}", bytes),
+                other => panic!("{:02X?} decoded as {:?}", bytes, other),
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_bad_data_length() {
+        assert!(matches!(encode(&[]), Err(Error::InvalidArgument(_))));
+        assert!(matches!(encode(&[0; MAX_DATA_LEN + 1]), Err(Error::InvalidArgument(_))));
+        assert!(matches!(encode_into(&[0xD4, 0x02], &mut [0; 8]), Err(Error::InvalidArgument(_))));
+    }
+
+    proptest! {
+        #[test]
+        fn round_trips(data in prop::collection::vec(any::<u8>(), 1..=MAX_DATA_LEN)) {
+            prop_assume!(data != [ERROR_DATA]);
+            let frame = encode(&data).unwrap();
+            prop_assert_eq!(frame.len(), frame_len(data.len()));
+            prop_assert_eq!(decode(&frame).unwrap(), Frame::Information(&data));
+        }
+
+        #[test]
+        fn decode_never_panics(bytes in prop::collection::vec(any::<u8>(), 0..MAX_FRAME_LEN + 20)) {
+            let _ = decode(&bytes);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::pn532::Error;
+
+/// How a command sent with [`PN532::send_command`](crate::pn532::PN532::send_command)
+/// ended.
+#[derive(Debug, Clone, Copy)]
+pub enum CommandOutcome<'a> {
+    /// The PN532 answered with a response of this many bytes.
+    Response(usize),
+    /// The PN532 did not acknowledge or answer in time.
+    NoResponse,
+    Failed(&'a Error),
+}
+
+/// Callbacks into the frame exchange of a reader, for metrics, protocol
+/// sniffers or fault injection in tests.  Every method does nothing by
+/// default.
+pub trait Hook: Send {
+    /// Called with each frame about to be written, which the hook may
+    /// change.
+    fn on_frame_sent(&mut self, _frame: &mut [u8]) {}
+
+    /// Called with the bytes of each ACK and response frame read, before
+    /// they are decoded, which the hook may change.
+    fn on_frame_received(&mut self, _frame: &mut [u8]) {}
+
+    /// Called when a command completes, with the time it took.
+    fn on_command_complete(&mut self, _command: u8, _outcome: CommandOutcome<'_>, _elapsed: Duration) {}
+
+    /// Called after the reader [recovered](crate::pn532::PN532::recover),
+    /// e.g. when the [`Watchdog`](crate::pn532::Watchdog) reset the PN532.
+    fn on_chip_reset(&mut self) {}
+}
+
+/// The hooks registered on a reader, called in the order they were added.
+#[derive(Default)]
+pub struct Hooks {
+    hooks: Vec<Box<dyn Hook>>,
+}
+
+impl Hooks {
+    pub fn add(&mut self, hook: impl Hook + 'static) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    /// Remove every hook.
+    pub fn clear(&mut self) {
+        self.hooks.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    pub(crate) fn frame_sent(&mut self, frame: &mut [u8]) {
+        for hook in &mut self.hooks {
+            hook.on_frame_sent(frame);
+        }
+    }
+
+    pub(crate) fn frame_received(&mut self, frame: &mut [u8]) {
+        for hook in &mut self.hooks {
+            hook.on_frame_received(frame);
+        }
+    }
+
+    pub(crate) fn command_complete(&mut self, command: u8, outcome: CommandOutcome<'_>, elapsed: Duration) {
+        for hook in &mut self.hooks {
+            hook.on_command_complete(command, outcome, elapsed);
+        }
+    }
+
+    pub(crate) fn chip_reset(&mut self) {
+        for hook in &mut self.hooks {
+            hook.on_chip_reset();
+        }
+    }
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Hooks({})", self.hooks.len())
+    }
+}
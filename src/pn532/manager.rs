@@ -0,0 +1,77 @@
+use rppal::spi::Bus;
+
+use crate::pn532::Result;
+use crate::pn532::spi::{open_bus, PN532Spi, PN532SpiBuilder, SharedBus};
+
+/// Identifies a reader registered with a [`ReaderManager`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReaderId(usize);
+
+/// Owns an SPI bus shared by several PN532 modules, each selected with its
+/// own CS pin (and optionally its own IRQ and reset pins).
+///
+/// Every SPI transaction locks the bus for its whole duration, so the readers
+/// can be polled one after another from a single loop, or moved to their own
+/// threads with [`ReaderManager::into_readers`] and polled concurrently.
+pub struct ReaderManager {
+    bus: SharedBus,
+    bus_id: Bus,
+    readers: Vec<PN532Spi>,
+}
+
+impl ReaderManager {
+    /// Open `bus` for use by the readers that will be added to it.
+    pub fn new(bus: Bus) -> Result<Self> {
+        Ok(Self {
+            bus: open_bus(bus)?,
+            bus_id: bus,
+            readers: Vec::new(),
+        })
+    }
+
+    /// Initialize a PN532 selected by `cs` on the shared bus and register it.
+    pub fn add_reader(&mut self, cs: u8, irq: Option<u8>, reset: Option<u8>) -> Result<ReaderId> {
+        let mut builder = PN532SpiBuilder::new().cs(cs);
+        if let Some(irq) = irq {
+            builder = builder.irq(irq);
+        }
+        if let Some(reset) = reset {
+            builder = builder.reset(reset);
+        }
+        self.add_reader_with(builder)
+    }
+
+    /// Initialize a PN532 described by `builder` on the shared bus and register
+    /// it. The bus set on the builder is ignored; it should have a CS pin.
+    pub fn add_reader_with(&mut self, builder: PN532SpiBuilder) -> Result<ReaderId> {
+        let reader = builder.build_on(self.bus.clone(), self.bus_id)?;
+        self.readers.push(reader);
+
+        Ok(ReaderId(self.readers.len() - 1))
+    }
+
+    /// Get the reader registered as `id`.
+    pub fn reader(&mut self, id: ReaderId) -> Option<&mut PN532Spi> {
+        self.readers.get_mut(id.0)
+    }
+
+    /// Iterate over all registered readers, in the order they were added.
+    pub fn readers(&mut self) -> impl Iterator<Item = (ReaderId, &mut PN532Spi)> {
+        self.readers.iter_mut().enumerate().map(|(idx, reader)| (ReaderId(idx), reader))
+    }
+
+    /// Number of registered readers.
+    pub fn len(&self) -> usize {
+        self.readers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.readers.is_empty()
+    }
+
+    /// Release the readers so each one can be driven from its own thread.
+    /// They keep sharing the bus safely.
+    pub fn into_readers(self) -> Vec<PN532Spi> {
+        self.readers
+    }
+}
@@ -0,0 +1,301 @@
+//! A PN532 with a simulated MIFARE Classic card or NTAG213 in the field, or
+//! a scripted ISO/IEC 14443-4 card, for tests of the card-level operations.
+
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+use crate::pn532::frame::{self, Frame, ACK, NACK};
+use crate::pn532::{AuthCache, GpioCache, Hooks, Options, Result, Stats, Transport, PN532};
+
+/// Status bytes of the simulated InDataExchange answers.
+const OK: u8 = 0x00;
+const TIMEOUT: u8 = 0x01;
+const MIFARE_AUTH: u8 = 0x14;
+
+/// A genuine MIFARE Classic 1K: 16 sectors of 4 blocks, with Key A and Key B
+/// per sector, and block 0 read-only.  A failed authentication or a refused
+/// command halts it until it is selected again, as a real card does.
+pub(crate) struct ClassicCard {
+    pub(crate) uid: [u8; 4],
+    pub(crate) blocks: Vec<[u8; 16]>,
+    /// Key A and Key B of each sector.
+    pub(crate) keys: Vec<([u8; 6], [u8; 6])>,
+    /// Blocks whose access conditions forbid reading or writing.
+    pub(crate) read_denied: HashSet<u8>,
+    pub(crate) write_denied: HashSet<u8>,
+    authenticated: Option<u8>,
+    halted: bool,
+}
+
+impl ClassicCard {
+    /// A blank card with the transport keys.
+    pub(crate) fn new(uid: [u8; 4]) -> Self {
+        let mut blocks = vec![[0; 16]; 64];
+        let bcc = uid.iter().fold(0, |bcc, b| bcc ^ b);
+        blocks[0][..5].copy_from_slice(&[uid[0], uid[1], uid[2], uid[3], bcc]);
+        blocks[0][5..8].copy_from_slice(&[0x08, 0x04, 0x00]);
+        for trailer in (3..64).step_by(4) {
+            blocks[trailer] = [0, 0, 0, 0, 0, 0, 0xFF, 0x07, 0x80, 0x69, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        }
+        Self {
+            uid,
+            blocks,
+            keys: vec![([0xFF; 6], [0xFF; 6]); 16],
+            read_denied: HashSet::new(),
+            write_denied: HashSet::from([0]),
+            authenticated: None,
+            halted: false,
+        }
+    }
+
+    /// Answer the data of InDataExchange with a status byte and data.
+    fn exchange(&mut self, data: &[u8]) -> Vec<u8> {
+        if self.halted {
+            return vec![TIMEOUT];
+        }
+        match *data {
+            [command @ (0x60 | 0x61), block, ref rest @ ..] if rest.len() == 10 => {
+                let (key_a, key_b) = self.keys[block as usize / 4];
+                let key = if command == 0x60 { key_a } else { key_b };
+                if rest[..6] == key && rest[6..] == self.uid {
+                    self.authenticated = Some(block / 4);
+                    vec![OK]
+                } else {
+                    self.refuse()
+                }
+            }
+            [0x30, block] if self.authenticated == Some(block / 4) && !self.read_denied.contains(&block) => {
+                let mut answer = vec![OK];
+                answer.extend_from_slice(&self.blocks[block as usize]);
+                answer
+            }
+            [0xA0, block, ref data @ ..] if self.authenticated == Some(block / 4) && !self.write_denied.contains(&block) => {
+                self.blocks[block as usize].copy_from_slice(data);
+                vec![OK]
+            }
+            _ => self.refuse(),
+        }
+    }
+
+    fn refuse(&mut self) -> Vec<u8> {
+        self.authenticated = None;
+        self.halted = true;
+        vec![MIFARE_AUTH]
+    }
+}
+
+/// An NTAG213: 45 pages of 4 bytes, the UID pages read-only.  A refused
+/// command halts it until it is selected again.
+pub(crate) struct NtagCard {
+    pub(crate) pages: Vec<[u8; 4]>,
+    pub(crate) write_denied: HashSet<u8>,
+    halted: bool,
+}
+
+impl NtagCard {
+    /// A blank tag with the capability container of an NDEF tag.
+    pub(crate) fn new(uid: [u8; 7]) -> Self {
+        let mut pages = vec![[0; 4]; 45];
+        pages[0] = [uid[0], uid[1], uid[2], 0x88 ^ uid[0] ^ uid[1] ^ uid[2]];
+        pages[1] = [uid[3], uid[4], uid[5], uid[6]];
+        pages[2] = [uid[3] ^ uid[4] ^ uid[5] ^ uid[6], 0x48, 0x00, 0x00];
+        pages[3] = [0xE1, 0x10, 0x12, 0x00];
+        Self { pages, write_denied: HashSet::from([0, 1]), halted: false }
+    }
+
+    /// Answer the data of InDataExchange with a status byte and data.
+    fn exchange(&mut self, data: &[u8]) -> Vec<u8> {
+        if self.halted {
+            return vec![TIMEOUT];
+        }
+        match *data {
+            // READ answers 4 pages, rolling over to page 0.
+            [0x30, page] if (page as usize) < self.pages.len() => {
+                let mut answer = vec![OK];
+                for i in 0..4 {
+                    answer.extend_from_slice(&self.pages[(page as usize + i) % self.pages.len()]);
+                }
+                answer
+            }
+            [0xA2, page, ref data @ ..] if (page as usize) < self.pages.len() && data.len() == 4 && !self.write_denied.contains(&page) => {
+                self.pages[page as usize].copy_from_slice(data);
+                vec![OK]
+            }
+            _ => {
+                self.halted = true;
+                vec![MIFARE_AUTH]
+            }
+        }
+    }
+
+    /// Answer the data of InCommunicateThru: GET_VERSION only.
+    fn communicate(&mut self, data: &[u8]) -> Vec<u8> {
+        match data {
+            [0x60] if !self.halted => vec![OK, 0x00, 0x04, 0x04, 0x02, 0x01, 0x00, 0x0F, 0x03],
+            _ => {
+                self.halted = true;
+                vec![TIMEOUT]
+            }
+        }
+    }
+}
+
+/// A PN532 answering every command at once, InDataExchange from the
+/// simulated card.
+pub(crate) struct MockPn532 {
+    options: Options,
+    auth_cache: AuthCache,
+    gpio_cache: GpioCache,
+    hooks: Hooks,
+    stats: Stats,
+    pub(crate) card: ClassicCard,
+    /// An NTAG213 in the field instead of the MIFARE Classic card.
+    pub(crate) ntag: Option<NtagCard>,
+    /// Answers of an ISO/IEC 14443-4 card to InDataExchange, in order; the
+    /// MIFARE Classic card answers once there are none left.
+    pub(crate) iso_dep_answers: VecDeque<Vec<u8>>,
+    /// Commands received, without TFI.
+    pub(crate) commands: Vec<Vec<u8>>,
+    output: VecDeque<Vec<u8>>,
+    last_response: Vec<u8>,
+}
+
+impl MockPn532 {
+    pub(crate) fn new(card: ClassicCard) -> Self {
+        Self {
+            options: Options::default(),
+            auth_cache: AuthCache::default(),
+            gpio_cache: GpioCache::default(),
+            hooks: Hooks::default(),
+            stats: Stats::default(),
+            card,
+            ntag: None,
+            iso_dep_answers: VecDeque::new(),
+            commands: Vec::new(),
+            output: VecDeque::new(),
+            last_response: Vec::new(),
+        }
+    }
+
+    pub(crate) fn with_ntag(ntag: NtagCard) -> Self {
+        let mut pn532 = Self::new(ClassicCard::new([0; 4]));
+        pn532.ntag = Some(ntag);
+        pn532
+    }
+
+    fn answer(&mut self, command: &[u8]) -> Vec<u8> {
+        if let Some(ntag) = &mut self.ntag {
+            match *command {
+                [0x40, 0x01, ref data @ ..] => return ntag.exchange(data),
+                [0x42, ref data @ ..] => return ntag.communicate(data),
+                [0x54, _] => ntag.halted = false,
+                [0x44, _] => ntag.halted = true,
+                _ => {}
+            }
+        }
+        match *command {
+            // InDataExchange, to target 1.
+            [0x40, 0x01, ref data @ ..] => match self.iso_dep_answers.pop_front() {
+                Some(answer) => [&[OK], &answer[..]].concat(),
+                None => self.card.exchange(data),
+            },
+            // ReadRegister and WriteRegister, for the raw framing.
+            [0x06, ref addresses @ ..] => vec![0; addresses.len() / 2],
+            [0x08, ..] => Vec::new(),
+            // InCommunicateThru: a genuine card ignores HLTA and the gen1a
+            // backdoor, and stays halted.
+            [0x42, ..] => {
+                self.card.halted = true;
+                vec![TIMEOUT]
+            }
+            // InListPassiveTarget of one type A card: Tg, SENS_RES, SEL_RES
+            // and the UID.  Activating the card wakes it.
+            [0x4A, 0x01, 0x00] => {
+                self.card.halted = false;
+                self.card.authenticated = None;
+                let mut answer = vec![0x01, 0x01, 0x00, 0x04, 0x08, 0x04];
+                answer.extend_from_slice(&self.card.uid);
+                answer
+            }
+            // InSelect wakes the card, InDeselect halts it.
+            [0x54, _] => {
+                self.card.halted = false;
+                self.card.authenticated = None;
+                vec![OK]
+            }
+            [0x44, _] => {
+                self.card.halted = true;
+                vec![OK]
+            }
+            _ => panic!("unexpected command {:02X?}", command),
+        }
+    }
+}
+
+impl Transport for MockPn532 {
+    fn gpio_init(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn reset(&mut self, _pin: u8) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let output = self.output.pop_front().unwrap_or_default();
+        let len = output.len().min(buf.len());
+        buf[..len].copy_from_slice(&output[..len]);
+        Ok(len)
+    }
+
+    fn write_data(&mut self, frame: &[u8]) -> Result<()> {
+        if frame == ACK {
+            return Ok(());
+        }
+        if frame == NACK {
+            self.output.push_back(self.last_response.clone());
+            return Ok(());
+        }
+        let Frame::Information(data) = frame::decode(frame)? else {
+            panic!("unexpected frame {:02X?}", frame);
+        };
+        let command = data[1..].to_vec();
+        let mut response = vec![0xD5, command[0] + 1];
+        response.extend(self.answer(&command));
+        self.commands.push(command);
+        self.last_response = frame::encode(&response)?;
+        self.output.push_back(ACK.to_vec());
+        self.output.push_back(self.last_response.clone());
+        Ok(())
+    }
+
+    fn wait_ready(&mut self, _timeout: Duration) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn wake_up(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl PN532 for MockPn532 {
+    fn options(&self) -> &Options {
+        &self.options
+    }
+
+    fn auth_cache(&mut self) -> &mut AuthCache {
+        &mut self.auth_cache
+    }
+
+    fn gpio_cache(&mut self) -> &mut GpioCache {
+        &mut self.gpio_cache
+    }
+
+    fn hooks(&mut self) -> &mut Hooks {
+        &mut self.hooks
+    }
+
+    fn stats_mut(&mut self) -> &mut Stats {
+        &mut self.stats
+    }
+}
@@ -1,65 +1,87 @@
-use std::fmt;
-use std::error::Error;
+use std::ops::Range;
 use std::result;
-
-use log::{info, debug};
-
+use std::time::{Duration, Instant};
+
+pub use analog::{AnalogPreset, AnalogSettings};
+pub use apdu::{Apdu, ApduChannel, Rapdu, StatusWord};
+pub use ats::Ats;
+pub use cancel::Canceller;
+pub use classic::{validate_access_bits, validate_manufacturer_block, AccessBits, AuthCache, ClassicDump, DataAccess, KeyMap, KeyType, MagicCard, SectorDump, SectorKey, SectorKeys, TrailerAccess};
+pub use command::{ActivationResult, BaudModulation, BitRate, Command, DiagnoseResult, DiagnoseTest, FelicaParams, FirmwareVersion, GeneralStatus, MifareParams, Modulation, ParametersFlags, Request, RfTimeout, SamMode, SerialBaudRate, TargetConfig, TargetModeFlags, TargetState, TargetStatus, TxFraming, TxMode, TxSpeed, TypeBPolling, WakeupSources, SelectedTarget, Target};
+pub use desfire::DesfireTag;
+pub use dump::{DumpAddress, NtagDump, RestoreChange, RestoreOptions, RestoreReport};
+pub use error::Error;
+pub use felica::FelicaBlock;
+pub use hook::{CommandOutcome, Hook, Hooks};
+pub use address::{Block, Page, Sector};
+pub use stats::{CommandStats, Counters, Stats};
+pub use status::Status;
+pub use target::{DepTarget, FelicaTarget, PassiveDetection, PassiveTarget, TagKind, TypeBTarget};
+pub use options::Options;
+pub use retry::{Retries, RetryPolicy};
+pub use self_test::{SelfTest, SelfTestReport};
+pub use timeouts::Timeouts;
+pub use transport::Transport;
+pub use uid::Uid;
+pub use ntag::{MirrorMode, NtagAccess, NtagConfig, NtagVariant, TagVersion};
+pub use watchdog::Watchdog;
+
+use command::{STATUS_ERROR, STATUS_MI, Diagnose, GetFirmwareVersion, GetGeneralStatus, InAtr, InCommunicateThru, InDataExchange, InDeselect, InJumpForDep, InListPassiveTarget, InPsl, InRelease, InSelect, PowerDown, ReadGpio, ReadRegister, RfConfiguration, RfRegulationTest, SamConfiguration, SetParameters, SetSerialBaudRate, TgGetData, TgGetInitiatorCommand, TgGetTargetStatus, TgInitAsTarget, TgResponseToInitiator, TgSetData, WriteGpio, WriteRegister};
+use frame::{Frame, FrameError, ACK, MAX_DATA_LEN, MAX_FRAME_LEN, NACK};
+use session::Tag;
+
+mod address;
+mod analog;
+pub mod apdu;
+mod ats;
+#[cfg(feature = "tokio")]
+pub mod async_spi;
+mod cancel;
+pub mod capture;
+mod classic;
+pub mod command;
+pub mod desfire;
+mod dump;
+mod error;
+mod felica;
+pub mod frame;
+mod hook;
+#[cfg(test)]
+mod mock;
+pub mod ntag;
+pub mod manager;
+mod options;
+pub mod registers;
+mod retry;
+mod self_test;
+pub mod session;
+#[cfg(feature = "signature")]
+pub mod signature;
 pub mod spi;
-
-type Result<U> = result::Result<U, Box<dyn Error>>;
-
-const PREAMBLE: u8 =    0x00;
-const STARTCODE1: u8 =  0x00;
-const STARTCODE2: u8 =  0xFF;
-const POSTAMBLE: u8 =   0x00;
-
-const HOSTTOPN532: u8 = 0xD4;
-const PN532TOHOST: u8 = 0xD5;
-
-// PN532 Commands
-const COMMAND_DIAGNOSE: u8 =                0x00;
-const COMMAND_GETFIRMWAREVERSION: u8 =      0x02;
-const COMMAND_GETGENERALSTATUS: u8 =        0x04;
-const COMMAND_READREGISTER: u8 =            0x06;
-const COMMAND_WRITEREGISTER: u8 =           0x08;
-const COMMAND_READGPIO: u8 =                0x0C;
-const COMMAND_WRITEGPIO: u8 =               0x0E;
-const COMMAND_SETSERIALBAUDRATE: u8 =       0x10;
-const COMMAND_SETPARAMETERS: u8 =           0x12;
-const COMMAND_SAMCONFIGURATION: u8 =        0x14;
-const COMMAND_POWERDOWN: u8 =               0x16;
-const COMMAND_RFCONFIGURATION: u8 =         0x32;
-const COMMAND_RFREGULATIONTEST: u8 =        0x58;
-const COMMAND_INJUMPFORDEP: u8 =            0x56;
-const COMMAND_INJUMPFORPSL: u8 =            0x46;
-const COMMAND_INLISTPASSIVETARGET: u8 =     0x4A;
-const COMMAND_INATR: u8 =                   0x50;
-const COMMAND_INPSL: u8 =                   0x4E;
-const COMMAND_INDATAEXCHANGE: u8 =          0x40;
-const COMMAND_INCOMMUNICATETHRU: u8 =       0x42;
-const COMMAND_INDESELECT: u8 =              0x44;
-const COMMAND_INRELEASE: u8 =               0x52;
-const COMMAND_INSELECT: u8 =                0x54;
-const COMMAND_INAUTOPOLL: u8 =              0x60;
-const COMMAND_TGINITASTARGET: u8 =          0x8C;
-const COMMAND_TGSETGENERALBYTES: u8 =       0x92;
-const COMMAND_TGGETDATA: u8 =               0x86;
-const COMMAND_TGSETDATA: u8 =               0x8E;
-const COMMAND_TGSETMETADATA: u8 =           0x94;
-const COMMAND_TGGETINITIATORCOMMAND: u8 =   0x88;
-const COMMAND_TGRESPONSETOINITIATOR: u8 =   0x90;
-const COMMAND_TGGETTARGETSTATUS: u8 =       0x8A;
-
-const RESPONSE_INDATAEXCHANGE: u8 =         0x41;
-const RESPONSE_INLISTPASSIVETARGET: u8 =    0x4B;
+mod stats;
+mod status;
+mod target;
+mod timeouts;
+mod transport;
+mod uid;
+pub mod ultralight;
+#[cfg(feature = "des")]
+pub mod ultralight_c;
+mod watchdog;
+
+pub type Result<U> = result::Result<U, Error>;
+
+pub(crate) const HOSTTOPN532: u8 = 0xD4;
+pub(crate) const PN532TOHOST: u8 = 0xD5;
 
 const WAKEUP: u8 = 0x55;
 
-const MIFARE_ISO14443A: u8 = 0x00;
+/// How long [`PN532::low_power_poll`] looks for a card each time.
+const LOW_POWER_BURST: Duration = Duration::from_millis(100);
 
 // Mifare Commands
-const MIFARE_CMD_AUTH_A: u8 =           0x60;
-const MIFARE_CMD_AUTH_B: u8 =           0x61;
+pub const MIFARE_CMD_AUTH_A: u8 =           0x60;
+pub const MIFARE_CMD_AUTH_B: u8 =           0x61;
 const MIFARE_CMD_READ: u8 =             0x30;
 const MIFARE_CMD_WRITE: u8 =            0xA0;
 const MIFARE_CMD_TRANSFER: u8 =         0xB0;
@@ -67,6 +89,10 @@ const MIFARE_CMD_DECREMENT: u8 =        0xC0;
 const MIFARE_CMD_INCREMENT: u8 =        0xC1;
 const MIFARE_CMD_STORE: u8 =            0xC2;
 const MIFARE_ULTRALIGHT_CMD_WRITE: u8 = 0xA2;
+const NTAG_CMD_FAST_READ: u8 =          0x3A;
+
+/// Pages read per FAST_READ, so that the answer fits in one PN532 frame.
+const NTAG_FAST_READ_PAGES: u8 = 60;
 
 // Prefixes for NDEF Records (to identify record type)
 const NDEF_URIPREFIX_NONE: u8 =         0x00;
@@ -108,9 +134,13 @@ const NDEF_URIPREFIX_URN_NFC: u8 =      0x23;
 
 const GPIO_VALIDATIONBIT: u8 = 0x80;
 
-const ACK: &[u8] = b"\x00\x00\xFF\x00\xFF\x00";
+/// Times a corrupted response frame is asked for again with a NACK.
+const NACK_RETRIES: u8 = 2;
+
 const FRAME_START: &[u8] = b"\x00\x00\xFF";
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum PN532Gpio {
     P30,
     P31,
@@ -151,230 +181,502 @@ impl PN532Gpio {
 
 }
 
-#[derive(Debug)]
-pub struct BusyError;
+/// State of the PN532's GPIO ports, as read by ReadGPIO:
+/// ```text
+/// P3[0] = P30,   P7[0] = 0,   I[0] = I0,
+/// P3[1] = P31,   P7[1] = P71, I[1] = I1,
+/// P3[2] = P32,   P7[2] = P72, I[2] = 0,
+/// P3[3] = P33,   P7[3] = 0,   I[3] = 0,
+/// P3[4] = P34,   P7[4] = 0,   I[4] = 0,
+/// P3[5] = P35,   P7[5] = 0,   I[5] = 0,
+/// P3[6] = 0,     P7[6] = 0,   I[6] = 0,
+/// P3[7] = 0,     P7[7] = 0,   I[7] = 0,
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct GpioState {
+    p3: u8,
+    p7: u8,
+    i: u8,
+}
 
-impl fmt::Display for BusyError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Busy Error!")
+impl GpioState {
+    /// The P3 port.
+    pub fn p3(&self) -> u8 {
+        self.p3
     }
-}
 
-impl Error for BusyError { }
+    /// The P7 port.
+    pub fn p7(&self) -> u8 {
+        self.p7
+    }
 
-#[derive(Debug)]
-pub struct RuntimeError(String);
+    /// The I0 pin.
+    pub fn i0(&self) -> bool {
+        PN532Gpio::I0.get(self.i)
+    }
 
-impl fmt::Display for RuntimeError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", &self.0)
-    } 
-}
+    /// The I1 pin.
+    pub fn i1(&self) -> bool {
+        PN532Gpio::I1.get(self.i)
+    }
 
-impl Error for RuntimeError {}
+    /// The state of a single pin.
+    pub fn pin(&self, pin: PN532Gpio) -> bool {
+        pin.get(self.port(pin.idx()))
+    }
 
+    fn port(&self, idx: usize) -> u8 {
+        match idx {
+            0 => self.p3,
+            1 => self.p7,
+            _ => self.i,
+        }
+    }
+}
 
-#[derive(Debug)]
-pub struct PN532Error {
-    code: u8,
-    msg: String,
+/// Last known state of the PN532's P3 and P7 output ports, so that writing a
+/// single pin does not need a ReadGPIO first.  Implementations clear it when
+/// they reset the PN532.
+#[derive(Debug, Clone, Default)]
+pub struct GpioCache {
+    ports: [Option<u8>; 2],
 }
 
-impl PN532Error {
-    fn error(code: u8) -> Self {
-        let msg = match code {
-            0x01 => "PN532 ERROR TIMEOUT",
-            0x02 => "PN532 ERROR CRC",
-            0x03 => "PN532 ERROR PARITY",
-            0x04 => "PN532 ERROR COLLISION_BITCOUNT",
-            0x05 => "PN532 ERROR MIFARE_FRAMING",
-            0x06 => "PN532 ERROR MIFARE_FRAMING",
-            0x07 => "PN532 ERROR NOBUFS",
-            0x09 => "PN532 ERROR RFNOBUFS",
-            0x0a => "PN532 ERROR ACTIVE_TOOSLOW",
-            0x0b => "PN532 ERROR RFPROTO",
-            0x0d => "PN532 ERROR TOOHOT",
-            0x0e => "PN532 ERROR INTERNAL_NOBUFS",
-            0x10 => "PN532 ERROR INVAL",
-            0x12 => "PN532 ERROR DEP_INVALID_COMMAND",
-            0x13 => "PN532 ERROR DEP_BADDATA",
-            0x14 => "PN532 ERROR MIFARE_AUTH",
-            0x18 => "PN532 ERROR NOSECURE",
-            0x19 => "PN532 ERROR I2CBUSY",
-            0x23 => "PN532 ERROR UIDCHECKSUM",
-            0x25 => "PN532 ERROR DEPSTATE",
-            0x26 => "PN532 ERROR HCIINVAL",
-            0x27 => "PN532 ERROR CONTEXT",
-            0x29 => "PN532 ERROR RELEASED",
-            0x2a => "PN532 ERROR CARDSWAPPED",
-            0x2b => "PN532 ERROR NOCARD",
-            0x2c => "PN532 ERROR MISMATCH",
-            0x2d => "PN532 ERROR OVERCURRENT",
-            0x2e => "PN532 ERROR NONAD",
-            _ => panic!("Error State: Unexpected PN532 Error Code: {}", code)
-        }.to_owned();
+impl GpioCache {
+    /// Forget the cached state, e.g. after something else drove the pins.
+    pub fn clear(&mut self) {
+        self.ports = [None; 2];
+    }
+
+    fn port(&self, idx: usize) -> Option<u8> {
+        self.ports.get(idx).copied().flatten()
+    }
+
+    fn record_read(&mut self, state: &GpioState) {
+        self.ports = [Some(state.p3), Some(state.p7)];
+    }
 
-        Self {
-            code,
-            msg
+    /// Record a WriteGPIO: only ports with the validation bit set are written.
+    fn record_write(&mut self, request: &WriteGpio) {
+        for (port, value) in self.ports.iter_mut().zip([request.p3, request.p7]) {
+            if value & GPIO_VALIDATIONBIT != 0 {
+                *port = Some(value & !GPIO_VALIDATIONBIT);
+            }
         }
     }
 }
 
-impl fmt::Display for PN532Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", &self.msg)
+/// How [`PN532::low_power_poll`] saves power between polls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LowPowerMode {
+    /// Switch the RF field off, which takes most of the power.
+    #[default]
+    RfOff,
+    /// Put the PN532 into power down, which takes a wake up of a few ms
+    /// before each poll.
+    PowerDown,
+}
+
+/// How the PN532 frames the raw data of [`PN532::communicate_thru`], set
+/// with [`PN532::set_raw_framing`].  The PN532 keeps it until changed or
+/// reset; the structured card operations need the default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RawFraming {
+    /// Append a CRC to the sent data.
+    pub tx_crc: bool,
+    /// Check and strip the CRC of the received data.
+    pub rx_crc: bool,
+    /// Generate and check the parity bits.  Without them every byte is sent
+    /// and received as 8 bits only.
+    pub parity: bool,
+    /// Number of bits of the last sent byte, 1 to 7, or 0 for a whole byte,
+    /// e.g. 7 for a short frame.
+    pub tx_last_bits: u8,
+}
+
+impl Default for RawFraming {
+    fn default() -> Self {
+        Self { tx_crc: true, rx_crc: true, parity: true, tx_last_bits: 0 }
     }
 }
 
-impl Error for PN532Error {}
+/// What the initiator did, as seen by [`PN532::tg_get_data`] and
+/// [`PN532::tg_get_initiator_command`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetEvent {
+    /// The initiator sent this data; with TgGetData, chained parts are
+    /// concatenated.
+    Data(Vec<u8>),
+    /// The initiator released the PN532 (status 0x29); it has to be
+    /// configured as target again.
+    Released,
+}
 
-trait PN532 {
-    fn init(&mut self, reset: Option<u8>) -> Result<()> {
-        if let Some(pin) = reset {
+pub trait PN532: Transport {
+    /// Bring the PN532 up as described by [`PN532::options`]: optionally reset
+    /// it through the `reset` pin, wake it up, check that it answers and apply
+    /// the SAM configuration.
+    ///
+    /// Cold-started chips often miss the first command, so the whole sequence
+    /// is tried up to [`Options::init_retries`] more times, pulsing the reset
+    /// pin before each new attempt when [`Options::reset_on_init_failure`] is
+    /// set.
+    ///
+    /// Then runs the [`Options::self_test`], if any, and returns its report.
+    /// A failed test fails with [`Error::SelfTest`].
+    fn init(&mut self, reset: Option<u8>) -> Result<Option<SelfTestReport>> {
+        let options = self.options().clone();
+        if let (Some(pin), true) = (reset, options.reset_on_startup) {
             debug!("Resetting!");
             self.reset(pin)?;
         }
 
-        self.wake_up()?;
-        self.get_firmware_version()?;
+        let mut attempt = 0;
+        let version = loop {
+            match bring_up(self, options.sam) {
+                Ok(version) => break version,
+                Err(_) if attempt < options.init_retries => {
+                    attempt += 1;
+                    debug!("PN532 did not answer, retrying ({}/{})", attempt, options.init_retries);
+                    if let (Some(pin), true) = (reset, options.reset_on_init_failure) {
+                        debug!("Resetting!");
+                        self.reset(pin)?;
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        };
+        info!("Found {}", version);
 
-        Ok(())
+        let Some(config) = options.self_test else {
+            return Ok(None);
+        };
+        let report = self.self_test(&config)?;
+        if !report.is_ok() {
+            return Err(Error::SelfTest(Box::new(report)));
+        }
+        info!("Self test passed: {}", report);
+
+        Ok(Some(report))
+    }
+
+    /// Check the PN532 is healthy: read its firmware version, have it echo a
+    /// known pattern and, if `config` asks for it, test the antenna.  Failed
+    /// tests are reported, not returned as errors.
+    fn self_test(&mut self, config: &SelfTest) -> Result<SelfTestReport> {
+        let firmware = self.get_firmware_version()?;
+        let communication_line = self.diagnose(DiagnoseTest::CommunicationLine(self_test::PATTERN.to_vec()))?;
+        let antenna = match config.antenna_threshold {
+            Some(threshold) => Some(self.diagnose(DiagnoseTest::Antenna { threshold })?),
+            None => None,
+        };
+
+        Ok(SelfTestReport { firmware, communication_line, antenna })
     }
 
-    fn gpio_init(&self, ) -> Result<()>;
+    /// Settings of this reader.
+    fn options(&self) -> &Options;
+
+    /// MIFARE Classic authentication state of the selected card, see
+    /// [`AuthCache`].  Implementations just store one.
+    fn auth_cache(&mut self) -> &mut AuthCache;
 
-    fn reset(&self, pin: u8) -> Result<()>;
+    /// Last known state of the GPIO output ports, see [`GpioCache`].
+    /// Implementations just store one.
+    fn gpio_cache(&mut self) -> &mut GpioCache;
 
-    fn read_data(&mut self, len: usize) -> Result<Vec<u8>>;
+    /// Hooks called as frames are exchanged, see [`Hook`].  Implementations
+    /// just store them.
+    fn hooks(&mut self) -> &mut Hooks;
 
-    fn write_data(&mut self, frame: &[u8]) -> Result<()>;
+    /// Timing and link health counters, see [`Stats`].  Implementations just
+    /// store them.
+    fn stats_mut(&mut self) -> &mut Stats;
+
+    /// Timeouts used when waiting for the PN532.
+    fn timeouts(&self) -> &Timeouts {
+        &self.options().timeouts
+    }
 
-    fn wait_ready(&mut self, timeout: f64) -> Result<bool>;
+    /// How commands are retried after a transient failure.
+    fn retries(&self) -> &Retries {
+        &self.options().retries
+    }
 
-    fn wake_up(&mut self) -> Result<()>;
+    /// Fail with [`Error::WriteDisabled`] if the reader is read-only. Every
+    /// operation that writes to a tag checks this first.
+    fn ensure_writable(&self) -> Result<()> {
+        if self.options().read_only {
+            Err(Error::WriteDisabled)
+        } else {
+            Ok(())
+        }
+    }
 
     /// Write a frame to the PN532 with the specified data bytearray.
     fn write_frame(&mut self, data: &[u8]) -> Result<()> {
-        assert!(data.len() > 1 && data.len() < 255);
-
-        // Build frame to send as:
-        // - Preamble (0x00)
-        // - Start code  (0x00, 0xFF)
-        // - Command length (1 byte)
-        // - Command length checksum
-        // - Command bytes
-        // - Checksum
-        // - Postamble (0x00)
-        let len = data.len() as u8;
-        let mut frame = vec![0_u8; (len+7) as usize];
-        frame[0] = PREAMBLE;
-        frame[1] = STARTCODE1;
-        frame[2] = STARTCODE2;
-        let mut checksum: u8 = frame[0..3].iter().sum();
-        frame[3] = len;
-        frame[4] = !len + 1;
-        frame[5..(len as usize -2)].copy_from_slice(data);
-        checksum += data.iter().sum::<u8>();
-        frame[len as usize -2] = !checksum;
-        frame[len as usize -1] = POSTAMBLE;
-
-        debug!("Write frame: {:?}", frame);
-        self.write_data(&frame)?;
+        if !(data.len() > 1 && data.len() <= MAX_DATA_LEN) {
+            return Err(Error::InvalidArgument(format!("frame data must be 2 to {} bytes, got {}", MAX_DATA_LEN, data.len())));
+        }
+
+        let mut frame = [0_u8; MAX_FRAME_LEN];
+        let len = frame::encode_into(data, &mut frame)?;
+        let frame = &mut frame[..len];
+        self.hooks().frame_sent(frame);
+        self.stats_mut().counters_mut().bytes_sent += frame.len() as u64;
+        debug!("{}", frame::Decoded(frame));
+        self.write_data(frame)?;
 
         Ok(())
     }
 
-    /// Read a response frame from the PN532 of at most length bytes in size.
-    /// Returns the data inside the frame if found, otherwise raises an exception
-    /// if there is an error parsing the frame.  Note that less than length bytes
-    /// might be returned!
+    /// Read a response frame from the PN532 and return at most `len` bytes
+    /// of its data, otherwise raises an exception if there is an error parsing
+    /// the frame.  The whole frame is read whatever `len` is, as announced by
+    /// its LEN byte.  If the transport delivers fewer bytes than that, a
+    /// truncated frame is reported as an error.
     fn read_frame(&mut self, len: usize) -> Result<Vec<u8>> {
+        let mut buf = [0; MAX_FRAME_LEN];
+        let read = self.read_frame_data(&mut buf)?;
+        self.hooks().frame_received(&mut buf[..read]);
+        self.stats_mut().counters_mut().bytes_received += read as u64;
+        debug!("{}", frame::Decoded(&buf[..read]));
+
+        let data = decode_frame(&buf[..read])?;
+        Ok(data[..data.len().min(len)].to_vec())
+    }
 
-        // Read frame with expected length of data.
-        let response = self.read_data(len + 7)?;
-        debug!("Read frame: {:?}", response);
-
-        // Swallow all the 0x00 values that preceed 0xFF.
-        let mut offset = 0_usize;
-        while response[offset] == 0x00 {
-            offset += 1;
-            if offset >= response.len() {
-                return Err(box RuntimeError("Response frame preamble does not contain 0x00FF!".to_owned()));
+    /// Send specified command to the PN532 and read back up to the largest
+    /// response the command can produce.  Params can optionally specify an
+    /// array of bytes to send as
+    /// parameters to the function call.  Will wait up to timeout (or the
+    /// configured response timeout of the command if `None`) for a response
+    /// and return a bytearray of response bytes, or None if no response is
+    /// available within the timeout.
+    #[doc(hidden)]
+    fn call_function(&mut self, command: Command, params: &[u8], timeout: Option<Duration>) -> Result<Option<Vec<u8>>> {
+        let timeout = timeout.unwrap_or_else(|| self.timeouts().response_for(command));
+        let policy = self.retries().policy_for(command);
+        let mut attempt = 1;
+        loop {
+            match self.send_command(command.code(), params, command.max_response_len(), timeout) {
+                Err(e) if e.is_transient() && attempt < policy.max_attempts => {
+                    debug!("Command {:#04x} failed, retrying ({}/{})", command.code(), attempt, policy.max_attempts);
+                    attempt += 1;
+                    self.stats_mut().counters_mut().retries += 1;
+                    std::thread::sleep(policy.backoff);
+                }
+                result => return result.map_err(|e| e.after_attempts(attempt)),
             }
         }
-        if response[offset] != 0xFF { 
-            return Err(box RuntimeError("Response frame preamble does not contain 0x00FF!".to_owned()));
-        }
-        offset += 1;
-        if offset >= response.len() {
-            return Err(box RuntimeError("Response contains no data!".to_owned()));
-        }
-        // Check length & length checksum match.
-        let frame_len = response[offset];
-        if (frame_len + response[offset + 1]) != 0 {
-            return Err(box RuntimeError("Response length checksum did not match length!".to_owned()));
-        }
-        // Check frame checksum value matches bytes.
-        let checksum: u8 = response[offset+2..offset+2+(frame_len as usize)+1].iter().sum::<u8>();
-        if checksum != 0 {
-            return Err(box RuntimeError(format!("Response checksum did not match expected value: {}", checksum)));
-        }
-        // Return frame data.
-        Ok(response[offset+2..offset+2+(frame_len as usize)].into())
     }
 
-    /// Send specified command to the PN532 and expect up to response_length
-    /// bytes back in a response.  Note that less than the expected bytes might
-    /// be returned!  Params can optionally specify an array of bytes to send as
-    /// parameters to the function call.  Will wait up to timeout seconds
-    /// for a response and return a bytearray of response bytes, or None if no
-    /// response is available within the timeout.
-    fn call_function(&mut self, command: u8, response_length: usize, params: &[u8], timeout: f64) -> Result<Option<Vec<u8>>> {
+    /// Send a raw command to the PN532, for commands that have no typed
+    /// wrapper yet.
+    ///
+    /// `command` and `params` are sent in a normal information frame
+    /// (`00 00 FF LEN LCS D4 command params.. DCS 00`), or an extended one
+    /// if they are longer than 253 bytes. The PN532 must ACK the
+    /// frame within the configured ACK timeout, after which the response frame
+    /// is awaited for up to `timeout`. At most `max_response` bytes following
+    /// the response code are read, so a value that is too small truncates the
+    /// response.
+    ///
+    /// Returns the response bytes following `D5 command+1`, or `None` if the
+    /// PN532 did not respond in time. Status bytes in the response are not
+    /// interpreted.
+    fn send_command(&mut self, command: u8, params: &[u8], max_response: usize, timeout: Duration) -> Result<Option<Vec<u8>>> {
+        let mut response = vec![0; max_response];
+        let len = self.send_command_into(command, params, &mut response, timeout)?;
+
+        Ok(len.map(|len| {
+            response.truncate(len);
+            response
+        }))
+    }
+
+    /// Like [`PN532::send_command`], but reads the response into `response`
+    /// instead of allocating it; at most `response.len()` bytes are read.
+    /// Returns the length of the response, or `None` if the PN532 did not
+    /// respond in time.
+    fn send_command_into(&mut self, command: u8, params: &[u8], response: &mut [u8], timeout: Duration) -> Result<Option<usize>> {
+        let start = (!self.hooks().is_empty()).then(Instant::now);
+        let result = exchange_into(self, command, params, response, timeout);
+        report_exchange(self, command, result.as_ref().map(|len| *len), start);
+
+        result
+    }
+
+    /// Bring a reader that stopped working back: reopen the transport, pulse
+    /// the reset pin when one is wired, wake the PN532 up and send the
+    /// SAMConfiguration of the [`Options`] again.  The selected targets and
+    /// GPIO states are lost.
+    fn recover(&mut self) -> Result<()> {
+        self.stats_mut().recovering = true;
+        let result = reinitialize(self);
+        let stats = self.stats_mut();
+        stats.recovering = false;
+        stats.consecutive_ack_timeouts = 0;
+        result?;
+        self.stats_mut().counters_mut().recoveries += 1;
+        self.hooks().chip_reset();
+
+        Ok(())
+    }
+
+    /// First half of [`PN532::send_command`]: send the command frame and
+    /// check the PN532 acknowledges it. Returns `false` if no ACK arrived
+    /// within the ACK timeout.
+    ///
+    /// The response must then be awaited (see [`Transport::wait_ready`]) and
+    /// read with [`PN532::finish_command`] before sending anything else.
+    fn start_command(&mut self, command: u8, params: &[u8]) -> Result<bool> {
+        if params.len() > MAX_DATA_LEN - 2 {
+            return Err(Error::InvalidArgument(format!("command parameters must be at most {} bytes, got {}", MAX_DATA_LEN - 2, params.len())));
+        }
+
+        if std::mem::take(&mut self.stats_mut().asleep) {
+            self.wake_up()?;
+        }
 
         // Build frame data with command and parameters.
-        let mut data = vec![0; 2 + params.len()];
+        let mut data = [0; MAX_FRAME_LEN];
+        let data = &mut data[..2 + params.len()];
         data[0] = HOSTTOPN532;
         data[1] = command;
 
         data[2..2+params.len()].copy_from_slice(params);
-        debug!("Calling function.... send command: {}, by data: {:?}", command, data);
+
+        let context = self.options().error_context;
+        let in_command = |e: Error, response: Option<&[u8]>| {
+            e.in_command(command, None, response.filter(|_| context))
+        };
 
         // Send frame and wait for response.
-        if let Err(e) = self.write_frame(data.as_slice()) {
+        if let Err(e) = self.write_frame(data) {
             self.wake_up()?;
-            return Err(e);
-        }
-        if !self.wait_ready(timeout)? {
-            return Ok(None);
+            return Err(in_command(e, None));
         }
-        // Verify ACK response and wait to be ready for function response.
-        if ACK != self.read_data(ACK.len())? {
-            return Err(box RuntimeError("Did not receive expected ACK from PN532!".to_owned()));
+        self.stats_mut().counters_mut().commands_sent += 1;
+        if !self.wait_ready(self.timeouts().ack).map_err(|e| in_command(e, None))? {
+            self.stats_mut().record_ack_timeout();
+            return Ok(false);
         }
-        if !self.wait_ready(timeout)? {
-            return Ok(None);
+        // Verify ACK response.
+        let mut ack = [0; ACK.len()];
+        let read = self.read_data(&mut ack).map_err(|e| in_command(e, None))?;
+        self.hooks().frame_received(&mut ack[..read]);
+        self.stats_mut().counters_mut().bytes_received += read as u64;
+        self.stats_mut().consecutive_ack_timeouts = 0;
+        if ACK != &ack[..read] {
+            self.stats_mut().counters_mut().ack_errors += 1;
+            return Err(in_command(Error::Ack, Some(&ack[..read])));
         }
-        // Read response bytes.
-        let response = self.read_frame(response_length + 2)?;
-        debug!("called function success!.... response: {:?}", response);
+
+        Ok(true)
+    }
+
+    /// Second half of [`PN532::send_command`]: read the response to
+    /// `command` once the PN532 is ready, returning the bytes following
+    /// `D5 command+1`.
+    fn finish_command(&mut self, command: u8, max_response: usize) -> Result<Vec<u8>> {
+        let mut response = vec![0; max_response];
+        let len = self.finish_command_into(command, &mut response)?;
+        response.truncate(len);
+
+        Ok(response)
+    }
+
+    /// Like [`PN532::finish_command`], but reads the response into `response`
+    /// and returns its length.
+    fn finish_command_into(&mut self, command: u8, response: &mut [u8]) -> Result<usize> {
+        let context = self.options().error_context;
+        let in_command = |e: Error, response: Option<&[u8]>| {
+            e.in_command(command, None, response.filter(|_| context))
+        };
+
+        // Read response bytes, asking for them again if they were corrupted.
+        let mut buf = [0; MAX_FRAME_LEN];
+        let mut retries = NACK_RETRIES;
+        let (frame, data) = loop {
+            let read = self.read_frame_data(&mut buf).map_err(|e| in_command(e, None))?;
+            self.hooks().frame_received(&mut buf[..read]);
+            self.stats_mut().counters_mut().bytes_received += read as u64;
+            let frame = &buf[..read];
+            debug!("{}", frame::Decoded(frame));
+            let result = decode_frame(frame);
+            if matches!(&result, Err(Error::Frame(e)) if e.is_corruption()) {
+                self.stats_mut().counters_mut().checksum_errors += 1;
+            }
+            match result {
+                Err(Error::Frame(e)) if e.is_corruption() && retries > 0 => {
+                    debug!("Corrupted response frame ({}), sending NACK", e);
+                    retries -= 1;
+                    self.send_nack().map_err(|e| in_command(e, None))?;
+                    if !self.wait_ready(self.timeouts().ack).map_err(|e| in_command(e, None))? {
+                        return Err(in_command(e.into(), Some(frame)));
+                    }
+                }
+                Ok(data) => break (frame, data),
+                Err(e) => return Err(in_command(e, Some(frame))),
+            }
+        };
         // Check that response is for the called function.
-        if !(response[0] == PN532TOHOST && response[1] == (command + 1)) {
-            return Err(box RuntimeError("Received unexpected command response!".to_owned()));
+        if !(data.len() >= 2 && data[0] == PN532TOHOST && data[1] == command.wrapping_add(1)) {
+            return Err(in_command(Error::UnexpectedResponse("Received unexpected command response!"), Some(frame)));
         }
 
         // Return response data.
-        Ok(Some(response[2..].to_owned()))
+        let len = (data.len() - 2).min(response.len());
+        response[..len].copy_from_slice(&data[2..2 + len]);
+        Ok(len)
+    }
+
+    /// Send a typed request and parse its response. Returns `None` if the
+    /// PN532 did not respond within the timeout.
+    #[doc(hidden)]
+    fn execute<R: Request>(&mut self, request: &R, timeout: Option<Duration>) -> Result<Option<R::Response>> {
+        let params = request.params();
+        match self.call_function(R::COMMAND, &params, timeout)? {
+            Some(response) => parse_response(self, request, &params, response).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Call PN532 GetFirmwareVersion function and return the IC, Ver, Rev,
+    /// and Support values.
+    fn get_firmware_version(&mut self) -> Result<FirmwareVersion> {
+        self.execute(&GetFirmwareVersion, None)?.ok_or(Error::Timeout)
     }
 
-    /// Call PN532 GetFirmwareVersion function and return a tuple with the IC,
-    /// Ver, Rev, and Support values.
-    fn get_firmware_version(&mut self) -> Result<Vec<u8>> {
-        let response = self.call_function(COMMAND_GETFIRMWAREVERSION, 4, &[], 0.5)?;
-        match response {
-            Some(response) => Ok(response),
-            None => Err(box RuntimeError("Failed to detect the PN532".to_owned()))
+    /// Run a PN532 self test, e.g. [`DiagnoseTest::Antenna`] to detect a
+    /// detached antenna.
+    ///
+    /// [`DiagnoseTest::EchoBack`] never answers: it is only started, and
+    /// [`DiagnoseResult::Running`] returned once the PN532 acknowledged it.
+    fn diagnose(&mut self, test: DiagnoseTest) -> Result<DiagnoseResult> {
+        if let DiagnoseTest::EchoBack { .. } = test {
+            let params = Diagnose { test }.params();
+            return if self.start_command(Command::Diagnose.code(), &params)? {
+                Ok(DiagnoseResult::Running)
+            } else {
+                Err(Error::Timeout)
+            };
         }
+        if let DiagnoseTest::Polling(BitRate::Kbps106) = test {
+            return Err(Error::InvalidArgument("the polling test runs at 212 or 424 kbps".into()));
+        }
+
+        self.execute(&Diagnose { test }, None)?.ok_or(Error::Timeout)
+    }
+
+    /// Call PN532 GetGeneralStatus function and return the last error, the
+    /// RF field, the targets being handled and the SAM status.
+    fn get_general_status(&mut self) -> Result<GeneralStatus> {
+        self.execute(&GetGeneralStatus, None)?.ok_or(Error::Timeout)
     }
 
     /// Configure the PN532 to read MiFare cards.
@@ -382,285 +684,2098 @@ trait PN532 {
     /// - 0x01, normal mode
     /// - 0x14, timeout 50ms * 20 = 1 second
     /// - 0x01, use IRQ pin
+    ///
     /// Note that no other verification is necessary as call_function will
     /// check the command was executed as expected.
     fn SAM_configuration(&mut self) -> Result<()> {
-        self.call_function(COMMAND_SAMCONFIGURATION, 0,&[0x01, 0x14, 0x01], 1.0)?;
+        self.configure_sam(SamMode::Normal, Duration::from_secs(1), true)
+    }
+
+    /// Select how the PN532 uses an attached SAM.  `timeout`, at most 12.75
+    /// seconds in steps of 50 ms, only applies to [`SamMode::VirtualCard`];
+    /// zero disables it.
+    fn configure_sam(&mut self, mode: SamMode, timeout: Duration, use_irq: bool) -> Result<()> {
+        let steps = timeout.as_millis() / 50;
+        let timeout = u8::try_from(steps)
+            .map_err(|_| Error::InvalidArgument(format!("SAM timeout must be at most 12.75 s, got {:?}", timeout)))?;
+        self.execute(&SamConfiguration { mode, timeout, use_irq }, None)?.ok_or(Error::Timeout)
+    }
+
+    /// Switch the PN532 to wired card mode and activate the attached SAM,
+    /// which becomes target 1 for [`PN532::sam_transceive`].  Returns the
+    /// SAM's target data.  No RF field is emitted until
+    /// [`PN532::sam_disconnect`].
+    fn sam_connect(&mut self) -> Result<Target> {
+        let use_irq = self.options().sam.map_or(true, |sam| sam.use_irq);
+        // The other modes can only be left through normal mode.
+        self.configure_sam(SamMode::Normal, Duration::ZERO, use_irq)?;
+        self.configure_sam(SamMode::WiredCard, Duration::ZERO, use_irq)?;
+        self.auth_cache().clear();
+        let request = InListPassiveTarget {
+            max_targets: 1,
+            baud: BaudModulation::Iso14443aType106,
+            initiator_data: Vec::new(),
+        };
+        let targets = self.execute(&request, None)?.ok_or(Error::Timeout)?;
+
+        targets.into_iter().next().ok_or(Error::UnexpectedResponse("No SAM answered in wired card mode!"))
+    }
+
+    /// Exchange an ISO/IEC 7816 APDU with the SAM activated by
+    /// [`PN532::sam_connect`], returning its response APDU.
+    fn sam_transceive(&mut self, apdu: &[u8]) -> Result<Vec<u8>> {
+        self.in_data_exchange(0x01, apdu)?.ok_or(Error::Timeout)
+    }
+
+    /// Release the SAM and go back to the SAM configuration of the
+    /// [`Options`], or normal mode without one.
+    fn sam_disconnect(&mut self) -> Result<()> {
+        self.execute(&InRelease { target: 0x01 }, None)?.ok_or(Error::Timeout)?;
+        match self.options().sam {
+            Some(sam) => self.execute(&sam, None)?.ok_or(Error::Timeout),
+            None => self.configure_sam(SamMode::Normal, Duration::ZERO, true),
+        }
+    }
+
+    /// Set the RFConfiguration item `item` to `data`, for the items without
+    /// a typed helper such as the analog settings.
+    fn rf_configuration(&mut self, item: u8, data: &[u8]) -> Result<()> {
+        self.execute(&RfConfiguration { item, data: data.to_vec() }, None)?.ok_or(Error::Timeout)
+    }
+
+    /// Switch the RF field on or off (CfgItem 0x01).  With `auto_rfca` the
+    /// PN532 only switches it on when no external field is detected.
+    fn set_rf_field(&mut self, on: bool, auto_rfca: bool) -> Result<()> {
+        self.rf_configuration(0x01, &[(auto_rfca as u8) << 1 | on as u8])
+    }
+
+    /// Set how long an initiator waits for ATR_RES, 102.4 ms by default, and
+    /// for a target's answer in InCommunicateThru and in InDataExchange with
+    /// FeliCa and MIFARE cards, 51.2 ms by default (CfgItem 0x02).
+    fn set_rf_timings(&mut self, atr_res_timeout: RfTimeout, retry_timeout: RfTimeout) -> Result<()> {
+        self.rf_configuration(0x02, &[0x00, atr_res_timeout.code(), retry_timeout.code()])
+    }
+
+    /// Set how many times the PN532 retries InCommunicateThru, and
+    /// InDataExchange with FeliCa and MIFARE cards, when the target is mute
+    /// or an error is detected (CfgItem 0x04).  0x00, the default, tries
+    /// once; 0xFF retries forever.
+    fn set_max_retry_com(&mut self, retries: u8) -> Result<()> {
+        self.rf_configuration(0x04, &[retries])
+    }
+
+    /// Set how many times the PN532 retries ATR_REQ (0xFF by default), PSL_REQ
+    /// or PPS (0x01 by default) and the activation of a passive target in
+    /// InListPassiveTarget (0xFF, forever, by default) (CfgItem 0x05).  0x00
+    /// tries once; 0xFF retries forever.
+    fn set_max_retries(&mut self, atr: u8, psl: u8, passive_activation: u8) -> Result<()> {
+        self.rf_configuration(0x05, &[atr, psl, passive_activation])
+    }
+
+    /// Start the RF regulation test: the PN532 transmits pseudo random data
+    /// continuously with `mode`, for EMC and antenna measurements.  Returns
+    /// once the PN532 acknowledged the command; end the test with
+    /// [`PN532::end_rf_regulation_test`].
+    fn rf_regulation_test(&mut self, mode: TxMode) -> Result<()> {
+        let params = RfRegulationTest { mode }.params();
+        if !self.start_command(Command::RfRegulationTest.code(), &params)? {
+            return Err(Error::Timeout);
+        }
+        info!("RF regulation test running");
+
         Ok(())
     }
 
-    /// Wait for a MiFare card to be available and return its UID when found.
+    /// End the RF regulation test, which stops at the next command, and
+    /// switch the RF field off.
+    fn end_rf_regulation_test(&mut self) -> Result<()> {
+        self.get_firmware_version()?;
+        self.set_rf_field(false, false)
+    }
+
+    /// Write the analog settings of every bit rate (CfgItems 0x0A to 0x0D),
+    /// e.g. `&AnalogPreset::LongRange.into()`.  They last until the PN532
+    /// is reset.
+    fn set_analog_settings(&mut self, settings: &AnalogSettings) -> Result<()> {
+        self.rf_configuration(0x0A, &settings.type_a_106)?;
+        self.rf_configuration(0x0B, &settings.felica)?;
+        self.rf_configuration(0x0C, &settings.type_b)?;
+        self.rf_configuration(0x0D, &settings.iso14443_4)
+    }
+
+    /// Put the PN532 into power down, where it draws a few µA until one of
+    /// `sources` wakes it up.  With `generate_irq` it then raises its IRQ
+    /// pin.  The next command wakes it up first with [`Transport::wake_up`],
+    /// so `sources` should include the host interface.
+    fn power_down(&mut self, sources: WakeupSources, generate_irq: Option<bool>) -> Result<()> {
+        self.execute(&PowerDown { wakeup_enable: sources.bits(), generate_irq }, None)?.ok_or(Error::Timeout)?;
+        self.stats_mut().asleep = true;
+        // The PN532 takes about 1 ms to go down after answering.
+        std::thread::sleep(Duration::from_millis(1));
+
+        Ok(())
+    }
+
+    /// Release all targets and switch the RF field off, then put the PN532
+    /// into power down if `power_down` is set. It wakes up again on any host
+    /// interface activity.
+    fn cleanup(&mut self, power_down: bool) -> Result<()> {
+        self.auth_cache().deactivate(0x00);
+        self.execute(&InRelease { target: 0x00 }, None)?;
+        self.set_rf_field(false, false)?;
+        if power_down {
+            self.power_down(WakeupSources::HOST, None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Wait for a MiFare card to be available and return it when found.
+    /// `baud` selects the modulation to look for, the found target is parsed
+    /// as an ISO/IEC 14443 type A target; type B cards are read with
+    /// [`PN532::read_passive_target_b`].
     /// Will wait up to timeout seconds and return None if no card is found,
-    /// otherwise a bytearray with the UID of the found card is returned.
-    fn read_passive_target(&mut self, card_baud: Option<u8>, timeout: f64) -> Result<Option<Vec<u8>>> {
-        // Send passive read command for 1 card.  Expect at most a 7 byte UUID.
-        let response = self.call_function(
-            COMMAND_INLISTPASSIVETARGET,
-            19,
-            &[0x01, card_baud.unwrap_or(MIFARE_ISO14443A)],
-            timeout)?;
-        match response {
+    /// otherwise the UID, ATQA, SAK and ATS (if any) of the found card are
+    /// returned.
+    ///
+    /// With [`Options::passive_activation_retries`] set, the PN532 gives up
+    /// on its own once the retries are spent, so this returns `None` right
+    /// away when no card is in the field instead of waiting for `timeout`.
+    fn read_passive_target(&mut self, baud: BaudModulation, timeout: f64) -> Result<Option<PassiveTarget>> {
+        // Selecting a card, even the same one again, resets its authentication.
+        self.auth_cache().clear();
+        // Send passive read command for 1 card.
+        let request = InListPassiveTarget {
+            max_targets: 1,
+            baud,
+            initiator_data: Vec::new(),
+        };
+        match self.execute(&request, Some(Duration::from_secs_f64(timeout)))? {
             // If no response is available return None to indicate no card is present.
             None => Ok(None),
-            Some(res) => {
-                // Check only 1 card with up to a 7 byte UID is present.
-                if res[0] != 0x01 {
-                    return Err(box RuntimeError("More than one card detected!".to_owned()));
-                }
-                if res[5] > 7 {
-                    return Err(box RuntimeError("Found card with unexpectedly long UID!".to_owned()));
-                }
-                // Return UID of card.
-                Ok(Some(res[6..6+(res[5] as usize)].to_owned()))
-            }
+            Some(targets) => select_single_target(self, &targets),
         }
     }
 
-    /// Authenticate specified block number for a MiFare classic card.  Uid
-    /// should be a byte array with the UID of the card, block number should be
-    /// the block to authenticate, key number should be the key type (like
-    /// `MIFARE_CMD_AUTH_A` or `MIFARE_CMD_AUTH_B`), and key should be a byte array
-    /// with the key data.  Returns True if the block was authenticated, or False
-    /// if not authenticated.
-    fn mifare_classic_authenticate_block(&mut self, uid: &[u8], block_number: u8, key_number: u8, key: &[u8]) -> Result<bool> {
-
-        // Build parameters for InDataExchange command to authenticate MiFare card.
-        let uid_len = uid.len();
-        let key_len = key.len();
-        let mut params = vec![0; 3 + uid_len + key_len];
-        params[0] = 0x01; // Max card numbers
-        params[1] = key_number;
-        params[2] = block_number;
-        params[3..3+key_len].copy_from_slice(key);
-        params[3+key_len..].copy_from_slice(uid);
+    /// Activate a type A card like [`PN532::read_passive_target`] and
+    /// return a handle offering the operations of its technology, see
+    /// [`session`].
+    fn read_tag(&mut self, timeout: f64) -> Result<Option<Tag<'_, Self>>> {
+        match self.read_passive_target(BaudModulation::Iso14443aType106, timeout)? {
+            Some(card) => Tag::new(self, card).map(Some),
+            None => Ok(None),
+        }
+    }
 
-        // Send InDataExchange request and verify response is 0x00.
-        let response = self.call_function(
-            COMMAND_INDATAEXCHANGE,
-            1,
-            params.as_slice(),
-            1.0,
-        )?;
+    /// Wait up to `timeout` seconds for an ISO/IEC 14443 type B card at
+    /// 106 kbps matching `polling`, e.g. ID cards, passports and transit
+    /// cards, and select it.  Returns None if no card is found.
+    fn read_passive_target_b(&mut self, polling: TypeBPolling, timeout: f64) -> Result<Option<TypeBTarget>> {
+        self.auth_cache().clear();
+        let request = InListPassiveTarget {
+            max_targets: 1,
+            baud: BaudModulation::Iso14443b106,
+            initiator_data: polling.initiator_data(),
+        };
+        let target = match self.execute(&request, Some(Duration::from_secs_f64(timeout)))?.as_deref() {
+            None | Some([]) => return Ok(None),
+            Some([target]) => TypeBTarget::try_from(target)?,
+            Some(_) => return Err(Error::UnexpectedResponse("More than one card detected!")),
+        };
+        self.auth_cache().select(target.target_number);
 
-        self.check_response(response)
+        Ok(Some(target))
     }
 
-    /// Read a block of data from the card.  Block number should be the block
-    /// to read.  If the block is successfully read a bytearray of length 16 with
-    /// data starting at the specified block will be returned.  If the block is
-    /// not read then None will be returned.
-    fn mifare_classic_read_block(&mut self, block_number: u8) -> Result<Vec<u8>> {
-
-        // Send InDataExchange request to read block of MiFare data.
-        let response = self.call_function(
-            COMMAND_INDATAEXCHANGE,
-            17,
-            &[0x01, MIFARE_CMD_READ, block_number],
-            1.0
-        )?;
-
-        if let Some(res) = response {
-            // Check first response is 0x00 to show success.
-            if res[0] != 0 {
-                Err(box PN532Error::error(res[0]))
-            } else {
-                // Return first 4 bytes since 16 bytes are always returned.
-                Ok(res[1..].into())
-            }
-        } else {
-            Ok(vec![])
+    /// Poll for a FeliCa card at `baud`, 212 or 424 kbps, for up to
+    /// `timeout` seconds, e.g. Suica or FeliCa Lite.  `system_code` selects
+    /// the cards to answer (0xFFFF for all), `request_code` asks for extra
+    /// data (1 for the system code) and `timeslots` (1, 2, 4, 8 or 16) is
+    /// the number of slots the cards may answer in.  Returns None if no
+    /// card answers.
+    fn felica_poll(&mut self, baud: BaudModulation, system_code: u16, request_code: u8, timeslots: u8, timeout: f64) -> Result<Option<FelicaTarget>> {
+        if !matches!(baud, BaudModulation::Felica212 | BaudModulation::Felica424) {
+            return Err(Error::InvalidArgument(format!("FeliCa polls at 212 or 424 kbps, not {:?}", baud)));
         }
+        if !matches!(timeslots, 1 | 2 | 4 | 8 | 16) {
+            return Err(Error::InvalidArgument(format!("FeliCa polls in 1, 2, 4, 8 or 16 timeslots, not {}", timeslots)));
+        }
+
+        self.auth_cache().clear();
+        let [sc0, sc1] = system_code.to_be_bytes();
+        let request = InListPassiveTarget {
+            max_targets: 1,
+            baud,
+            // POLLING command code, system code, request code and TSN.
+            initiator_data: vec![0x00, sc0, sc1, request_code, timeslots - 1],
+        };
+        let target = match self.execute(&request, Some(Duration::from_secs_f64(timeout)))?.as_deref() {
+            None | Some([]) => return Ok(None),
+            Some([target]) => FelicaTarget::try_from(target)?,
+            Some(_) => return Err(Error::UnexpectedResponse("More than one card detected!")),
+        };
+        self.auth_cache().select(target.target_number);
+
+        Ok(Some(target))
     }
 
-    /// Write a block of data to the card.  Block number should be the block
-    /// to write and data should be a byte array of length 4 with the data to
-    /// write.  If the data is successfully written then True is returned,
-    /// otherwise False is returned.
-    fn mifare_classic_write_block(&mut self, block_number: u8, data: &[u8]) -> Result<bool> {
-        assert_eq!(data.len(), 16);
+    /// Read `blocks` of 16 bytes from the FeliCa card `idm`, selected by
+    /// [`PN532::felica_poll`], with Read Without Encryption.  The blocks
+    /// refer to their service by index in `service_codes`.  A refusal of
+    /// the card is reported as [`Error::Felica`].
+    fn felica_read_without_encryption(&mut self, idm: &[u8; 8], service_codes: &[u16], blocks: &[FelicaBlock]) -> Result<Vec<[u8; 16]>> {
+        let code = felica::CMD_READ_WITHOUT_ENCRYPTION;
+        let frame = felica::command(code, idm, service_codes, blocks, &[])?;
+        let target = self.auth_cache().target();
+        let answer = self.in_data_exchange(target, &frame)?.ok_or(Error::Timeout)?;
+        let &[count, ref data @ ..] = felica::parse_response(code, idm, &answer)? else {
+            return Err(Error::UnexpectedResponse("FeliCa Read Without Encryption response is too short!"));
+        };
+        if count as usize != blocks.len() || data.len() < 16 * blocks.len() {
+            return Err(Error::UnexpectedResponse("FeliCa Read Without Encryption returned too few blocks!"));
+        }
 
-        let mut params = vec![0; 19];
-        params[0] = 0x01;
-        params[1] = MIFARE_CMD_WRITE;
-        params[2] = block_number;
-        params[3..].copy_from_slice(data);
+        Ok(data.chunks_exact(16).take(blocks.len()).map(|block| {
+            let mut data = [0; 16];
+            data.copy_from_slice(block);
+            data
+        }).collect())
+    }
+
+    /// Write `data`, 16 bytes per block, to `blocks` of the FeliCa card
+    /// `idm` with Write Without Encryption, e.g. to provision a FeliCa
+    /// Lite-S.  Addressed as for
+    /// [`PN532::felica_read_without_encryption`].
+    fn felica_write_without_encryption(&mut self, idm: &[u8; 8], service_codes: &[u16], blocks: &[FelicaBlock], data: &[[u8; 16]]) -> Result<()> {
+        self.ensure_writable()?;
+        if data.len() != blocks.len() {
+            return Err(Error::InvalidArgument(format!("{} blocks of data for {} blocks", data.len(), blocks.len())));
+        }
 
-        let response = self.call_function(
-            COMMAND_INDATAEXCHANGE,
-            1,
-            params.as_slice(),
-            1.0
-        )?;
+        let code = felica::CMD_WRITE_WITHOUT_ENCRYPTION;
+        let frame = felica::command(code, idm, service_codes, blocks, &data.concat())?;
+        let target = self.auth_cache().target();
+        let answer = self.in_data_exchange(target, &frame)?.ok_or(Error::Timeout)?;
+        felica::parse_response(code, idm, &answer)?;
 
-        self.check_response(response)
+        Ok(())
     }
 
-    fn ntag2xx_write_block(&mut self, block_number: u8, data: &[u8]) -> Result<bool> {
-        assert_eq!(data.len(), 4);
+    /// Like [`PN532::read_passive_target`], but activate up to `max_targets`
+    /// cards (1 or 2), e.g. two stacked cards.  Every card found is returned
+    /// with its logical target number; the card operations address the first
+    /// one until another is picked with [`PN532::select_target`].
+    fn read_passive_targets(&mut self, baud: BaudModulation, max_targets: u8, timeout: f64) -> Result<Vec<PassiveTarget>> {
+        if !(1..=2).contains(&max_targets) {
+            return Err(Error::InvalidArgument(format!("the PN532 activates 1 or 2 targets, got {}", max_targets)));
+        }
+        self.auth_cache().clear();
+        let request = InListPassiveTarget {
+            max_targets,
+            baud,
+            initiator_data: Vec::new(),
+        };
+        let targets = match self.execute(&request, Some(Duration::from_secs_f64(timeout)))? {
+            None => return Ok(Vec::new()),
+            Some(targets) => targets.iter().map(PassiveTarget::try_from).collect::<Result<Vec<_>>>()?,
+        };
+        if let Some(first) = targets.first() {
+            self.auth_cache().select(first.target_number);
+        }
 
-        let mut params = vec![0; 3+data.len()];
-        params[0] = 0x01;
-        params[1] = MIFARE_ULTRALIGHT_CMD_WRITE;
-        params[2] = block_number;
-        params[3..].copy_from_slice(data);
+        Ok(targets)
+    }
 
-        let response = self.call_function(
-            COMMAND_INDATAEXCHANGE,
-            1,
-            params.as_slice(),
-            1.0
-        )?;
+    /// Select target `tg`, the logical number of one of the cards returned
+    /// by [`PN532::read_passive_targets`], and address the card operations
+    /// (MIFARE Classic, NTAG2xx, ...) to it.  Deselects the previously
+    /// selected card, which loses its authentication.
+    fn select_target(&mut self, tg: u8) -> Result<()> {
+        self.auth_cache().clear();
+        self.execute(&InSelect { target: tg }, None)?.ok_or(Error::Timeout)?;
+        self.auth_cache().select(tg);
 
-        self.check_response(response)
+        Ok(())
     }
-    
-    fn ntag2xx_read_block(&mut self, block_number: u8) -> Result<Vec<u8>>{
-        self.mifare_classic_read_block(block_number).map(| res | res[..4].to_owned())
+
+    /// Deselect target `tg`, or all targets if 0, halting the card to save
+    /// power.  The PN532 keeps its information, so
+    /// [`PN532::select_target`] activates it again.
+    fn deselect_target(&mut self, tg: u8) -> Result<()> {
+        self.execute(&InDeselect { target: tg }, None)?.ok_or(Error::Timeout)?;
+        self.auth_cache().deactivate(tg);
+
+        Ok(())
     }
 
-    /// Read the state of the PN532's GPIO pins.
-    /// If `pin` is None, returns 3 bytes containing the pin state as `(None, Vec<u7>)`
-    /// where:
-    /// ```
-    /// P3[0] = P30,   P7[0] = 0,   I[0] = I0,
-    /// P3[1] = P31,   P7[1] = P71, I[1] = I1,
-    /// P3[2] = P32,   P7[2] = P72, I[2] = 0,
-    /// P3[3] = P33,   P7[3] = 0,   I[3] = 0,
-    /// P3[4] = P34,   P7[4] = 0,   I[4] = 0,
-    /// P3[5] = P35,   P7[5] = 0,   I[5] = 0,
-    /// P3[6] = 0,     P7[6] = 0,   I[6] = 0,
-    /// P3[7] = 0,     P7[7] = 0,   I[7] = 0,
-    /// ```
-    /// If `pin` is not None, returns the specified pin state as `(Bool, None)`
-    fn read_gpio(&mut self, pin: Option<PN532Gpio>) -> Result<(Option<bool>, Option<Vec<u8>>)> {
-        let response = self.call_function(
-            COMMAND_READGPIO,
-            3,
-            &[],
-            1.0
-        )?.unwrap();
-        info!("GPIO Status: {:?}", response);
+    /// Release target `tg`, or all targets if 0.  The PN532 forgets it; it
+    /// has to be activated again with [`PN532::read_passive_targets`].
+    fn release_target(&mut self, tg: u8) -> Result<()> {
+        self.execute(&InRelease { target: tg }, None)?.ok_or(Error::Timeout)?;
+        self.auth_cache().deactivate(tg);
+
+        Ok(())
+    }
 
-        match pin {
-            Some(pin) => {
-                Ok((Some(pin.get(response[pin.idx()])), None))
+    /// Wait up to `timeout` for a card, looking for one in short bursts every
+    /// `interval` and saving power in between as `mode` says.  Trades
+    /// latency for power, e.g. for battery powered door locks.
+    fn low_power_poll(&mut self, baud: BaudModulation, interval: Duration, timeout: Duration, mode: LowPowerMode) -> Result<Option<PassiveTarget>> {
+        let start = Instant::now();
+        loop {
+            if let Some(target) = self.read_passive_target(baud, LOW_POWER_BURST.as_secs_f64())? {
+                return Ok(Some(target));
             }
-            None => Ok((None, Some(response[..3].to_owned())))
+            // The PN532 may still be looking for a card.
+            self.send_ack()?;
+            if start.elapsed() + interval >= timeout {
+                return Ok(None);
+            }
+            match mode {
+                LowPowerMode::RfOff => self.set_rf_field(false, false)?,
+                LowPowerMode::PowerDown => self.power_down(WakeupSources::HOST, None)?,
+            }
+            std::thread::sleep(interval);
         }
     }
 
-    /// Write the state to the PN532's GPIO pins.
-    /// If p3 or p7 is not `None`, set the pins with p3 or p7, there is
-    /// no need to read pin states before write with the param p3 or p7
-    /// bits:
-    /// ```
-    /// P3[0] = P30,   P7[0] = 0,
-    /// P3[1] = P31,   P7[1] = P71,
-    /// P3[2] = P32,   P7[2] = P72,
-    /// P3[3] = P33,   P7[3] = nu,
-    /// P3[4] = P34,   P7[4] = nu,
-    /// P3[5] = P35,   P7[5] = nu,
-    /// P3[6] = nu,    P7[6] = nu,
-    /// P3[7] = Val,   P7[7] = Val,
-    /// ```
-    /// For each port that is validated (bit Val = 1), all the bits are applied
-    /// simultaneously. It is not possible for example to modify the state of
-    /// the port P32 without applying a value to the ports P30, P31, P33, P34
-    /// and P35.
-    ///
-    /// If p3 and p7 are `None`, set one pin with the params 'pin' and 'state'
-    fn write_gpio(&mut self, pin: PN532Gpio, state: bool, p3: Option<u8>, p7: Option<u8>) -> Result<()> {
-        let mut params = [0x00; 2];
-        if let (Some(p3), Some(p7)) = (p3, p7) {
-            params[0] = if p3 == 0 { 0x00 } else { 0x80 | p3 };
-            params[1] = if p7 == 0 { 0x00 } else { 0x80 | p7 };
-            self.call_function(
-                COMMAND_WRITEGPIO,
-                1,
-                &params,
-                1.0
-            ).map(|_|())
-        } else {
-            match pin {
-                PN532Gpio::I0 | PN532Gpio::I1 => Ok(()),
-                _ => {
-                    let response = self.read_gpio(None)?.1.unwrap();
-                    params[pin.idx()] = if state {
-                        0x80 | response[pin.idx()] | (1 << pin.offset())
-                    } else {
-                        0x80 | response[pin.idx()] & !(1 << pin.offset())
-                    };
+    /// Start looking for a card without waiting for one, for control loops
+    /// that cannot block. Sends InListPassiveTarget for one target with
+    /// modulation `baud` and returns once the PN532 acknowledged it; the card
+    /// is then collected with [`PN532::poll_result`], or the detection
+    /// cancelled with [`PN532::abort`].
+    fn start_passive_detection(&mut self, baud: BaudModulation) -> Result<PassiveDetection> {
+        self.auth_cache().clear();
+        let request = InListPassiveTarget {
+            max_targets: 1,
+            baud,
+            initiator_data: Vec::new(),
+        };
+        if !self.start_command(Command::InListPassiveTarget.code(), &request.params())? {
+            return Err(Error::Timeout);
+        }
 
-                    self.call_function(
-                        COMMAND_WRITEGPIO,
-                        1,
-                        &params,
-                        1.0
-                    ).map(|_| ())
-                }
-            }
+        Ok(PassiveDetection { request })
+    }
+
+    /// Collect the result of `detection`, returning [`nb::Error::WouldBlock`]
+    /// until the PN532 has a response. `None` means the PN532 gave up without
+    /// finding a card, after the number of retries set with RFConfiguration.
+    fn poll_result(&mut self, detection: &PassiveDetection) -> nb::Result<Option<PassiveTarget>, Error> {
+        if !self.is_ready()? {
+            return Err(nb::Error::WouldBlock);
         }
+        let command = Command::InListPassiveTarget;
+        let response = self.finish_command(command.code(), command.max_response_len())?;
+
+        Ok(select_single_target(self, &detection.request.parse(response)?)?)
     }
 
-    /// The host controller uses this command to configure the PN532 as
-    /// target.
-    /// :params mode: a byte indicating which mode the PN532 should respect.
-    /// :params mifare_params: information needed to be able to be
-    /// activated at 106 kbps in passive mode.
-    /// :params felica_params: information to be able to respond to a polling
-    /// request at 212/424 kbps in passive mode.
-    /// :params nfcid3t: used in the ATR_RES in case of ATR_REQ received from
-    /// the initiator
-    /// :params gt: an array containing the general bytes to be used in the
-    /// ATR_RES. This information is optional and the length is not fixed
-    /// (max. 47 bytes),
-    /// :params tk: an array containing the historical bytes to be used in the
-    /// ATS when PN532 is in ISO/IEC14443-4 PICC emulation mode. This
-    /// information is optional.
-    /// :returns mode: a byte indicating in which mode the PN532 has been
-    /// activated.
-    /// :returns initiator_command: an array containing the first valid frame
-    /// received by the PN532 once the PN532 has been initialized.
-    fn tg_init_as_target(&mut self, mode: u8,
-                         mifare_params: [u8; 6], felica_params: [u8; 18], nfcid3t: [u8; 10],
-                         gt: Option<&[u8]>, tk: Option<&[u8]>, timeout: f64) -> Result<Option<(u8, Vec<u8>)>> {
-        let mut params = Vec::new();
-        params.push(mode);
-        params.extend_from_slice(&mifare_params);
-        params.extend_from_slice(&felica_params);
-        params.extend_from_slice(&nfcid3t);
-        let push_slice = | params: &mut Vec<u8>, slice: Option<&[u8]> | {
-            if let Some(slice) = slice {
-                params.push(slice.len() as u8);
-                params.extend_from_slice(slice);
-            } else { params.push(0x00) }
-        };
-        push_slice(&mut params, gt);
-        push_slice(&mut params, tk);
-
-        let response = self.call_function(
-            COMMAND_TGINITASTARGET,
-            64,
-            params.as_slice(),
-            timeout
-        )?;
-        // Try to read 64 bytes although the response length is not fixed
-        if let Some(response) = response {
-            Ok(Some((response[0], response[1..].to_owned())))
-        } else {
-            Ok(None)
+    /// Cancel `detection`, which is no longer wanted.  The PN532 stops
+    /// looking for a card and is ready for the next command.
+    fn abort(&mut self, _detection: PassiveDetection) -> Result<()> {
+        self.send_ack()
+    }
+
+    /// Send an ACK frame, which aborts the command the PN532 is executing.
+    fn send_ack(&mut self) -> Result<()> {
+        self.stats_mut().counters_mut().bytes_sent += ACK.len() as u64;
+        self.write_data(ACK)
+    }
+
+    /// Send a NACK frame, which makes the PN532 send its last response
+    /// again.
+    fn send_nack(&mut self) -> Result<()> {
+        self.stats_mut().counters_mut().bytes_sent += NACK.len() as u64;
+        self.write_data(NACK)
+    }
+
+    /// Send `data` to the activated `target` with InDataExchange and return
+    /// its answer, or `None` if the PN532 did not respond in time.
+    ///
+    /// An answer longer than the PN532's buffer is chained: the MI bit of
+    /// the status byte is set while more follows, and each following part
+    /// is fetched with an InDataExchange carrying no data.  The parts are
+    /// concatenated, so large reads such as DESFire files come back whole.
+    fn in_data_exchange(&mut self, target: u8, data: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.in_data_exchange_with_timeout(target, data, None)
+    }
+
+    /// [`PN532::in_data_exchange`] waiting `timeout` for each part of the
+    /// answer, instead of the InDataExchange response timeout, e.g. as the
+    /// frame waiting time of an ISO/IEC 14443-4 card tells, see
+    /// [`Ats::response_timeout`].
+    fn in_data_exchange_with_timeout(&mut self, target: u8, data: &[u8], timeout: Option<Duration>) -> Result<Option<Vec<u8>>> {
+        let command = Command::InDataExchange;
+        let mut request = InDataExchange { target, data: data.to_vec() };
+        let mut answer = Vec::new();
+        loop {
+            let params = request.params();
+            let response = match self.call_function(command, &params, timeout)? {
+                Some(response) => response,
+                None => return Ok(None),
+            };
+            let raw = self.options().error_context.then(|| response.clone());
+            let result = request.parse_part(response);
+            count_chip_error(self, &result);
+            let (part, more) = result.map_err(|e| match &raw {
+                Some(response) => e.in_command(command.code(), Some(&params), Some(response)),
+                None => e.in_command(command.code(), None, None),
+            })?;
+            answer.extend_from_slice(&part);
+            if !more {
+                return Ok(Some(answer));
+            }
+            debug!("InDataExchange answer continues, {} bytes so far", answer.len());
+            request.data.clear();
         }
     }
 
-    fn check_response(&self, response: Option<Vec<u8>>) -> Result<bool> {
-        if let Some(res) = response {
-            if res[0] != 0x00 {
-                Err(box PN532Error::error(res[0]))
-            } else {
-                Ok(true)
+    /// Activate an NFCIP-1 target such as a phone or another PN532 with
+    /// InJumpForDEP, in active or passive mode at `baud`, and select it so
+    /// that [`PN532::dep_exchange`] talks to it.
+    ///
+    /// `passive_initiator_data` is the ID of the target to activate at
+    /// 106 kbps, or the polling request payload, mandatory at 212 and
+    /// 424 kbps; it is only used in passive mode.  `nfcid3` and
+    /// `general_bytes` (at most 48) go into ATR_REQ.
+    fn jump_for_dep(&mut self, active: bool, baud: BitRate, passive_initiator_data: Option<&[u8]>, nfcid3: Option<[u8; 10]>, general_bytes: Option<&[u8]>) -> Result<DepTarget> {
+        let passive_initiator_data = if active { None } else { passive_initiator_data };
+        match (baud, passive_initiator_data) {
+            (BitRate::Kbps106, Some(data)) if data.len() != 4 => {
+                return Err(Error::InvalidArgument(format!("the target ID is 4 bytes, got {}", data.len())));
             }
-        } else {
-            Ok(false)
+            (BitRate::Kbps212 | BitRate::Kbps424, None) if !active => {
+                return Err(Error::InvalidArgument("passive activation at 212 and 424 kbps needs the polling request payload".to_owned()));
+            }
+            (BitRate::Kbps212 | BitRate::Kbps424, Some(data)) if data.len() != 5 => {
+                return Err(Error::InvalidArgument(format!("the polling request payload is 5 bytes, got {}", data.len())));
+            }
+            _ => {}
         }
+        if general_bytes.is_some_and(|bytes| bytes.len() > 48) {
+            return Err(Error::InvalidArgument("at most 48 general bytes fit in ATR_REQ".to_owned()));
+        }
+
+        self.auth_cache().clear();
+        let request = InJumpForDep {
+            active,
+            baud,
+            passive_initiator_data: passive_initiator_data.map(<[u8]>::to_vec),
+            nfcid3i: nfcid3,
+            general_bytes: general_bytes.map(<[u8]>::to_vec),
+        };
+        let target = self.execute(&request, None)?.ok_or(Error::Timeout)?;
+        self.auth_cache().select(target.target_number);
+
+        Ok(target)
+    }
+
+    /// Exchange DEP data with the target activated by
+    /// [`PN532::jump_for_dep`] and return its answer.  Long answers are
+    /// chained as in [`PN532::in_data_exchange`].
+    fn dep_exchange(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+        let target = self.auth_cache().target();
+        self.in_data_exchange(target, data)?.ok_or(Error::Timeout)
+    }
+
+    /// Activate ISO-DEP on `target` with InATR, then switch it to `rate` in
+    /// both directions with InPSL, so that the following InDataExchange with
+    /// it run faster.  Returns the ATS.
+    fn select_and_upgrade_speed(&mut self, target: &PassiveTarget, rate: BitRate) -> Result<Vec<u8>> {
+        if !target.is_iso14443_4() {
+            return Err(Error::InvalidArgument(format!("card {} does not support ISO/IEC 14443-4", target.uid)));
+        }
+
+        let atr = InAtr { target: target.target_number, nfcid3i: None, general_bytes: None };
+        let ats = self.execute(&atr, None)?.ok_or(Error::Timeout)?;
+        let psl = InPsl {
+            target: target.target_number,
+            initiator_to_target: rate,
+            target_to_initiator: rate,
+        };
+        self.execute(&psl, None)?.ok_or(Error::Timeout)?;
+
+        Ok(ats)
+    }
+
+    /// Authenticate specified block number for a MiFare classic card.  Uid
+    /// should be the UID of the card, block should be
+    /// the block to authenticate, key number should be the key type (like
+    /// `MIFARE_CMD_AUTH_A` or `MIFARE_CMD_AUTH_B`), and key should be a byte array
+    /// with the key data.  Returns True if the block was authenticated, or False
+    /// if the card refused the key; it then halts until selected again.
+    /// Authenticating a block of the sector that is already authenticated
+    /// with the same key is skipped, see [`AuthCache`].
+    fn mifare_classic_authenticate_block(&mut self, uid: &Uid, block: Block, key_number: u8, key: &[u8]) -> Result<bool> {
+        if self.auth_cache().is_authenticated(uid, block.sector(), key_number, key) {
+            return Ok(true);
+        }
+        self.auth_cache().clear();
+
+        // Build parameters for InDataExchange command to authenticate MiFare card.
+        let mut data = vec![key_number, block.0];
+        data.extend_from_slice(key);
+        data.extend_from_slice(uid.as_bytes());
+
+        // Send InDataExchange request and verify response is 0x00.
+        let target = self.auth_cache().target();
+        let authenticated = match self.in_data_exchange(target, &data) {
+            Ok(answer) => answer.is_some(),
+            Err(e) if refused_by_card(&e) => false,
+            Err(e) => return Err(e),
+        };
+        if authenticated {
+            self.auth_cache().record(uid, block.sector(), key_number, key);
+        }
+
+        Ok(authenticated)
+    }
+
+    /// Read a block of data from the card.  Block should be the block
+    /// to read.  If the block is successfully read a bytearray of length 16 with
+    /// data starting at the specified block will be returned.  If the block is
+    /// not read then an empty bytearray will be returned.
+    fn mifare_classic_read_block(&mut self, block: Block) -> Result<Vec<u8>> {
+        let mut buf = [0; 16];
+        if self.mifare_classic_read_block_into(block, &mut buf)? {
+            Ok(buf.to_vec())
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Read a block into `buf` without allocating, for hot loops such as
+    /// dumping a whole card.  Returns `false` if the card did not answer or
+    /// refused the read, as the access conditions of the block may; it then
+    /// halts until selected again.
+    fn mifare_classic_read_block_into(&mut self, block: Block, buf: &mut [u8; 16]) -> Result<bool> {
+        let result = data_exchange_into(self, &[MIFARE_CMD_READ, block.0], buf);
+        if !matches!(result, Ok(Some(16))) {
+            self.auth_cache().clear();
+        }
+        match result {
+            Ok(Some(16)) => Ok(true),
+            Ok(Some(_)) => Err(Error::UnexpectedResponse("MIFARE Classic READ response is too short!")),
+            Ok(None) => Ok(false),
+            Err(e) if refused_by_card(&e) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Read the consecutive `blocks` into one buffer, authenticating each
+    /// sector with `key` as it is reached.  Sectors are authenticated once
+    /// (see [`AuthCache`]) and every block is read into the same buffer.
+    fn mifare_classic_read_blocks(&mut self, uid: &Uid, blocks: Range<Block>, key_number: u8, key: &[u8]) -> Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(16 * blocks.end.0.saturating_sub(blocks.start.0) as usize);
+        let mut buf = [0; 16];
+        for block in (blocks.start.0..blocks.end.0).map(Block) {
+            if !self.mifare_classic_authenticate_block(uid, block, key_number, key)? {
+                return Err(Error::Authentication(block.sector()));
+            }
+            if !self.mifare_classic_read_block_into(block, &mut buf)? {
+                return Err(Error::Timeout);
+            }
+            data.extend_from_slice(&buf);
+        }
+
+        Ok(data)
+    }
+
+    /// Write `data`, a multiple of 16 bytes, to consecutive blocks starting at
+    /// `start`, authenticating each sector with `key` as it is reached.  The
+    /// whole range is checked before anything is written: it may not contain
+    /// a sector trailer or block 0.
+    fn mifare_classic_write_blocks(&mut self, uid: &Uid, start: Block, data: &[u8], key_number: u8, key: &[u8]) -> Result<()> {
+        self.ensure_writable()?;
+        if data.len() % 16 != 0 {
+            return Err(Error::InvalidArgument(format!("MIFARE Classic blocks are 16 bytes, got {} bytes of data", data.len())));
+        }
+        let count = data.len() / 16;
+        if start.0 as usize + count > u8::MAX as usize + 1 {
+            return Err(Error::InvalidArgument(format!("{} blocks starting at {} run past the last block", count, start)));
+        }
+        let blocks = (start.0 as usize..start.0 as usize + count).map(|block| Block(block as u8));
+        if let Some(trailer) = blocks.clone().find(|block| block.is_trailer()) {
+            return Err(Error::SectorTrailer(trailer));
+        }
+        if blocks.clone().any(|block| block.is_manufacturer()) {
+            return Err(Error::ManufacturerBlock);
+        }
+
+        for (block, data) in blocks.zip(data.chunks_exact(16)) {
+            if !self.mifare_classic_authenticate_block(uid, block, key_number, key)? {
+                return Err(Error::Authentication(block.sector()));
+            }
+            if !self.mifare_classic_write_block(block, data)? {
+                return Err(Error::Timeout);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write block 0, UID included, of a gen2 (direct write) magic card
+    /// `uid`, which accepts it through the plain WRITE command once sector 0
+    /// is authenticated.  The BCC of a 4 byte UID is checked first, a wrong
+    /// one makes the card unselectable.
+    fn magic_gen2_write_block0(&mut self, uid: &Uid, data: &[u8; 16]) -> Result<()> {
+        self.ensure_writable()?;
+        classic::validate_manufacturer_block(data, uid.len())?;
+        if !classic_write(self, &classic_write_params(Block(0), data))? {
+            return Err(Error::Timeout);
+        }
+
+        Ok(())
+    }
+
+    /// Write block 0, UID included, of a gen1a magic card through its
+    /// backdoor: halt the card, unlock it with a 7 bit 0x40 and 0x43, then
+    /// WRITE without authentication, all with [`PN532::communicate_thru`].
+    /// The BCC is checked first if `uid`, the card's, is 4 bytes long.  The
+    /// default [`RawFraming`] is restored afterwards, and the card has to
+    /// be selected again.
+    fn magic_gen1a_write_block0(&mut self, uid: &Uid, data: &[u8; 16]) -> Result<()> {
+        self.ensure_writable()?;
+        classic::validate_manufacturer_block(data, uid.len())?;
+        self.auth_cache().clear();
+
+        let result = magic_gen1a_write(self, data);
+        self.set_raw_framing(RawFraming::default())?;
+        result
+    }
+
+    /// Probe whether the selected MIFARE Classic card `uid` is a magic clone:
+    /// whether it answers the gen1a backdoor, and, if `keys` opens sector 0
+    /// and writing is enabled, whether it accepts block 0 writes like a gen2
+    /// card.  The gen2 probe writes block 0 back with its own content, a
+    /// genuine card refuses it.  The card is selected again afterwards.
+    fn detect_magic(&mut self, uid: &Uid, keys: &KeyMap) -> Result<MagicCard> {
+        let target = self.auth_cache().target();
+        self.auth_cache().clear();
+        let gen1a = magic_gen1a_unlock(self, false);
+        self.set_raw_framing(RawFraming::default())?;
+        wake_target(self, target)?;
+        let gen1a = match gen1a {
+            Ok(()) => true,
+            Err(e) if matches!(e.root(), Error::Status(_) | Error::Timeout | Error::UnexpectedResponse(_)) => false,
+            Err(e) => return Err(e),
+        };
+
+        let sector = Sector::new(0)?;
+        if keys.keys(sector).is_empty() || self.ensure_writable().is_err() {
+            return Ok(MagicCard { gen1a, gen2: None });
+        }
+        match authenticate_sector(self, uid, sector, keys) {
+            Ok(_) => {}
+            Err(e) if matches!(e, Error::Authentication(_)) || refused_by_card(&e) => {
+                wake_target(self, target)?;
+                return Ok(MagicCard { gen1a, gen2: None });
+            }
+            Err(e) => return Err(e),
+        }
+        let mut block0 = [0; 16];
+        if !self.mifare_classic_read_block_into(Block(0), &mut block0)? {
+            wake_target(self, target)?;
+            return Ok(MagicCard { gen1a, gen2: None });
+        }
+        // Never write back a block 0 the card could not be selected with.
+        if classic::validate_manufacturer_block(&block0, uid.len()).is_err() {
+            return Ok(MagicCard { gen1a, gen2: None });
+        }
+        let gen2 = classic_write(self, &classic_write_params(Block(0), &block0))?;
+        if !gen2 {
+            wake_target(self, target)?;
+        }
+
+        Ok(MagicCard { gen1a, gen2: Some(gen2) })
+    }
+
+    /// Tell the technology of `card` from its SAK, ATQA and ATS, and for
+    /// Type 2 tags from their answer to GET_VERSION, for which the card is
+    /// selected.
+    fn identify_tag(&mut self, card: &PassiveTarget) -> Result<TagKind> {
+        if let Some(kind) = TagKind::from_activation(card) {
+            return Ok(kind);
+        }
+
+        self.select_target(card.target_number)?;
+        match self.ntag2xx_get_version() {
+            Ok(version) => Ok(version.variant().into()),
+            // Plain Ultralight and Ultralight C NAK it, and halt.
+            Err(e) if matches!(e.root(), Error::Status(_) | Error::Timeout | Error::UnexpectedResponse(_)) => {
+                wake_target(self, card.target_number)?;
+                Ok(TagKind::Ultralight)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Read the blocks of `sector` but its trailer, authenticating with
+    /// the first of the keys `keys` holds for it that works: 48 bytes, or
+    /// 240 for the large sectors of a 4K card.  Sector 0 starts with the
+    /// manufacturer block.
+    fn read_sector(&mut self, uid: &Uid, sector: Sector, keys: &KeyMap) -> Result<Vec<u8>> {
+        authenticate_sector(self, uid, sector, keys)?;
+        let mut data = Vec::with_capacity(16 * (sector.block_count() - 1) as usize);
+        let mut buf = [0; 16];
+        for block in sector.blocks().filter(|block| !block.is_trailer()) {
+            if !self.mifare_classic_read_block_into(block, &mut buf)? {
+                return Err(Error::Timeout);
+            }
+            data.extend_from_slice(&buf);
+        }
+
+        Ok(data)
+    }
+
+    /// Write the blocks of `sector` but its trailer, laid out as
+    /// [`PN532::read_sector`] returns them, authenticating with the first
+    /// of the keys `keys` holds for it that works.  The manufacturer block
+    /// of sector 0 is read-only, its 16 bytes are skipped.  Use
+    /// [`PN532::write_sector_trailer`] for the trailer.
+    fn write_sector(&mut self, uid: &Uid, sector: Sector, data: &[u8], keys: &KeyMap) -> Result<()> {
+        self.ensure_writable()?;
+        let len = 16 * (sector.block_count() - 1) as usize;
+        if data.len() != len {
+            return Err(Error::InvalidArgument(format!("{} holds {} bytes of data, got {}", sector, len, data.len())));
+        }
+
+        authenticate_sector(self, uid, sector, keys)?;
+        let blocks = sector.blocks().filter(|block| !block.is_trailer());
+        for (block, data) in blocks.zip(data.chunks_exact(16)).filter(|(block, _)| !block.is_manufacturer()) {
+            if !self.mifare_classic_write_block(block, data)? {
+                return Err(Error::Timeout);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read every sector of the MIFARE Classic `card`, trying the keys
+    /// `keys` holds for each in order.  Sectors no key opens and blocks the
+    /// access conditions protect are recorded as missing rather than failing
+    /// the dump.
+    fn dump_classic(&mut self, card: &PassiveTarget, keys: &KeyMap) -> Result<ClassicDump> {
+        let sectors = card.classic_sectors()
+            .ok_or_else(|| Error::InvalidArgument(format!("SAK {:#04x} is not a MIFARE Classic", card.sak)))?;
+
+        let mut dump = ClassicDump { uid: card.uid, sectors: Vec::with_capacity(sectors as usize) };
+        let mut buf = [0; 16];
+        for sector in (0..sectors).map(Sector::new) {
+            let sector = sector?;
+            let mut sector_dump = SectorDump { sector, key: None, blocks: Vec::new(), access: None };
+            match authenticate_sector(self, &card.uid, sector, keys) {
+                Ok(key) => sector_dump.key = Some(key),
+                Err(Error::Authentication(_)) => {
+                    // The card halts on a failed authentication.
+                    wake_target(self, card.target_number)?;
+                    dump.sectors.push(sector_dump);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+
+            for block in sector.blocks() {
+                if self.mifare_classic_read_block_into(block, &mut buf)? {
+                    sector_dump.blocks.push(Some(buf));
+                    continue;
+                }
+                // Refused by the access conditions: the card halts, wake it
+                // and authenticate again for the next block.
+                sector_dump.blocks.push(None);
+                wake_target(self, card.target_number)?;
+                if let Some(key) = sector_dump.key.filter(|_| !block.is_trailer()) {
+                    if !self.mifare_classic_authenticate_block(&card.uid, block, key.key_type.auth_command(), &key.key)? {
+                        return Err(Error::Authentication(sector));
+                    }
+                }
+            }
+            sector_dump.access = sector_dump.blocks.last().copied().flatten()
+                .and_then(|trailer| AccessBits::decode([trailer[6], trailer[7], trailer[8]]).ok());
+            dump.sectors.push(sector_dump);
+        }
+
+        Ok(dump)
+    }
+
+    /// Write `dump` back to the MIFARE Classic `card`, authenticating each
+    /// sector with the keys `keys` holds for the card, which may differ from
+    /// the ones of the dumped card.  Blocks already matching the dump are not
+    /// written again, block 0 and the trailers only if `options` allow it.
+    fn restore_classic(&mut self, card: &PassiveTarget, dump: &ClassicDump, keys: &KeyMap, options: &RestoreOptions) -> Result<RestoreReport> {
+        if !options.dry_run {
+            self.ensure_writable()?;
+        }
+        let sectors = card.classic_sectors()
+            .ok_or_else(|| Error::InvalidArgument(format!("SAK {:#04x} is not a MIFARE Classic", card.sak)))?;
+        if dump.sectors.len() > sectors as usize {
+            return Err(Error::InvalidArgument(format!("the dump has {} sectors, the card {}", dump.sectors.len(), sectors)));
+        }
+
+        let mut report = RestoreReport { dry_run: options.dry_run, ..RestoreReport::default() };
+        let mut buf = [0; 16];
+        for sector_dump in &dump.sectors {
+            let sector = sector_dump.sector;
+            let key = match authenticate_sector(self, &card.uid, sector, keys) {
+                Ok(key) => key,
+                Err(Error::Authentication(_)) => {
+                    wake_target(self, card.target_number)?;
+                    report.skipped.extend(sector.blocks().map(DumpAddress::Block));
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            for (block, data) in sector.blocks().zip(&sector_dump.blocks) {
+                let data = match data {
+                    Some(_) if block.is_trailer() && options.trailers => sector_dump.restored_trailer(),
+                    Some(_) if block.is_trailer() || block.is_manufacturer() && !options.manufacturer => None,
+                    data => *data,
+                };
+                let Some(data) = data else {
+                    report.skipped.push(DumpAddress::Block(block));
+                    continue;
+                };
+
+                let current = if self.mifare_classic_read_block_into(block, &mut buf)? {
+                    Some(buf)
+                } else {
+                    // Refused by the access conditions.
+                    reauthenticate(self, card, block, &key)?;
+                    None
+                };
+                // Key A always reads as zeros, compare the rest of a trailer.
+                let unchanged = match current {
+                    Some(current) if block.is_trailer() => current[6..] == data[6..] && data[..6] == key.key,
+                    Some(current) => current == data,
+                    None => false,
+                };
+                if unchanged {
+                    continue;
+                }
+
+                if !options.dry_run {
+                    let written = if block.is_trailer() {
+                        let keys = SectorKeys { key_a: [data[0], data[1], data[2], data[3], data[4], data[5]], key_b: [data[10], data[11], data[12], data[13], data[14], data[15]] };
+                        self.write_sector_trailer(sector, &keys, [data[6], data[7], data[8], data[9]])?
+                    } else if block.is_manufacturer() {
+                        // Written as on a gen2 card; a genuine card refuses it.
+                        classic::validate_manufacturer_block(&data, card.uid.len())?;
+                        classic_write(self, &classic_write_params(block, &data))?
+                    } else {
+                        self.mifare_classic_write_block(block, &data)?
+                    };
+                    if !written {
+                        // Refused by the access conditions.
+                        reauthenticate(self, card, block, &key)?;
+                        report.skipped.push(DumpAddress::Block(block));
+                        continue;
+                    }
+                }
+                report.changes.push(RestoreChange {
+                    address: DumpAddress::Block(block),
+                    current: current.map(|current| current.to_vec()),
+                    data: data.to_vec(),
+                });
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Write a block of data to the card.  Block should be the block
+    /// to write and data should be a byte array of length 16 with the data to
+    /// write.  If the data is successfully written then True is returned,
+    /// otherwise False is returned.  Sector trailers are refused with
+    /// [`Error::SectorTrailer`], use [`PN532::write_sector_trailer`] for them,
+    /// and block 0 with [`Error::ManufacturerBlock`].
+    fn mifare_classic_write_block(&mut self, block: Block, data: &[u8]) -> Result<bool> {
+        self.ensure_writable()?;
+        if block.is_trailer() {
+            return Err(Error::SectorTrailer(block));
+        }
+        if block.is_manufacturer() {
+            return Err(Error::ManufacturerBlock);
+        }
+        if data.len() != 16 {
+            return Err(Error::InvalidArgument(format!("MIFARE Classic blocks are 16 bytes, got {}", data.len())));
+        }
+
+        classic_write(self, &classic_write_params(block, data))
+    }
+
+    /// Write the trailer of `sector`: its keys and access conditions.
+    /// `access_bits` are bytes 6-9 of the trailer, the three access bytes
+    /// followed by the general purpose byte.  The access bytes are validated
+    /// first, since writing an invalid encoding blocks the sector for good.
+    /// The sector must have been authenticated.
+    fn write_sector_trailer(&mut self, sector: Sector, keys: &SectorKeys, access_bits: [u8; 4]) -> Result<bool> {
+        self.ensure_writable()?;
+        let trailer = classic::sector_trailer(keys, access_bits)?;
+
+        classic_write(self, &classic_write_params(sector.trailer(), &trailer))
+    }
+
+    /// Write a 4 byte page of an NTAG2xx / MIFARE Ultralight tag.
+    fn ntag2xx_write_block(&mut self, page: Page, data: &[u8]) -> Result<bool> {
+        self.ensure_writable()?;
+        if data.len() != 4 {
+            return Err(Error::InvalidArgument(format!("NTAG2xx pages are 4 bytes, got {}", data.len())));
+        }
+
+        let mut params = vec![MIFARE_ULTRALIGHT_CMD_WRITE, page.0];
+        params.extend_from_slice(data);
+
+        let target = self.auth_cache().target();
+        Ok(self.in_data_exchange(target, &params)?.is_some())
+    }
+    
+    /// Read a 4 byte page of an NTAG2xx / MIFARE Ultralight tag.
+    fn ntag2xx_read_block(&mut self, page: Page) -> Result<Vec<u8>>{
+        let mut buf = [0; 4];
+        if self.ntag2xx_read_block_into(page, &mut buf)? {
+            Ok(buf.to_vec())
+        } else {
+            Err(Error::Timeout)
+        }
+    }
+
+    /// Read a 4 byte page into `buf` without allocating.  Returns `false` if
+    /// the tag did not answer.
+    fn ntag2xx_read_block_into(&mut self, page: Page, buf: &mut [u8; 4]) -> Result<bool> {
+        // The READ command returns 16 bytes starting at the page.
+        let mut pages = [0; 16];
+        match data_exchange_into(self, &[MIFARE_CMD_READ, page.0], &mut pages)? {
+            Some(len) if len >= 4 => {
+                buf.copy_from_slice(&pages[..4]);
+                Ok(true)
+            }
+            Some(_) => Err(Error::UnexpectedResponse("NTAG2xx READ response is too short!")),
+            None => Ok(false),
+        }
+    }
+
+    /// Identify a type 2 tag with GET_VERSION: [`TagVersion::variant`]
+    /// tells NTAG213/215/216 and the MIFARE Ultralight EV1 sizes apart and
+    /// gives their memory layout.  Older tags do not answer it.
+    fn ntag2xx_get_version(&mut self) -> Result<TagVersion> {
+        TagVersion::from_bytes(&self.communicate_thru(&[ntag::CMD_GET_VERSION])?)
+    }
+
+    /// Authenticate to an NTAG21x or Ultralight EV1 tag with its 32 bit
+    /// `password` with PWD_AUTH, unlocking the pages from AUTH0 on until
+    /// the tag is deselected.  Returns the PACK the tag answers with; when
+    /// `pack` is given it must match, proving the tag knows the password.
+    /// A refused password fails with [`Error::CardAuthentication`].
+    fn ntag2xx_password_auth(&mut self, password: &[u8; 4], pack: Option<&[u8; 2]>) -> Result<[u8; 2]> {
+        let mut command = vec![ntag::CMD_PWD_AUTH];
+        command.extend_from_slice(password);
+        // A tag that refuses the password NAKs it.
+        let answer = self.communicate_thru(&command).map_err(|e| match e.chip_status() {
+            Some(_) => Error::CardAuthentication,
+            None => e,
+        })?;
+        let [p0, p1, ..] = answer[..] else {
+            return Err(Error::CardAuthentication);
+        };
+        if pack.is_some_and(|pack| *pack != [p0, p1]) {
+            return Err(Error::CardAuthentication);
+        }
+
+        Ok([p0, p1])
+    }
+
+    /// Store `password` and the `pack` answered to it in a tag of
+    /// `variant`.  Neither can be read back.  Once AUTH0 protects the
+    /// configuration pages, authenticate with the old password first.
+    fn ntag2xx_set_password(&mut self, variant: NtagVariant, password: &[u8; 4], pack: &[u8; 2]) -> Result<()> {
+        self.ensure_writable()?;
+        let pwd_page = ntag::layout_page(variant, NtagVariant::pwd_page)?;
+        let pack_page = ntag::layout_page(variant, NtagVariant::pack_page)?;
+        let [k0, k1] = *pack;
+        for (page, data) in [(pwd_page, *password), (pack_page, [k0, k1, 0x00, 0x00])] {
+            if !self.ntag2xx_write_block(page, &data)? {
+                return Err(Error::Timeout);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read the configuration pages of a tag of `variant`: mirror, AUTH0
+    /// and ACCESS.  Change it and write it back with
+    /// [`PN532::ntag2xx_write_config`].
+    fn ntag2xx_read_config(&mut self, variant: NtagVariant) -> Result<NtagConfig> {
+        let cfg0_page = ntag::layout_page(variant, NtagVariant::cfg0_page)?;
+        // READ returns 16 bytes, CFG0 and CFG1 come together.
+        let mut pages = [0; 16];
+        match data_exchange_into(self, &[MIFARE_CMD_READ, cfg0_page.0], &mut pages)? {
+            Some(len) if len >= 8 => {}
+            Some(_) => return Err(Error::UnexpectedResponse("NTAG2xx READ response is too short!")),
+            None => return Err(Error::Timeout),
+        }
+        let [c0, c1, c2, c3, a0, a1, a2, a3, ..] = pages;
+
+        Ok(NtagConfig::from_pages([c0, c1, c2, c3], [a0, a1, a2, a3]))
+    }
+
+    /// Write the configuration pages of a tag of `variant`, after checking
+    /// `config` fits its layout.  Once AUTH0 protects them, authenticate
+    /// with [`PN532::ntag2xx_password_auth`] first.
+    fn ntag2xx_write_config(&mut self, variant: NtagVariant, config: &NtagConfig) -> Result<()> {
+        self.ensure_writable()?;
+        let cfg0_page = ntag::layout_page(variant, NtagVariant::cfg0_page)?;
+        let cfg1_page = ntag::layout_page(variant, NtagVariant::cfg1_page)?;
+        let [cfg0, cfg1] = config.to_pages(variant)?;
+
+        // Write ACCESS first, so the pages are never open to reads in between
+        // when lowering AUTH0 with read protection.
+        for (page, data) in [(cfg1_page, cfg1), (cfg0_page, cfg0)] {
+            if !self.ntag2xx_write_block(page, &data)? {
+                return Err(Error::Timeout);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Password protect the pages of a tag of `variant` from `auth0` on
+    /// ([`ntag::AUTH0_DISABLED`] for none): writes need
+    /// [`PN532::ntag2xx_password_auth`], and reads too if `protect_reads`.
+    fn ntag2xx_set_auth0(&mut self, variant: NtagVariant, auth0: Page, protect_reads: bool) -> Result<()> {
+        let mut config = self.ntag2xx_read_config(variant)?;
+        config.auth0 = auth0;
+        config.access.read_protected = protect_reads;
+        self.ntag2xx_write_config(variant, &config)
+    }
+
+    /// Read the NFC counter of an NTAG213/215/216 tag, which the tag
+    /// increments on its own at the first read of each session once enabled
+    /// with [`PN532::ntag2xx_enable_counter`].
+    fn ntag2xx_read_counter(&mut self) -> Result<u32> {
+        read_counter(self, ntag::NFC_COUNTER)
+    }
+
+    /// Make a tag of `variant` read-only for good: mark the capability
+    /// container read-only, then set every dynamic and static lock bit.
+    ///
+    /// This cannot be undone, not even with the password.  The
+    /// configuration pages are left alone, see [`NtagAccess::config_locked`].
+    fn ntag2xx_make_read_only(&mut self, variant: NtagVariant) -> Result<()> {
+        self.ensure_writable()?;
+        let last_user_page = ntag::layout_page(variant, NtagVariant::last_user_page)?;
+        warn!("Permanently locking {} user pages up to {}", variant, last_user_page);
+
+        // CC bits can only be set, like the OTP page.
+        if !self.ntag2xx_write_block(ntag::CC_PAGE, &[0x00, 0x00, 0x00, ntag::CC_READ_ONLY])? {
+            return Err(Error::Timeout);
+        }
+        if let (Some(page), Some([b0, b1, b2])) = (variant.dynamic_lock_page(), variant.dynamic_lock_bytes()) {
+            if !self.ntag2xx_write_block(page, &[b0, b1, b2, 0x00])? {
+                return Err(Error::Timeout);
+            }
+        }
+        // Last, as it locks the CC page and the block-locking bits.
+        let lock_bytes = self.ultralight_set_static_lock_bits([0xFF, 0xFF])?;
+        if lock_bytes != [0xFF, 0xFF] {
+            return Err(Error::UnexpectedResponse("static lock bytes did not take!"));
+        }
+
+        Ok(())
+    }
+
+    /// Enable or disable the NFC counter of an NTAG213/215/216 tag of
+    /// `variant`, and whether reading it needs
+    /// [`PN532::ntag2xx_password_auth`].
+    fn ntag2xx_enable_counter(&mut self, variant: NtagVariant, enable: bool, password_protected: bool) -> Result<()> {
+        if !variant.is_ntag21x() {
+            return Err(Error::InvalidArgument(format!("{} has no NFC counter", variant)));
+        }
+        let mut config = self.ntag2xx_read_config(variant)?;
+        config.access.nfc_counter = enable;
+        config.access.nfc_counter_password = password_protected;
+        self.ntag2xx_write_config(variant, &config)
+    }
+
+    /// Read every page of an NTAG21x or Ultralight EV1 tag, for
+    /// [`PN532::restore_ntag`].  Pages past AUTH0 need
+    /// [`PN532::ntag2xx_password_auth`] first if reads are protected.
+    fn dump_ntag(&mut self) -> Result<NtagDump> {
+        let variant = self.ntag2xx_get_version()?.variant();
+        let pages = variant.pages()
+            .ok_or_else(|| Error::InvalidArgument(format!("the memory layout of {} is unknown", variant)))?;
+        let data = self.ntag2xx_fast_read(Page(0), Page((pages - 1) as u8))?;
+        let pages = data.chunks_exact(4).map(|page| [page[0], page[1], page[2], page[3]]).collect();
+
+        Ok(NtagDump { variant, pages })
+    }
+
+    /// Write `dump` back to a tag of the same variant.  Pages already
+    /// matching the dump are not written again, pages 0 to 3 and the lock and
+    /// configuration pages only if `options` allow it, and PWD and PACK
+    /// never.
+    fn restore_ntag(&mut self, dump: &NtagDump, options: &RestoreOptions) -> Result<RestoreReport> {
+        if !options.dry_run {
+            self.ensure_writable()?;
+        }
+        let variant = self.ntag2xx_get_version()?.variant();
+        if variant != dump.variant {
+            return Err(Error::InvalidArgument(format!("the dump is of a {}, the tag is a {}", dump.variant, variant)));
+        }
+        let last_user_page = ntag::layout_page(variant, NtagVariant::last_user_page)?;
+        let pwd_page = ntag::layout_page(variant, NtagVariant::pwd_page)?;
+
+        let target = self.auth_cache().target();
+        let mut report = RestoreReport { dry_run: options.dry_run, ..RestoreReport::default() };
+        let mut buf = [0; 4];
+        for (page, data) in (0..=u8::MAX).map(Page).zip(&dump.pages) {
+            let allowed = if page < ntag::FIRST_USER_PAGE {
+                options.manufacturer
+            } else if page > last_user_page {
+                options.trailers && page < pwd_page
+            } else {
+                true
+            };
+            if !allowed {
+                report.skipped.push(DumpAddress::Page(page));
+                continue;
+            }
+
+            let current = match self.ntag2xx_read_block_into(page, &mut buf) {
+                Ok(read) => read.then_some(buf),
+                // Protected by the password: the tag NAKs and halts.
+                Err(e) if refused_by_card(&e) => {
+                    wake_target(self, target)?;
+                    None
+                }
+                Err(e) => return Err(e),
+            };
+            if current == Some(*data) {
+                continue;
+            }
+            if !options.dry_run {
+                let written = match self.ntag2xx_write_block(page, data) {
+                    Ok(written) => written,
+                    Err(e) if refused_by_card(&e) => false,
+                    Err(e) => return Err(e),
+                };
+                if !written {
+                    // Locked or read-only: the tag NAKs and halts.
+                    wake_target(self, target)?;
+                    report.skipped.push(DumpAddress::Page(page));
+                    continue;
+                }
+            }
+            report.changes.push(RestoreChange {
+                address: DumpAddress::Page(page),
+                current: current.map(|current| current.to_vec()),
+                data: data.to_vec(),
+            });
+        }
+
+        Ok(report)
+    }
+
+    /// Read the 32 byte NXP originality signature of an NTAG21x or
+    /// Ultralight EV1 tag: the ECC signature of its UID.
+    fn ntag2xx_read_signature(&mut self) -> Result<[u8; 32]> {
+        let answer = self.communicate_thru(&[ntag::CMD_READ_SIG, 0x00])?;
+        let signature = answer.get(..32).ok_or(Error::UnexpectedResponse("READ_SIG response is too short!"))?;
+        let mut buf = [0; 32];
+        buf.copy_from_slice(signature);
+
+        Ok(buf)
+    }
+
+    /// Read the originality signature of the tag `uid` and check it against
+    /// NXP's `public_key`, e.g. [`signature::NTAG21X_PUBLIC_KEY`].  Returns
+    /// false for a counterfeit tag.
+    #[cfg(feature = "signature")]
+    fn ntag2xx_verify_signature(&mut self, uid: &Uid, public_key: &[u8; 33]) -> Result<bool> {
+        let sig = self.ntag2xx_read_signature()?;
+        Ok(signature::verify(uid, &sig, public_key))
+    }
+
+    /// Read the pages `start` to `end` (inclusive) of an NTAG21x tag with
+    /// FAST_READ, a handful of exchanges instead of one READ per page.
+    /// Returns 4 bytes per page.
+    fn ntag2xx_fast_read(&mut self, start: Page, end: Page) -> Result<Vec<u8>> {
+        if end < start {
+            return Err(Error::InvalidArgument(format!("{} is before {}", end, start)));
+        }
+
+        let mut data = Vec::with_capacity(4 * (end.0 as usize - start.0 as usize + 1));
+        let mut first = start.0;
+        loop {
+            let last = end.0.min(first.saturating_add(NTAG_FAST_READ_PAGES - 1));
+            let pages = self.communicate_thru(&[NTAG_CMD_FAST_READ, first, last])?;
+            let expected = 4 * (last - first + 1) as usize;
+            if pages.len() < expected {
+                return Err(Error::UnexpectedResponse("NTAG2xx FAST_READ response is too short!"));
+            }
+            data.extend_from_slice(&pages[..expected]);
+
+            if last == end.0 {
+                return Ok(data);
+            }
+            first = last + 1;
+        }
+    }
+
+    /// Read one-way counter `counter` (0 to 2) of an Ultralight EV1 tag.
+    fn ultralight_read_counter(&mut self, counter: u8) -> Result<u32> {
+        ultralight::check_counter(counter)?;
+        read_counter(self, counter)
+    }
+
+    /// Increment one-way counter `counter` (0 to 2) of an Ultralight EV1
+    /// tag by `by`.  The tag refuses to go past
+    /// [`ultralight::COUNTER_MAX`]; counters never go down.
+    fn ultralight_increment_counter(&mut self, counter: u8, by: u32) -> Result<()> {
+        self.ensure_writable()?;
+        ultralight::check_counter(counter)?;
+        if by > ultralight::COUNTER_MAX {
+            return Err(Error::InvalidArgument(format!("counters are 24 bits, cannot add {}", by)));
+        }
+
+        let [b0, b1, b2, _] = by.to_le_bytes();
+        let answer = self.communicate_thru(&[ultralight::CMD_INCR_CNT, counter, b0, b1, b2, 0x00])?;
+        ultralight::check_ack(&answer)
+    }
+
+    /// Send VCSL (Virtual Card Select Last) with the installation
+    /// identifier `iid` and the PCD capabilities, returning the VCTID of an
+    /// Ultralight EV1 tag.
+    fn ultralight_vcsl(&mut self, iid: &[u8; 16], pcd_caps: &[u8; 4]) -> Result<u8> {
+        let mut command = vec![ultralight::CMD_VCSL];
+        command.extend_from_slice(iid);
+        command.extend_from_slice(pcd_caps);
+        match self.communicate_thru(&command)?[..] {
+            [vctid, ..] => Ok(vctid),
+            [] => Err(Error::UnexpectedResponse("VCSL response is empty!")),
+        }
+    }
+
+    /// The static lock bytes of an Ultralight / NTAG2xx tag, bytes 2 and 3
+    /// of [`ultralight::LOCK_PAGE`].
+    fn ultralight_static_lock_bytes(&mut self) -> Result<[u8; 2]> {
+        let page = self.ntag2xx_read_block(ultralight::LOCK_PAGE)?;
+        Ok([page[2], page[3]])
+    }
+
+    /// Set bits of the static lock bytes, permanently making pages read-only.
+    /// The tag ORs them into the current ones, and the bytes 0 and 1 of the
+    /// lock page written along are ignored.  Returns the lock bytes now in
+    /// effect.
+    fn ultralight_set_static_lock_bits(&mut self, bits: [u8; 2]) -> Result<[u8; 2]> {
+        self.ensure_writable()?;
+        let [l0, l1] = bits;
+        if !self.ntag2xx_write_block(ultralight::LOCK_PAGE, &[0x00, 0x00, l0, l1])? {
+            return Err(Error::Timeout);
+        }
+        self.ultralight_static_lock_bytes()
+    }
+
+    /// Set bits of the one-time programmable page.  Bits can only be set,
+    /// so this fails without writing if the page is locked or `bits` would
+    /// not change it.  Returns the OTP page now in effect.
+    fn ultralight_write_otp(&mut self, bits: [u8; 4]) -> Result<[u8; 4]> {
+        self.ensure_writable()?;
+        if self.ultralight_static_lock_bytes()?[0] & ultralight::LOCK_OTP != 0 {
+            return Err(Error::InvalidArgument("the OTP page is locked".to_owned()));
+        }
+        let current = self.ntag2xx_read_block(ultralight::OTP_PAGE)?;
+        if current.iter().zip(bits).all(|(&old, new)| old | new == old) {
+            return Err(Error::InvalidArgument(format!("the OTP bits {:02x?} are already set", bits)));
+        }
+        if !self.ntag2xx_write_block(ultralight::OTP_PAGE, &bits)? {
+            return Err(Error::Timeout);
+        }
+
+        let page = self.ntag2xx_read_block(ultralight::OTP_PAGE)?;
+        Ok([page[0], page[1], page[2], page[3]])
+    }
+
+    /// Authenticate to a MIFARE Ultralight C tag with its 16 byte 3DES
+    /// `key`, unlocking the pages from AUTH0 on until the tag is deselected.
+    /// A wrong key fails with [`Error::CardAuthentication`].
+    #[cfg(feature = "des")]
+    fn ultralight_c_authenticate(&mut self, key: &[u8; 16]) -> Result<()> {
+        let mut handshake = ultralight_c::Handshake::new(key)?;
+        let challenge = self.communicate_thru(&[ultralight_c::CMD_AUTHENTICATE, 0x00])?;
+        let answer = handshake.answer(&challenge)?;
+        // A tag that does not accept the answer NAKs it.
+        let response = self.communicate_thru(&answer).map_err(|e| match e.chip_status() {
+            Some(_) => Error::CardAuthentication,
+            None => e,
+        })?;
+        handshake.verify(&response)
+    }
+
+    /// Store a new 3DES `key` in a MIFARE Ultralight C tag.  Once AUTH0
+    /// protects the key pages, authenticate with the old key first.  The
+    /// key cannot be read back, so keep it safe before changing it.
+    #[cfg(feature = "des")]
+    fn ultralight_c_change_key(&mut self, key: &[u8; 16]) -> Result<()> {
+        self.ensure_writable()?;
+        for (page, data) in (ultralight_c::KEY_PAGE.0..).zip(ultralight_c::key_pages(key)) {
+            if !self.ntag2xx_write_block(Page(page), &data)? {
+                return Err(Error::Timeout);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send `data` as is to the selected target with InCommunicateThru and
+    /// return its answer, for commands without a structured API (NTAG
+    /// GET_VERSION, clone card backdoors, ...).  Framed as set with
+    /// [`PN532::set_raw_framing`], by default with the CRC added and
+    /// checked by the PN532.
+    fn communicate_thru(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+        self.execute(&InCommunicateThru { data: data.to_vec() }, None)?.ok_or(Error::Timeout)
+    }
+
+    /// Set the CRC, parity and last byte bits used by
+    /// [`PN532::communicate_thru`], changing only the relevant bits of the
+    /// CIU TxMode, RxMode, ManualRCV and BitFraming registers.
+    fn set_raw_framing(&mut self, framing: RawFraming) -> Result<()> {
+        if framing.tx_last_bits > 7 {
+            return Err(Error::InvalidArgument(format!("a byte has 8 bits, got {} last bits", framing.tx_last_bits)));
+        }
+        let addresses = [registers::CIU_TX_MODE, registers::CIU_RX_MODE, registers::CIU_MANUAL_RCV, registers::CIU_BIT_FRAMING];
+        let [tx_mode, rx_mode, manual_rcv, bit_framing] = self.read_registers(&addresses)?[..] else {
+            return Err(Error::UnexpectedResponse("ReadRegister response is too short!"));
+        };
+        let with = |value: u8, mask: u8, set: bool| if set { value | mask } else { value & !mask };
+        self.write_registers(&[
+            (registers::CIU_TX_MODE, with(tx_mode, 0x80, framing.tx_crc)),
+            (registers::CIU_RX_MODE, with(rx_mode, 0x80, framing.rx_crc)),
+            (registers::CIU_MANUAL_RCV, with(manual_rcv, 0x10, !framing.parity)),
+            (registers::CIU_BIT_FRAMING, (bit_framing & !0x07) | framing.tx_last_bits),
+        ])
+    }
+
+    /// Change the baud rate of a UART link: send SetSerialBaudRate, ACK its
+    /// response so the PN532 switches, switch the transport with
+    /// [`Transport::set_baud_rate`] and check the PN532 answers at the new
+    /// rate.  Fails without sending anything if the transport cannot switch,
+    /// which would leave the PN532 at a rate the host does not use.
+    fn set_serial_baudrate(&mut self, baud: SerialBaudRate) -> Result<()> {
+        if !self.supports_baud_rate() {
+            return Err(Error::InvalidArgument(format!("cannot switch this transport to {} baud", baud.bits_per_second())));
+        }
+        self.execute(&SetSerialBaudRate { baud }, None)?.ok_or(Error::Timeout)?;
+        // The ACK is mandatory for this command.
+        self.send_ack()?;
+        self.set_baud_rate(baud.bits_per_second())?;
+        let version = self.get_firmware_version()?;
+        info!("Switched to {} baud, {}", baud.bits_per_second(), version);
+
+        Ok(())
+    }
+
+    /// Set the internal flags of the PN532 with SetParameters, e.g. to use
+    /// NAD and DID as initiator or emulate an ISO/IEC 14443-4 card.
+    fn set_parameters(&mut self, flags: ParametersFlags) -> Result<()> {
+        self.execute(&SetParameters { flags }, None)?.ok_or(Error::Timeout)
+    }
+
+    /// Read the register at `address`, see [`registers`].
+    fn read_register(&mut self, address: u16) -> Result<u8> {
+        Ok(self.read_registers(&[address])?[0])
+    }
+
+    /// Read the registers at `addresses`, at most 131, in one command.
+    fn read_registers(&mut self, addresses: &[u16]) -> Result<Vec<u8>> {
+        let request = ReadRegister { addresses: addresses.to_vec() };
+        let mut values = self.execute(&request, None)?.ok_or(Error::Timeout)?;
+        values.truncate(addresses.len());
+
+        Ok(values)
+    }
+
+    /// Write `value` to the register at `address`, see [`registers`].
+    fn write_register(&mut self, address: u16, value: u8) -> Result<()> {
+        self.write_registers(&[(address, value)])
+    }
+
+    /// Write the registers of the address and value pairs `writes`, at most
+    /// 87, in order in one command.
+    fn write_registers(&mut self, writes: &[(u16, u8)]) -> Result<()> {
+        // The GPIO ports may change under the cache.
+        if writes.iter().any(|&(address, _)| address == registers::P3 || address == registers::P7) {
+            self.gpio_cache().clear();
+        }
+        self.execute(&WriteRegister { writes: writes.to_vec() }, None)?.ok_or(Error::Timeout)
+    }
+
+    /// Read the state of the PN532's GPIO pins.
+    fn read_gpio(&mut self) -> Result<GpioState> {
+        let [p3, p7, i] = self.execute(&ReadGpio, None)?.ok_or(Error::Timeout)?;
+        let state = GpioState { p3, p7, i };
+        info!("GPIO Status: {:?}", state);
+        self.gpio_cache().record_read(&state);
+
+        Ok(state)
+    }
+
+    /// Read the state of a single GPIO pin.
+    fn read_gpio_pin(&mut self, pin: PN532Gpio) -> Result<bool> {
+        Ok(self.read_gpio()?.pin(pin))
+    }
+
+    /// Write the state to the PN532's GPIO pins.
+    /// If p3 or p7 is not `None`, set the pins with p3 or p7, there is
+    /// no need to read pin states before write with the param p3 or p7
+    /// bits:
+    /// ```text
+    /// P3[0] = P30,   P7[0] = 0,
+    /// P3[1] = P31,   P7[1] = P71,
+    /// P3[2] = P32,   P7[2] = P72,
+    /// P3[3] = P33,   P7[3] = nu,
+    /// P3[4] = P34,   P7[4] = nu,
+    /// P3[5] = P35,   P7[5] = nu,
+    /// P3[6] = nu,    P7[6] = nu,
+    /// P3[7] = Val,   P7[7] = Val,
+    /// ```
+    /// For each port that is validated (bit Val = 1), all the bits are applied
+    /// simultaneously. It is not possible for example to modify the state of
+    /// the port P32 without applying a value to the ports P30, P31, P33, P34
+    /// and P35.
+    ///
+    /// If p3 and p7 are `None`, set one pin with the params 'pin' and 'state'.
+    /// The other pins of its port keep the state last read or written, see
+    /// [`GpioCache`]; it is read first if unknown or if
+    /// [`Options::gpio_force_refresh`] is set.
+    fn write_gpio(&mut self, pin: PN532Gpio, state: bool, p3: Option<u8>, p7: Option<u8>) -> Result<()> {
+        let mut params = [0x00; 2];
+        if let (Some(p3), Some(p7)) = (p3, p7) {
+            params[0] = if p3 == 0 { 0x00 } else { 0x80 | p3 };
+            params[1] = if p7 == 0 { 0x00 } else { 0x80 | p7 };
+            write_gpio_ports(self, WriteGpio { p3: params[0], p7: params[1] })
+        } else {
+            match pin {
+                PN532Gpio::I0 | PN532Gpio::I1 => Ok(()),
+                _ => {
+                    let port = gpio_port(self, pin.idx())?;
+                    params[pin.idx()] = if state {
+                        0x80 | port | (1 << pin.offset())
+                    } else {
+                        0x80 | port & !(1 << pin.offset())
+                    };
+
+                    write_gpio_ports(self, WriteGpio { p3: params[0], p7: params[1] })
+                }
+            }
+        }
+    }
+
+    /// Write the P3 port (bits 0-5 = P30-P35), leaving P7 untouched.
+    fn write_gpio_p3(&mut self, p3: u8) -> Result<()> {
+        write_gpio_ports(self, WriteGpio { p3: GPIO_VALIDATIONBIT | (p3 & 0x3F), p7: 0x00 })
+    }
+
+    /// Write the P7 port (bits 1-2 = P71-P72), leaving P3 untouched.
+    fn write_gpio_p7(&mut self, p7: u8) -> Result<()> {
+        write_gpio_ports(self, WriteGpio { p3: 0x00, p7: GPIO_VALIDATIONBIT | (p7 & 0x06) })
+    }
+
+    /// Write only the pins selected by the masks: for each port, the bits set in
+    /// the mask are taken from the value and the other pins keep their current
+    /// state. A port whose mask is 0 is not written at all, and the current
+    /// state is only needed when a port is partially masked, see
+    /// [`PN532::write_gpio`] for how it is known.
+    fn write_gpio_masked(&mut self, p3_mask: u8, p3: u8, p7_mask: u8, p7: u8) -> Result<()> {
+        let (p3_mask, p7_mask) = (p3_mask & 0x3F, p7_mask & 0x06);
+        let current = |pn532: &mut Self, idx: usize, mask: u8, full: u8| {
+            if mask != 0 && mask != full {
+                gpio_port(pn532, idx)
+            } else {
+                Ok(0)
+            }
+        };
+        let current_p3 = current(self, 0, p3_mask, 0x3F)? & 0x3F;
+        let current_p7 = current(self, 1, p7_mask, 0x06)? & 0x06;
+        let merge = |mask: u8, value: u8, current: u8| {
+            if mask == 0 {
+                0x00
+            } else {
+                GPIO_VALIDATIONBIT | (current & !mask) | (value & mask)
+            }
+        };
+        let request = WriteGpio {
+            p3: merge(p3_mask, p3, current_p3),
+            p7: merge(p7_mask, p7, current_p7),
+        };
+
+        write_gpio_ports(self, request)
+    }
+
+    /// Configure the PN532 as target described by `config` and wait up to
+    /// `timeout` seconds for an initiator to activate it.  Returns how it
+    /// was activated and the first command received, or `None` on timeout.
+    fn tg_init_as_target(&mut self, config: &TargetConfig, timeout: f64) -> Result<Option<ActivationResult>> {
+        config.validate()?;
+        let request = TgInitAsTarget { config: config.clone() };
+
+        self.execute(&request, Some(Duration::from_secs_f64(timeout)))
+    }
+
+    /// As target, wait up to `timeout` seconds for the initiator's next
+    /// data, a DEP request or a C-APDU.  Returns `None` on timeout.
+    fn tg_get_data(&mut self, timeout: f64) -> Result<Option<TargetEvent>> {
+        let mut data = Vec::new();
+        loop {
+            match self.execute(&TgGetData, Some(Duration::from_secs_f64(timeout))) {
+                Ok(None) => return Ok(None),
+                Ok(Some((part, more))) => {
+                    data.extend_from_slice(&part);
+                    if !more {
+                        return Ok(Some(TargetEvent::Data(data)));
+                    }
+                    debug!("TgGetData data continues, {} bytes so far", data.len());
+                }
+                Err(e) if e.chip_status() == Some(Status::Released) => return Ok(Some(TargetEvent::Released)),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// As target, send `data` (at most 262 bytes) to the initiator, the
+    /// answer to the data of [`PN532::tg_get_data`].  Returns false if the
+    /// initiator released the PN532 instead.
+    fn tg_set_data(&mut self, data: &[u8]) -> Result<bool> {
+        if data.len() > TgSetData::MAX_LEN {
+            return Err(Error::InvalidArgument(format!("TgSetData carries at most {} bytes, got {}", TgSetData::MAX_LEN, data.len())));
+        }
+        match self.execute(&TgSetData { data: data.to_vec() }, None) {
+            Ok(answered) => answered.ok_or(Error::Timeout).map(|()| true),
+            Err(e) if e.chip_status() == Some(Status::Released) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// As target, wait up to `timeout` seconds for the initiator's next raw
+    /// frame, for protocols the PN532 does not handle.  Returns `None` on
+    /// timeout.
+    fn tg_get_initiator_command(&mut self, timeout: f64) -> Result<Option<TargetEvent>> {
+        match self.execute(&TgGetInitiatorCommand, Some(Duration::from_secs_f64(timeout))) {
+            Ok(command) => Ok(command.map(TargetEvent::Data)),
+            Err(e) if e.chip_status() == Some(Status::Released) => Ok(Some(TargetEvent::Released)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get the state of the PN532 as target and the bit rates in use, e.g.
+    /// to notice the initiator left and configure the target again with
+    /// [`PN532::tg_init_as_target`].
+    fn tg_get_target_status(&mut self) -> Result<TargetStatus> {
+        self.execute(&TgGetTargetStatus, None)?.ok_or(Error::Timeout)
+    }
+
+    /// As target, send the raw answer `data` (at most 262 bytes) to the
+    /// frame of [`PN532::tg_get_initiator_command`].  Returns false if the
+    /// initiator released the PN532 instead.
+    fn tg_response_to_initiator(&mut self, data: &[u8]) -> Result<bool> {
+        if data.len() > TgResponseToInitiator::MAX_LEN {
+            return Err(Error::InvalidArgument(format!("TgResponseToInitiator carries at most {} bytes, got {}", TgResponseToInitiator::MAX_LEN, data.len())));
+        }
+        match self.execute(&TgResponseToInitiator { data: data.to_vec() }, None) {
+            Ok(answered) => answered.ok_or(Error::Timeout).map(|()| true),
+            Err(e) if e.chip_status() == Some(Status::Released) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Read the 24 bit counter at `address` with READ_CNT.
+fn read_counter<P: PN532 + ?Sized>(pn532: &mut P, address: u8) -> Result<u32> {
+    match pn532.communicate_thru(&[ntag::CMD_READ_CNT, address])?[..] {
+        [b0, b1, b2, ..] => Ok(u32::from_le_bytes([b0, b1, b2, 0])),
+        _ => Err(Error::UnexpectedResponse("READ_CNT response is too short!")),
+    }
+}
+
+/// Current state of GPIO port `idx` (0 for P3, 1 for P7), from the cache
+/// unless it is empty or refreshing is forced.
+fn gpio_port<P: PN532 + ?Sized>(pn532: &mut P, idx: usize) -> Result<u8> {
+    if !pn532.options().gpio_force_refresh {
+        if let Some(port) = pn532.gpio_cache().port(idx) {
+            return Ok(port);
+        }
+    }
+
+    Ok(pn532.read_gpio()?.port(idx))
+}
+
+/// Send `request` and remember the written ports.
+fn write_gpio_ports<P: PN532 + ?Sized>(pn532: &mut P, request: WriteGpio) -> Result<()> {
+    pn532.execute(&request, None)?;
+    pn532.gpio_cache().record_write(&request);
+
+    Ok(())
+}
+
+/// The target found by a one-target InListPassiveTarget, if any.
+fn single_target(targets: &[Target]) -> Result<Option<PassiveTarget>> {
+    // Check only 1 card is present.
+    match targets {
+        [] => Ok(None),
+        [target] => PassiveTarget::try_from(target).map(Some),
+        _ => Err(Error::UnexpectedResponse("More than one card detected!")),
+    }
+}
+
+/// The target found by a one-target InListPassiveTarget, if any, noted as
+/// the selected one in the authentication cache.
+pub(crate) fn select_single_target<P: PN532 + ?Sized>(pn532: &mut P, targets: &[Target]) -> Result<Option<PassiveTarget>> {
+    let target = single_target(targets)?;
+    if let Some(target) = &target {
+        pn532.auth_cache().select(target.target_number);
+    }
+    Ok(target)
+}
+
+/// Wake the PN532 up, check that it answers and apply `sam` and the
+/// passive activation retries.
+fn bring_up<P: PN532 + ?Sized>(pn532: &mut P, sam: Option<SamConfiguration>) -> Result<FirmwareVersion> {
+    pn532.wake_up()?;
+    let version = pn532.get_firmware_version()?;
+    if let Some(sam) = sam {
+        pn532.execute(&sam, None)?.ok_or(Error::Timeout)?;
+    }
+    if let Some(retries) = pn532.options().passive_activation_retries {
+        // Default ATR and PSL retries.
+        pn532.set_max_retries(0xFF, 0x01, retries)?;
+    }
+
+    Ok(version)
+}
+
+/// Halt the card and send the gen1a backdoor wake-up, the 7 bit 0x40,
+/// then 0x43 if `both`.  Leaves the framing changed.
+fn magic_gen1a_unlock<P: PN532 + ?Sized>(pn532: &mut P, both: bool) -> Result<()> {
+    // The card does not answer HLTA.
+    match pn532.communicate_thru(&classic::CMD_HALT) {
+        Err(e) if !matches!(e.root(), Error::Status(_) | Error::Timeout) => return Err(e),
+        _ => {}
+    }
+
+    let raw = RawFraming { tx_crc: false, rx_crc: false, parity: true, tx_last_bits: 7 };
+    pn532.set_raw_framing(raw)?;
+    classic::check_ack(&pn532.communicate_thru(&[classic::CMD_GEN1A_UNLOCK1])?)?;
+    if both {
+        pn532.set_raw_framing(RawFraming { tx_last_bits: 0, ..raw })?;
+        classic::check_ack(&pn532.communicate_thru(&[classic::CMD_GEN1A_UNLOCK2])?)?;
+    }
+
+    Ok(())
+}
+
+/// The gen1a backdoor sequence of [`PN532::magic_gen1a_write_block0`],
+/// leaving the framing changed.
+fn magic_gen1a_write<P: PN532 + ?Sized>(pn532: &mut P, data: &[u8; 16]) -> Result<()> {
+    magic_gen1a_unlock(pn532, true)?;
+
+    // The ACKs come without CRC, the commands need one.
+    pn532.set_raw_framing(RawFraming { tx_crc: true, rx_crc: false, parity: true, tx_last_bits: 0 })?;
+    classic::check_ack(&pn532.communicate_thru(&[MIFARE_CMD_WRITE, 0x00])?)?;
+    classic::check_ack(&pn532.communicate_thru(data)?)?;
+
+    Ok(())
+}
+
+/// Wake target `tg` after the card halted, on a failed authentication or
+/// a refused command.  InSelect alone does nothing for a target the PN532
+/// still deems selected.
+fn wake_target<P: PN532 + ?Sized>(pn532: &mut P, tg: u8) -> Result<()> {
+    pn532.deselect_target(tg)?;
+    pn532.select_target(tg)
+}
+
+/// Whether a MIFARE Classic command failed because the card refused it: a
+/// wrong key, or access conditions forbidding the block.  The card NAKs or
+/// stays silent, and the PN532 reports an authentication error or a
+/// timeout.
+fn refused_by_card(e: &Error) -> bool {
+    matches!(e.chip_status(), Some(Status::MifareAuth | Status::Timeout | Status::MifareFraming))
+}
+
+/// Authenticate `sector` with the first of the keys `keys` holds for it
+/// that works, returning it.
+fn authenticate_sector<P: PN532 + ?Sized>(pn532: &mut P, uid: &Uid, sector: Sector, keys: &KeyMap) -> Result<SectorKey> {
+    let candidates = keys.keys(sector);
+    if candidates.is_empty() {
+        return Err(Error::InvalidArgument(format!("no key for {}", sector)));
+    }
+    for (i, key) in candidates.iter().enumerate() {
+        if i > 0 {
+            // The card halts on a failed authentication.
+            let target = pn532.auth_cache().target();
+            wake_target(pn532, target)?;
+        }
+        if pn532.mifare_classic_authenticate_block(uid, sector.first_block(), key.key_type.auth_command(), &key.key)? {
+            return Ok(*key);
+        }
+    }
+
+    Err(Error::Authentication(sector))
+}
+
+/// Wake `card` after it refused a command on `block`, and authenticate the
+/// sector again with `key`, which opened it before.
+fn reauthenticate<P: PN532 + ?Sized>(pn532: &mut P, card: &PassiveTarget, block: Block, key: &SectorKey) -> Result<()> {
+    wake_target(pn532, card.target_number)?;
+    if !pn532.mifare_classic_authenticate_block(&card.uid, block, key.key_type.auth_command(), &key.key)? {
+        return Err(Error::Authentication(block.sector()));
+    }
+    Ok(())
+}
+
+/// Send a MIFARE Classic WRITE, forgetting the authentication the card drops
+/// if it fails.  Returns `false` if the card did not answer or refused it.
+fn classic_write<P: PN532 + ?Sized>(pn532: &mut P, data: &[u8]) -> Result<bool> {
+    let target = pn532.auth_cache().target();
+    let result = pn532.in_data_exchange(target, data);
+    if !matches!(result, Ok(Some(_))) {
+        pn532.auth_cache().clear();
+    }
+
+    match result {
+        Ok(answer) => Ok(answer.is_some()),
+        Err(e) if refused_by_card(&e) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// InDataExchange data of a MIFARE Classic WRITE.
+fn classic_write_params(block: Block, data: &[u8]) -> Vec<u8> {
+    let mut params = vec![MIFARE_CMD_WRITE, block.0];
+    params.extend_from_slice(data);
+    params
+}
+
+/// InDataExchange of `data` with the selected target without allocating: its
+/// answer is read into `out`. Returns its length, or `None` on timeout.
+fn data_exchange_into<P: PN532 + ?Sized>(pn532: &mut P, data: &[u8], out: &mut [u8]) -> Result<Option<usize>> {
+    let mut params = [0; MAX_FRAME_LEN];
+    let params = &mut params[..data.len() + 1];
+    params[0] = pn532.auth_cache().target();
+    params[1..].copy_from_slice(data);
+
+    let command = Command::InDataExchange;
+    let mut response = [0; MAX_FRAME_LEN];
+    let response = &mut response[..(out.len() + 1).min(command.max_response_len())];
+    let timeout = pn532.timeouts().response_for(command);
+    let len = match pn532.send_command_into(command.code(), params, response, timeout)? {
+        Some(len) => len,
+        None => return Ok(None),
+    };
+
+    match response[..len].split_first() {
+        Some((&status, _)) if status & STATUS_ERROR != 0 => {
+            let result = Err(Error::status(status & STATUS_ERROR));
+            count_chip_error(pn532, &result);
+            result.map_err(|e| e.in_command(command.code(), None, None))
+        }
+        Some((&status, _)) if status & STATUS_MI != 0 => Err(Error::UnexpectedResponse("InDataExchange answer is longer than expected!").in_command(command.code(), None, None)),
+        Some((_, answer)) => {
+            out[..answer.len()].copy_from_slice(answer);
+            Ok(Some(answer.len()))
+        }
+        None => Err(Error::UnexpectedResponse("InDataExchange response is empty!").in_command(command.code(), None, None)),
+    }
+}
+
+/// Body of [`PN532::send_command_into`], whose outcome is reported to the
+/// hooks.
+fn exchange_into<P: PN532 + ?Sized>(pn532: &mut P, command: u8, params: &[u8], response: &mut [u8], timeout: Duration) -> Result<Option<usize>> {
+    if !begin_exchange(pn532, command, params)? {
+        return Ok(None);
+    }
+    let ready = pn532.wait_ready(timeout);
+    if !end_wait(pn532, command, params, ready)? {
+        return Ok(None);
+    }
+    finish_exchange(pn532, command, params, response).map(Some)
+}
+
+/// The error of `command` with the context the options ask for.
+fn exchange_error<P: PN532 + ?Sized>(pn532: &P, command: u8, params: &[u8], e: Error) -> Error {
+    e.in_command(command, pn532.options().error_context.then_some(params), None)
+}
+
+/// First phase of an exchange: send `command` and check the PN532
+/// acknowledges it, counting a missing ACK against the watchdog.  Returns
+/// whether it was acknowledged.
+pub(crate) fn begin_exchange<P: PN532 + ?Sized>(pn532: &mut P, command: u8, params: &[u8]) -> Result<bool> {
+    match pn532.start_command(command, params) {
+        Ok(true) => Ok(true),
+        Ok(false) => match check_watchdog(pn532) {
+            Ok(false) => Ok(false),
+            Ok(true) => Err(exchange_error(pn532, command, params, Error::ChipReset)),
+            Err(e) => Err(exchange_error(pn532, command, params, e)),
+        },
+        Err(e) => Err(exchange_error(pn532, command, params, e)),
+    }
+}
+
+/// Second phase of an exchange, once waiting for the response is over:
+/// `ready` is whether it arrived.  A command whose response did not arrive
+/// in time or was cancelled is aborted, so the PN532 takes the next one.
+pub(crate) fn end_wait<P: PN532 + ?Sized>(pn532: &mut P, command: u8, params: &[u8], ready: Result<bool>) -> Result<bool> {
+    let error = match ready {
+        Ok(true) => return Ok(true),
+        Ok(false) => None,
+        Err(Error::Cancelled) => Some(Error::Cancelled),
+        Err(e) => return Err(exchange_error(pn532, command, params, e)),
+    };
+    if let Err(e) = pn532.send_ack() {
+        return Err(exchange_error(pn532, command, params, e));
+    }
+    match error {
+        Some(e) => Err(exchange_error(pn532, command, params, e)),
+        None => Ok(false),
+    }
+}
+
+/// Last phase of an exchange: read the response into `response`.  Returns
+/// its length.
+pub(crate) fn finish_exchange<P: PN532 + ?Sized>(pn532: &mut P, command: u8, params: &[u8], response: &mut [u8]) -> Result<usize> {
+    pn532.finish_command_into(command, response).map_err(|e| exchange_error(pn532, command, params, e))
+}
+
+/// Report the outcome of an exchange started at `start` to the hooks, and
+/// recover the reader if its transport failed and the options ask for it.
+pub(crate) fn report_exchange<P: PN532 + ?Sized>(pn532: &mut P, command: u8, result: result::Result<Option<usize>, &Error>, start: Option<Instant>) {
+    if let Some(start) = start {
+        let outcome = match result {
+            Ok(Some(len)) => CommandOutcome::Response(len),
+            Ok(None) => CommandOutcome::NoResponse,
+            Err(e) => CommandOutcome::Failed(e),
+        };
+        pn532.hooks().command_complete(command, outcome, start.elapsed());
+    }
+    if matches!(result, Err(e) if e.is_transport()) && pn532.options().recover_on_transport_error && !pn532.stats_mut().recovering {
+        warn!("Transport failed during command {:#04x}, recovering", command);
+        if pn532.recover().is_err() {
+            warn!("Recovery failed");
+        }
+    }
+}
+
+/// Parse the `response` to `request`, counting the status the PN532
+/// reported, if any, and adding the context the options ask for.
+pub(crate) fn parse_response<P: PN532 + ?Sized, R: Request>(pn532: &mut P, request: &R, params: &[u8], response: Vec<u8>) -> Result<R::Response> {
+    let raw = pn532.options().error_context.then(|| response.clone());
+    let result = request.parse(response);
+    count_chip_error(pn532, &result);
+    result.map_err(|e| match &raw {
+        Some(response) => e.in_command(R::COMMAND.code(), Some(params), Some(response)),
+        None => e.in_command(R::COMMAND.code(), None, None),
+    })
+}
+
+/// Count an ACK timeout against the watchdog, and recover the reader if it
+/// trips.  Returns whether it did.
+fn check_watchdog<P: PN532 + ?Sized>(pn532: &mut P) -> Result<bool> {
+    let Some(watchdog) = pn532.options().watchdog else {
+        return Ok(false);
+    };
+    let stats = pn532.stats_mut();
+    if stats.recovering || stats.consecutive_ack_timeouts < watchdog.max_ack_timeouts {
+        return Ok(false);
+    }
+
+    warn!("PN532 missed {} ACKs in a row, resetting it", stats.consecutive_ack_timeouts);
+    stats.counters_mut().watchdog_resets += 1;
+    pn532.recover()?;
+
+    Ok(true)
+}
+
+/// Reopen the transport, pulse the reset pin and bring the PN532 up again.
+fn reinitialize<P: PN532 + ?Sized>(pn532: &mut P) -> Result<()> {
+    pn532.reopen()?;
+    pn532.gpio_init()?;
+    if let Some(pin) = pn532.reset_pin() {
+        pn532.reset(pin)?;
+    }
+    let version = bring_up(pn532, pn532.options().sam)?;
+    info!("Recovered {}", version);
+
+    Ok(())
+}
+
+/// Count the status reported by the PN532 if `result` is one.
+fn count_chip_error<P: PN532 + ?Sized, T>(pn532: &mut P, result: &Result<T>) {
+    if let Some(status) = result.as_ref().err().and_then(Error::chip_status) {
+        *pn532.stats_mut().counters_mut().chip_errors.entry(status).or_default() += 1;
+    }
+}
+
+/// Extract the data of a response frame, checking its length and checksums.
+fn decode_frame(response: &[u8]) -> Result<&[u8]> {
+    match frame::decode(response)? {
+        Frame::Information(data) => Ok(data),
+        Frame::Ack | Frame::Nack => Err(FrameError::UnexpectedAck.into()),
+        Frame::Error => Err(Error::SyntaxError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock::{ClassicCard, MockPn532, NtagCard};
+
+    const UID: [u8; 4] = [0xDE, 0xAD, 0xBE, 0xEF];
+
+    fn classic_1k() -> PassiveTarget {
+        PassiveTarget { target_number: 1, atqa: [0x00, 0x04], sak: 0x08, uid: Uid::try_from(&UID[..]).unwrap(), ats: None }
+    }
+
+    #[test]
+    fn falls_back_to_key_b_after_a_wrong_key_a() {
+        let mut card = ClassicCard::new(UID);
+        card.keys[1] = ([0x11; 6], [0x22; 6]);
+        card.blocks[4] = [0x42; 16];
+        let mut pn532 = MockPn532::new(card);
+        let sector = Sector::new(1).unwrap();
+        let keys = KeyMap::new(SectorKey::a([0xFF; 6])).or_key(SectorKey::b([0x22; 6]));
+
+        let data = pn532.read_sector(&classic_1k().uid, sector, &keys).unwrap();
+        assert_eq!(data[..16], [0x42; 16]);
+        // The card halted on the wrong key and was woken before Key B.
+        let commands: Vec<u8> = pn532.commands.iter().map(|command| command[0]).collect();
+        assert_eq!(commands, [0x40, 0x44, 0x54, 0x40, 0x40, 0x40, 0x40]);
+    }
+
+    #[test]
+    fn authenticates_a_card_activated_again() {
+        let mut pn532 = MockPn532::new(ClassicCard::new(UID));
+        let auths = |pn532: &MockPn532| pn532.commands.iter().filter(|command| command[..3] == [0x40, 0x01, 0x60]).count();
+
+        let card = pn532.read_passive_target(BaudModulation::Iso14443aType106, 1.0).unwrap().unwrap();
+        assert_eq!(card, classic_1k());
+        assert!(pn532.auth_cache().is_active());
+        assert!(pn532.mifare_classic_authenticate_block(&card.uid, Block(4), MIFARE_CMD_AUTH_A, &[0xFF; 6]).unwrap());
+        assert!(pn532.mifare_classic_authenticate_block(&card.uid, Block(5), MIFARE_CMD_AUTH_A, &[0xFF; 6]).unwrap());
+        assert_eq!(auths(&pn532), 1);
+
+        // The same card again: its authentication is gone.
+        let card = pn532.read_passive_target(BaudModulation::Iso14443aType106, 1.0).unwrap().unwrap();
+        assert!(pn532.mifare_classic_authenticate_block(&card.uid, Block(4), MIFARE_CMD_AUTH_A, &[0xFF; 6]).unwrap());
+        assert_eq!(auths(&pn532), 2);
+        assert_eq!(pn532.mifare_classic_read_block(Block(4)).unwrap(), [0; 16]);
+    }
+
+    #[test]
+    fn dump_records_locked_sectors_and_blocks() {
+        let mut card = ClassicCard::new(UID);
+        card.keys[2] = ([0x11; 6], [0x22; 6]);
+        card.read_denied.insert(5);
+        let mut pn532 = MockPn532::new(card);
+
+        let dump = pn532.dump_classic(&classic_1k(), &KeyMap::new(SectorKey::a([0xFF; 6]))).unwrap();
+        assert_eq!(dump.sectors.len(), 16);
+        assert_eq!(dump.unreadable_sectors().collect::<Vec<_>>(), [Sector::new(2).unwrap()]);
+        assert_eq!(dump.sectors[1].blocks, [Some([0; 16]), None, Some([0; 16]), Some(pn532.card.blocks[7])]);
+        assert!(dump.sectors[3].is_complete());
+        assert_eq!(dump.sectors[0].blocks[0], Some(pn532.card.blocks[0]));
+    }
+
+    #[test]
+    fn restore_skips_locked_sectors_and_refused_writes() {
+        let mut source = ClassicCard::new(UID);
+        for block in [4, 5, 6, 8] {
+            source.blocks[block] = [block as u8; 16];
+        }
+        let keys = KeyMap::new(SectorKey::a([0xFF; 6]));
+        let dump = MockPn532::new(source).dump_classic(&classic_1k(), &keys).unwrap();
+
+        let mut card = ClassicCard::new(UID);
+        card.keys[2] = ([0x11; 6], [0x22; 6]);
+        card.write_denied.insert(5);
+        let mut pn532 = MockPn532::new(card);
+        let report = pn532.restore_classic(&classic_1k(), &dump, &keys, &RestoreOptions::default()).unwrap();
+
+        let changed: Vec<_> = report.changes.iter().map(|change| change.address).collect();
+        assert_eq!(changed, [DumpAddress::Block(Block(4)), DumpAddress::Block(Block(6))]);
+        for block in [5, 8, 9, 10, 11] {
+            assert!(report.skipped.contains(&DumpAddress::Block(Block(block))), "block {} not skipped", block);
+        }
+        assert_eq!(pn532.card.blocks[4], [4; 16]);
+        assert_eq!(pn532.card.blocks[5], [0; 16]);
+        assert_eq!(pn532.card.blocks[8], [0; 16]);
+    }
+
+    #[test]
+    fn restore_skips_block_0_of_genuine_cards() {
+        let keys = KeyMap::new(SectorKey::a([0xFF; 6]));
+        let mut dump = MockPn532::new(ClassicCard::new(UID)).dump_classic(&classic_1k(), &keys).unwrap();
+        let mut block0 = dump.sectors[0].blocks[0].unwrap();
+        block0[15] = 0x42;
+        dump.sectors[0].blocks[0] = Some(block0);
+        dump.sectors[0].blocks[1] = Some([0x01; 16]);
+
+        let mut pn532 = MockPn532::new(ClassicCard::new(UID));
+        let options = RestoreOptions { manufacturer: true, ..RestoreOptions::default() };
+        let report = pn532.restore_classic(&classic_1k(), &dump, &keys, &options).unwrap();
+
+        assert!(report.skipped.contains(&DumpAddress::Block(Block(0))));
+        let changed: Vec<_> = report.changes.iter().map(|change| change.address).collect();
+        assert_eq!(changed, [DumpAddress::Block(Block(1))]);
+        assert_eq!(pn532.card.blocks[0][15], 0x00);
+        assert_eq!(pn532.card.blocks[1], [0x01; 16]);
+    }
+
+    #[test]
+    fn ntag_restore_skips_refused_pages() {
+        let source = NtagCard::new([0x04, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66]);
+        let mut dump = NtagDump { variant: NtagVariant::Ntag213, pages: source.pages };
+        dump.pages[4] = [0x03, 0x00, 0xFE, 0x00];
+        dump.pages[5] = [0x42; 4];
+
+        let mut tag = NtagCard::new([0x04, 0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]);
+        tag.write_denied.insert(5);
+        let mut pn532 = MockPn532::with_ntag(tag);
+        let options = RestoreOptions { manufacturer: true, ..RestoreOptions::default() };
+        let report = pn532.restore_ntag(&dump, &options).unwrap();
+
+        // The UID pages and the locked page are refused, the rest written.
+        let changed: Vec<_> = report.changes.iter().map(|change| change.address).collect();
+        assert_eq!(changed, [DumpAddress::Page(Page(2)), DumpAddress::Page(Page(4))]);
+        for page in [0, 1, 5] {
+            assert!(report.skipped.contains(&DumpAddress::Page(Page(page))), "page {} not skipped", page);
+        }
+        let tag = pn532.ntag.as_ref().unwrap();
+        assert_eq!(tag.pages[4], [0x03, 0x00, 0xFE, 0x00]);
+        assert_eq!(tag.pages[5], [0; 4]);
+    }
+
+    #[test]
+    fn baud_rate_switch_checks_the_transport_first() {
+        let mut pn532 = MockPn532::new(ClassicCard::new(UID));
+        let result = pn532.set_serial_baudrate(SerialBaudRate::Baud921600);
+        assert!(matches!(result, Err(Error::InvalidArgument(_))));
+        assert!(pn532.commands.is_empty());
+    }
+
+    #[test]
+    fn detects_genuine_cards() {
+        let keys = KeyMap::new(SectorKey::a([0xFF; 6]));
+        let mut pn532 = MockPn532::new(ClassicCard::new(UID));
+        let magic = pn532.detect_magic(&classic_1k().uid, &keys).unwrap();
+        assert_eq!(magic, MagicCard { gen1a: false, gen2: Some(false) });
+
+        // Sector 0 does not open: the gen2 probe cannot run.
+        let mut card = ClassicCard::new(UID);
+        card.keys[0] = ([0x11; 6], [0x22; 6]);
+        let mut pn532 = MockPn532::new(card);
+        let magic = pn532.detect_magic(&classic_1k().uid, &keys).unwrap();
+        assert_eq!(magic, MagicCard { gen1a: false, gen2: None });
+        assert!(!magic.is_magic());
     }
 }
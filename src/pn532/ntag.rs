@@ -0,0 +1,336 @@
+//! NTAG21x and MIFARE Ultralight EV1 identification with GET_VERSION, and
+//! the memory layout of each variant.
+
+use std::fmt;
+
+use crate::pn532::{Error, Page, Result};
+
+pub(crate) const CMD_GET_VERSION: u8 = 0x60;
+pub(crate) const CMD_PWD_AUTH: u8 =    0x1B;
+pub(crate) const CMD_READ_CNT: u8 =    0x39;
+pub(crate) const CMD_READ_SIG: u8 =    0x3C;
+
+/// READ_CNT address of the NTAG21x NFC counter.
+pub(crate) const NFC_COUNTER: u8 = 0x02;
+
+/// Capability container page, OTP like the Ultralight one.
+pub const CC_PAGE: Page = Page(3);
+/// Write access byte of the capability container for a read-only NDEF tag.
+pub(crate) const CC_READ_ONLY: u8 = 0x0F;
+
+// Bits of the ACCESS byte, the first of the CFG1 page.
+const ACCESS_PROT: u8 =             0x80;
+const ACCESS_CFGLCK: u8 =           0x40;
+const ACCESS_NFC_CNT_EN: u8 =       0x10;
+const ACCESS_NFC_CNT_PWD_PROT: u8 = 0x08;
+const ACCESS_AUTHLIM: u8 =          0x07;
+
+// Bits of the MIRROR byte, the first of the CFG0 page.
+const MIRROR_CONF: u8 =    0xC0;
+const MIRROR_BYTE: u8 =    0x30;
+const STRG_MOD_EN: u8 =    0x04;
+/// AUTH0 value leaving every page unprotected.
+pub const AUTH0_DISABLED: Page = Page(0xFF);
+
+/// First page of user memory, after the UID, lock and capability pages.
+pub const FIRST_USER_PAGE: Page = Page(4);
+
+/// Answer to GET_VERSION, shared by NTAG21x and Ultralight EV1 tags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TagVersion {
+    /// 0x04 for NXP.
+    pub vendor: u8,
+    /// 0x03 for Ultralight, 0x04 for NTAG.
+    pub product_type: u8,
+    pub product_subtype: u8,
+    pub major: u8,
+    pub minor: u8,
+    /// Storage size code: the user memory is between 2^(n/2) and
+    /// 2^(n/2 + 1) bytes.
+    pub storage_size: u8,
+    pub protocol: u8,
+}
+
+impl TagVersion {
+    /// Parse the 8 byte answer to GET_VERSION.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let [0x00, vendor, product_type, product_subtype, major, minor, storage_size, protocol, ..] = bytes[..] else {
+            return Err(Error::UnexpectedResponse("GET_VERSION response is not a version!"));
+        };
+        Ok(Self { vendor, product_type, product_subtype, major, minor, storage_size, protocol })
+    }
+
+    pub fn variant(&self) -> NtagVariant {
+        match (self.vendor, self.product_type, self.major, self.storage_size) {
+            (0x04, 0x03, 0x01, 0x0B) => NtagVariant::UltralightEv1Mf0ul11,
+            (0x04, 0x03, 0x01, 0x0E) => NtagVariant::UltralightEv1Mf0ul21,
+            (0x04, 0x04, _, 0x0F) => NtagVariant::Ntag213,
+            (0x04, 0x04, _, 0x11) => NtagVariant::Ntag215,
+            (0x04, 0x04, _, 0x13) => NtagVariant::Ntag216,
+            _ => NtagVariant::Unknown,
+        }
+    }
+
+    /// Whether the tag is a MIFARE Ultralight EV1, with counters and VCSL.
+    pub fn is_ultralight_ev1(&self) -> bool {
+        matches!(self.variant(), NtagVariant::UltralightEv1Mf0ul11 | NtagVariant::UltralightEv1Mf0ul21)
+    }
+}
+
+impl fmt::Display for TagVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (type {:#04x}/{:#04x}, version {}.{}, storage {:#04x})",
+            self.variant(), self.product_type, self.product_subtype, self.major, self.minor, self.storage_size)
+    }
+}
+
+/// Type 2 tags told apart by GET_VERSION.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum NtagVariant {
+    Ntag213,
+    Ntag215,
+    Ntag216,
+    /// MIFARE Ultralight EV1 with 48 bytes of user memory.
+    UltralightEv1Mf0ul11,
+    /// MIFARE Ultralight EV1 with 128 bytes of user memory.
+    UltralightEv1Mf0ul21,
+    /// Any other answer to GET_VERSION; the layout is unknown.
+    Unknown,
+}
+
+impl NtagVariant {
+    /// Last user memory page, dynamic lock page and CFG0 page.
+    fn layout(self) -> Option<(u8, Option<u8>, u8)> {
+        match self {
+            NtagVariant::Ntag213 => Some((0x27, Some(0x28), 0x29)),
+            NtagVariant::Ntag215 => Some((0x81, Some(0x82), 0x83)),
+            NtagVariant::Ntag216 => Some((0xE1, Some(0xE2), 0xE3)),
+            NtagVariant::UltralightEv1Mf0ul11 => Some((0x0F, None, 0x10)),
+            NtagVariant::UltralightEv1Mf0ul21 => Some((0x23, Some(0x24), 0x25)),
+            NtagVariant::Unknown => None,
+        }
+    }
+
+    /// Whether the tag is an NTAG213, 215 or 216, with an NFC counter.
+    pub fn is_ntag21x(self) -> bool {
+        matches!(self, NtagVariant::Ntag213 | NtagVariant::Ntag215 | NtagVariant::Ntag216)
+    }
+
+    /// Last page of user memory, which starts at [`FIRST_USER_PAGE`].
+    pub fn last_user_page(self) -> Option<Page> {
+        self.layout().map(|(last, _, _)| Page(last))
+    }
+
+    /// Bytes of user memory.
+    pub fn user_memory_len(self) -> Option<usize> {
+        self.layout().map(|(last, _, _)| 4 * (last - FIRST_USER_PAGE.0 + 1) as usize)
+    }
+
+    /// Number of pages, the last one being [`NtagVariant::pack_page`].
+    pub fn pages(self) -> Option<u16> {
+        self.layout().map(|(_, _, cfg0)| u16::from(cfg0) + 4)
+    }
+
+    /// Page of the dynamic lock bytes; the MF0UL11 has none.
+    pub fn dynamic_lock_page(self) -> Option<Page> {
+        self.layout().and_then(|(_, lock, _)| lock.map(Page))
+    }
+
+    /// Dynamic lock bytes 0 to 2 setting every lock bit of the user pages
+    /// past the static lock bytes, 16 on, and every block-locking bit.  The
+    /// NTAG215/216 lock 16 pages per bit, the others 2.
+    pub fn dynamic_lock_bytes(self) -> Option<[u8; 3]> {
+        let (last, _, _) = self.layout()?;
+        self.dynamic_lock_page()?;
+        let pages_per_bit = if matches!(self, NtagVariant::Ntag215 | NtagVariant::Ntag216) { 16 } else { 2 };
+        let lock_bits = (u32::from(last) + 1 - 16 + pages_per_bit - 1) / pages_per_bit;
+        let block_lock_bits = (lock_bits + 3) / 4;
+        let [b0, b1, ..] = ((1u32 << lock_bits) - 1).to_le_bytes();
+        Some([b0, b1, ((1u32 << block_lock_bits) - 1) as u8])
+    }
+
+    /// First configuration page: mirror, AUTH0.
+    pub fn cfg0_page(self) -> Option<Page> {
+        self.layout().map(|(_, _, cfg0)| Page(cfg0))
+    }
+
+    /// Second configuration page: ACCESS (PROT, CFGLCK, AUTHLIM).
+    pub fn cfg1_page(self) -> Option<Page> {
+        self.layout().map(|(_, _, cfg0)| Page(cfg0 + 1))
+    }
+
+    /// Page of the 32 bit password, write only.
+    pub fn pwd_page(self) -> Option<Page> {
+        self.layout().map(|(_, _, cfg0)| Page(cfg0 + 2))
+    }
+
+    /// Page of the 16 bit password acknowledge, write only.
+    pub fn pack_page(self) -> Option<Page> {
+        self.layout().map(|(_, _, cfg0)| Page(cfg0 + 3))
+    }
+}
+
+impl fmt::Display for NtagVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NtagVariant::Ntag213 => "NTAG213",
+            NtagVariant::Ntag215 => "NTAG215",
+            NtagVariant::Ntag216 => "NTAG216",
+            NtagVariant::UltralightEv1Mf0ul11 => "MIFARE Ultralight EV1 MF0UL11",
+            NtagVariant::UltralightEv1Mf0ul21 => "MIFARE Ultralight EV1 MF0UL21",
+            NtagVariant::Unknown => "unknown type 2 tag",
+        })
+    }
+}
+
+/// What an NTAG21x mirrors into its user memory as ASCII hex, e.g. to make
+/// an NDEF URL unique per tag or per tap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MirrorMode {
+    #[default]
+    None,
+    /// The UID, 14 characters.
+    Uid,
+    /// The NFC counter, 6 characters.
+    Counter,
+    /// The UID, a 'x' and the NFC counter, 21 characters.
+    UidAndCounter,
+}
+
+impl MirrorMode {
+    /// Number of characters the mirror takes.
+    pub fn len(self) -> usize {
+        match self {
+            MirrorMode::None => 0,
+            MirrorMode::Uid => 14,
+            MirrorMode::Counter => 6,
+            MirrorMode::UidAndCounter => 21,
+        }
+    }
+
+    pub fn is_empty(self) -> bool {
+        self == MirrorMode::None
+    }
+}
+
+/// The ACCESS byte of the configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NtagAccess {
+    /// PROT: the password protects reads too, not only writes, from AUTH0
+    /// on.
+    pub read_protected: bool,
+    /// CFGLCK: permanently lock the configuration pages.  Cannot be undone.
+    pub config_locked: bool,
+    /// NFC_CNT_EN: enable the NFC counter (NTAG21x only).
+    pub nfc_counter: bool,
+    /// NFC_CNT_PWD_PROT: reading the NFC counter needs the password
+    /// (NTAG21x only).
+    pub nfc_counter_password: bool,
+    /// AUTHLIM: failed password attempts before the tag disables them for
+    /// good, 2^n for n from 1 to 7, or 0 for no limit.
+    pub auth_limit: u8,
+}
+
+/// The configuration pages CFG0 and CFG1 of an NTAG21x or Ultralight EV1
+/// tag, read with
+/// [`PN532::ntag2xx_read_config`](crate::pn532::PN532::ntag2xx_read_config).
+/// The RFU bits are kept as read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NtagConfig {
+    /// What is mirrored into user memory (NTAG21x only).
+    pub mirror: MirrorMode,
+    /// Page the mirror starts in.
+    pub mirror_page: Page,
+    /// Byte of `mirror_page` the mirror starts at, 0 to 3.
+    pub mirror_byte: u8,
+    /// STRG_MOD_EN: strong load modulation.
+    pub strong_modulation: bool,
+    /// First page protected by the password; [`AUTH0_DISABLED`] or any page
+    /// past the end for none.
+    pub auth0: Page,
+    pub access: NtagAccess,
+    raw: [[u8; 4]; 2],
+}
+
+impl NtagConfig {
+    pub(crate) fn from_pages(cfg0: [u8; 4], cfg1: [u8; 4]) -> Self {
+        let mirror = match (cfg0[0] & MIRROR_CONF) >> 6 {
+            0 => MirrorMode::None,
+            1 => MirrorMode::Uid,
+            2 => MirrorMode::Counter,
+            _ => MirrorMode::UidAndCounter,
+        };
+        let access = cfg1[0];
+        Self {
+            mirror,
+            mirror_page: Page(cfg0[2]),
+            mirror_byte: (cfg0[0] & MIRROR_BYTE) >> 4,
+            strong_modulation: cfg0[0] & STRG_MOD_EN != 0,
+            auth0: Page(cfg0[3]),
+            access: NtagAccess {
+                read_protected: access & ACCESS_PROT != 0,
+                config_locked: access & ACCESS_CFGLCK != 0,
+                nfc_counter: access & ACCESS_NFC_CNT_EN != 0,
+                nfc_counter_password: access & ACCESS_NFC_CNT_PWD_PROT != 0,
+                auth_limit: access & ACCESS_AUTHLIM,
+            },
+            raw: [cfg0, cfg1],
+        }
+    }
+
+    /// The CFG0 and CFG1 pages, checked against the layout of `variant`.
+    pub(crate) fn to_pages(self, variant: NtagVariant) -> Result<[[u8; 4]; 2]> {
+        if self.access.auth_limit > ACCESS_AUTHLIM {
+            return Err(Error::InvalidArgument(format!("AUTHLIM is 0 to 7, got {}", self.access.auth_limit)));
+        }
+        if self.mirror_byte > 3 {
+            return Err(Error::InvalidArgument(format!("the mirror starts at byte 0 to 3, got {}", self.mirror_byte)));
+        }
+        if !self.mirror.is_empty() {
+            if !variant.is_ntag21x() {
+                return Err(Error::InvalidArgument(format!("{} cannot mirror", variant)));
+            }
+            // The mirror must fit in user memory.
+            let last = layout_page(variant, NtagVariant::last_user_page)?;
+            let start = 4 * usize::from(self.mirror_page.0) + usize::from(self.mirror_byte);
+            if self.mirror_page < FIRST_USER_PAGE || start + self.mirror.len() > 4 * (usize::from(last.0) + 1) {
+                return Err(Error::InvalidArgument(format!("a {:?} mirror at {} byte {} does not fit in user memory", self.mirror, self.mirror_page, self.mirror_byte)));
+            }
+        }
+
+        let [mut cfg0, mut cfg1] = self.raw;
+        let conf = match self.mirror {
+            MirrorMode::None => 0,
+            MirrorMode::Uid => 1,
+            MirrorMode::Counter => 2,
+            MirrorMode::UidAndCounter => 3,
+        };
+        cfg0[0] = (cfg0[0] & !(MIRROR_CONF | MIRROR_BYTE | STRG_MOD_EN))
+            | conf << 6
+            | self.mirror_byte << 4
+            | if self.strong_modulation { STRG_MOD_EN } else { 0 };
+        cfg0[2] = self.mirror_page.0;
+        cfg0[3] = self.auth0.0;
+        let access = &self.access;
+        cfg1[0] = (cfg1[0] & !(ACCESS_PROT | ACCESS_CFGLCK | ACCESS_NFC_CNT_EN | ACCESS_NFC_CNT_PWD_PROT | ACCESS_AUTHLIM))
+            | [
+                (access.read_protected, ACCESS_PROT),
+                (access.config_locked, ACCESS_CFGLCK),
+                (access.nfc_counter, ACCESS_NFC_CNT_EN),
+                (access.nfc_counter_password, ACCESS_NFC_CNT_PWD_PROT),
+            ].iter().filter(|(set, _)| *set).fold(0, |bits, (_, bit)| bits | bit)
+            | access.auth_limit;
+
+        Ok([cfg0, cfg1])
+    }
+}
+
+/// `page` of `variant`, or an error if the layout of the variant is unknown.
+pub(crate) fn layout_page(variant: NtagVariant, page: fn(NtagVariant) -> Option<Page>) -> Result<Page> {
+    page(variant).ok_or_else(|| Error::InvalidArgument(format!("the memory layout of {} is unknown", variant)))
+}
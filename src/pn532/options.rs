@@ -0,0 +1,81 @@
+use crate::pn532::{Retries, SelfTest, Timeouts, Watchdog};
+use crate::pn532::command::{SamConfiguration, SamMode};
+
+/// Settings applied when a reader is initialized.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// SAMConfiguration sent once the PN532 answers, or `None` to leave it to
+    /// the application.
+    pub sam: Option<SamConfiguration>,
+    /// How many more times InListPassiveTarget tries to activate a card, set
+    /// with [`PN532::set_max_retries`](crate::pn532::PN532::set_max_retries)
+    /// during initialization.  `None` keeps the PN532's default of retrying
+    /// forever, so looking for a card blocks until one shows up or the
+    /// command times out; `Some(0x00)` makes it a single shot that returns
+    /// "no card" right away.
+    pub passive_activation_retries: Option<u8>,
+    /// Whether the driver waits for an interrupt on the IRQ pin, when one is
+    /// wired, instead of polling the status byte over SPI.
+    pub use_irq: bool,
+    /// How many more times the wake up, GetFirmwareVersion and
+    /// SAMConfiguration sequence is tried if the PN532 does not answer during
+    /// initialization.
+    pub init_retries: u8,
+    /// Health check run once the PN532 is initialized, or `None` to skip it.
+    pub self_test: Option<SelfTest>,
+    /// Whether to pulse the reset pin, when one is wired, before initializing.
+    pub reset_on_startup: bool,
+    /// Whether to pulse the reset pin, when one is wired, before retrying a
+    /// failed initialization.
+    pub reset_on_init_failure: bool,
+    pub timeouts: Timeouts,
+    /// How commands are retried after a transient failure.
+    pub retries: Retries,
+    /// Whether a command failing in the transport (see
+    /// [`Error::is_transport`](crate::pn532::Error::is_transport)) makes the
+    /// reader [recover](crate::pn532::PN532::recover) before returning the
+    /// error, so the next command finds it working again.
+    pub recover_on_transport_error: bool,
+    /// Reset the PN532 when it stops acknowledging commands, or `None` to
+    /// leave it to the application.
+    pub watchdog: Option<Watchdog>,
+    /// Refuse every operation that writes to a tag with
+    /// [`Error::WriteDisabled`](crate::pn532::Error::WriteDisabled).
+    pub read_only: bool,
+    /// Whether dropping the reader releases the targets and switches the RF
+    /// field off.
+    pub cleanup_on_drop: bool,
+    /// Whether dropping the reader also puts the PN532 into power down. Only
+    /// applies with `cleanup_on_drop`.
+    pub power_down_on_drop: bool,
+    /// Record the bytes sent and the raw response frame in
+    /// [`Error::Command`](crate::pn532::Error::Command), for field debugging.
+    pub error_context: bool,
+    /// Read the GPIO ports before every single pin write instead of trusting
+    /// the last known state, for pins that are also driven externally.
+    pub gpio_force_refresh: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            // Normal mode, 1 second virtual card timeout, drive the IRQ pin.
+            sam: Some(SamConfiguration { mode: SamMode::Normal, timeout: 0x14, use_irq: true }),
+            passive_activation_retries: None,
+            use_irq: true,
+            init_retries: 2,
+            self_test: None,
+            reset_on_startup: true,
+            reset_on_init_failure: true,
+            timeouts: Timeouts::default(),
+            retries: Retries::default(),
+            recover_on_transport_error: false,
+            watchdog: None,
+            read_only: false,
+            cleanup_on_drop: true,
+            power_down_on_drop: false,
+            error_context: false,
+            gpio_force_refresh: false,
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Addresses of the PN532 registers for
+//! [`PN532::read_register`](crate::pn532::PN532::read_register) and
+//! [`PN532::write_register`](crate::pn532::PN532::write_register).
+//!
+//! SFR registers are addressed with a high byte of 0xFF; the others are
+//! memory mapped in the XRAM.  See UM0701-02 §7.2.4 and the PN532 data sheet
+//! for their content.
+
+// SFR registers accessible to the host (UM0701-02 table 14).
+pub const PCON: u16 =          0xFF87;
+pub const RWL: u16 =           0xFF9A;
+pub const TWL: u16 =           0xFF9B;
+pub const FIFOFS: u16 =        0xFF9C;
+pub const FIFOFF: u16 =        0xFF9D;
+pub const SFF: u16 =           0xFF9E;
+pub const FIT: u16 =           0xFF9F;
+pub const FITEN: u16 =         0xFFA1;
+pub const FDATA: u16 =         0xFFA2;
+pub const FSIZE: u16 =         0xFFA3;
+pub const IE0: u16 =           0xFFA8;
+pub const SPI_CONTROL: u16 =   0xFFA9;
+pub const SPI_STATUS: u16 =    0xFFAA;
+pub const HSU_STA: u16 =       0xFFAB;
+pub const HSU_CTR: u16 =       0xFFAC;
+pub const HSU_PRE: u16 =       0xFFAD;
+pub const HSU_CNT: u16 =       0xFFAE;
+pub const P3: u16 =            0xFFB0;
+pub const IP0: u16 =           0xFFB8;
+pub const CIU_COMMAND: u16 =   0xFFD1;
+pub const IEN1: u16 =          0xFFE8;
+pub const P7CFGA: u16 =        0xFFF4;
+pub const P7CFGB: u16 =        0xFFF5;
+pub const P7: u16 =            0xFFF7;
+pub const IP1: u16 =           0xFFF8;
+pub const P3CFGA: u16 =        0xFFFC;
+pub const P3CFGB: u16 =        0xFFFD;
+
+// XRAM registers.
+pub const ANDET_CONTROL: u16 = 0x610C;
+
+// Contactless interface unit, mapped in the XRAM.
+pub const CIU_MODE: u16 =            0x6301;
+pub const CIU_TX_MODE: u16 =         0x6302;
+pub const CIU_RX_MODE: u16 =         0x6303;
+pub const CIU_TX_CONTROL: u16 =      0x6304;
+pub const CIU_TX_AUTO: u16 =         0x6305;
+pub const CIU_TX_SEL: u16 =          0x6306;
+pub const CIU_RX_SEL: u16 =          0x6307;
+pub const CIU_RX_THRESHOLD: u16 =    0x6308;
+pub const CIU_DEMOD: u16 =           0x6309;
+pub const CIU_FEL_NFC1: u16 =        0x630A;
+pub const CIU_FEL_NFC2: u16 =        0x630B;
+pub const CIU_MIF_NFC: u16 =         0x630C;
+pub const CIU_MANUAL_RCV: u16 =      0x630D;
+pub const CIU_TYPE_B: u16 =          0x630E;
+pub const CIU_CRC_RESULT_MSB: u16 =  0x6311;
+pub const CIU_CRC_RESULT_LSB: u16 =  0x6312;
+pub const CIU_GSN_OFF: u16 =         0x6313;
+pub const CIU_MOD_WIDTH: u16 =       0x6314;
+pub const CIU_TX_BIT_PHASE: u16 =    0x6315;
+pub const CIU_RF_CFG: u16 =          0x6316;
+pub const CIU_GSN_ON: u16 =          0x6317;
+pub const CIU_CW_GSP: u16 =          0x6318;
+pub const CIU_MOD_GSP: u16 =         0x6319;
+pub const CIU_T_MODE: u16 =          0x631A;
+pub const CIU_T_PRESCALER: u16 =     0x631B;
+pub const CIU_T_RELOAD_HI: u16 =     0x631C;
+pub const CIU_T_RELOAD_LO: u16 =     0x631D;
+pub const CIU_T_COUNTER_HI: u16 =    0x631E;
+pub const CIU_T_COUNTER_LO: u16 =    0x631F;
+pub const CIU_TEST_SEL1: u16 =       0x6321;
+pub const CIU_TEST_SEL2: u16 =       0x6322;
+pub const CIU_TEST_PIN_EN: u16 =     0x6323;
+pub const CIU_TEST_PIN_VALUE: u16 =  0x6324;
+pub const CIU_TEST_BUS: u16 =        0x6325;
+pub const CIU_AUTO_TEST: u16 =       0x6326;
+pub const CIU_VERSION: u16 =         0x6327;
+pub const CIU_ANALOG_TEST: u16 =     0x6328;
+pub const CIU_TEST_DAC1: u16 =       0x6329;
+pub const CIU_TEST_DAC2: u16 =       0x632A;
+pub const CIU_TEST_ADC: u16 =        0x632B;
+pub const CIU_RF_LEVEL_DET: u16 =    0x632F;
+pub const CIU_SIC_CLK_EN: u16 =      0x6330;
+pub const CIU_COMMAND_REG: u16 =     0x6331;
+pub const CIU_COMM_IEN: u16 =        0x6332;
+pub const CIU_DIV_IEN: u16 =         0x6333;
+pub const CIU_COMM_IRQ: u16 =        0x6334;
+pub const CIU_DIV_IRQ: u16 =         0x6335;
+pub const CIU_ERROR: u16 =           0x6336;
+pub const CIU_STATUS1: u16 =         0x6337;
+pub const CIU_STATUS2: u16 =         0x6338;
+pub const CIU_FIFO_DATA: u16 =       0x6339;
+pub const CIU_FIFO_LEVEL: u16 =      0x633A;
+pub const CIU_WATER_LEVEL: u16 =     0x633B;
+pub const CIU_CONTROL: u16 =         0x633C;
+pub const CIU_BIT_FRAMING: u16 =     0x633D;
+pub const CIU_COLL: u16 =            0x633E;
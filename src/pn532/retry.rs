@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::pn532::Command;
+
+/// How many times a command is tried when it fails in a way that may not
+/// happen again, such as a corrupted frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of tries, including the first one.
+    pub max_attempts: u32,
+    /// Pause before each retry.
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Try once.
+    pub const NONE: RetryPolicy = RetryPolicy { max_attempts: 1, backoff: Duration::ZERO };
+
+    pub fn new(max_attempts: u32, backoff: Duration) -> Self {
+        Self { max_attempts, backoff }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(3, Duration::from_millis(10))
+    }
+}
+
+/// The retry policy of each command.
+///
+/// By default every command is retried except the exchanges with a card:
+/// when only the response got corrupted the card has already executed the
+/// command, and repeating it is not safe for all of them (e.g. a MIFARE
+/// Classic increment).  Set an override to retry them anyway.
+#[derive(Debug, Clone)]
+pub struct Retries {
+    /// Policy of the commands without an override.
+    pub default: RetryPolicy,
+    overrides: HashMap<Command, RetryPolicy>,
+}
+
+impl Retries {
+    /// Use `policy` for `command`.
+    pub fn with_override(mut self, command: Command, policy: RetryPolicy) -> Self {
+        self.set_override(command, policy);
+        self
+    }
+
+    /// Use `policy` for `command`.
+    pub fn set_override(&mut self, command: Command, policy: RetryPolicy) {
+        self.overrides.insert(command, policy);
+    }
+
+    /// Go back to the default policy for `command`.
+    pub fn clear_override(&mut self, command: Command) {
+        self.overrides.remove(&command);
+    }
+
+    /// The policy used for `command`.
+    pub fn policy_for(&self, command: Command) -> RetryPolicy {
+        self.overrides.get(&command).copied().unwrap_or(self.default)
+    }
+}
+
+impl Default for Retries {
+    fn default() -> Self {
+        Self {
+            default: RetryPolicy::default(),
+            overrides: HashMap::new(),
+        }.with_override(Command::InDataExchange, RetryPolicy::NONE)
+            .with_override(Command::InCommunicateThru, RetryPolicy::NONE)
+    }
+}
@@ -0,0 +1,45 @@
+use std::fmt;
+
+use crate::pn532::{DiagnoseResult, FirmwareVersion};
+
+/// Pattern the communication line test has the PN532 echo back, with bits
+/// flipping in both directions.
+pub(crate) const PATTERN: [u8; 8] = [0x00, 0xFF, 0x55, 0xAA, 0x0F, 0xF0, 0x5A, 0xA5];
+
+/// Health check run when a reader is initialized, see
+/// [`Options::self_test`](crate::pn532::Options::self_test).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SelfTest {
+    /// Also run the antenna test with this detection threshold, see
+    /// [`DiagnoseTest::Antenna`](crate::pn532::DiagnoseTest::Antenna).
+    pub antenna_threshold: Option<u8>,
+}
+
+/// Results of a [`SelfTest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SelfTestReport {
+    pub firmware: FirmwareVersion,
+    /// Whether the PN532 echoed a known pattern intact.
+    pub communication_line: DiagnoseResult,
+    /// Outcome of the antenna test, if it was run.
+    pub antenna: Option<DiagnoseResult>,
+}
+
+impl SelfTestReport {
+    /// Whether every test passed.
+    pub fn is_ok(&self) -> bool {
+        self.communication_line == DiagnoseResult::Passed
+            && self.antenna.map_or(true, |antenna| antenna == DiagnoseResult::Passed)
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, communication line {:?}", self.firmware, self.communication_line)?;
+        if let Some(antenna) = self.antenna {
+            write!(f, ", antenna {:?}", antenna)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,313 @@
+//! Handles on an activated card that only offer the operations of its
+//! technology, and address them to it: the UID and target number are
+//! carried along instead of passed to every call.
+//!
+//! A handle borrows the reader mutably, so only one card is talked to at a
+//! time.  Creating one selects the card.
+
+use std::time::Duration;
+
+use crate::pn532::{
+    Ats, Block, ClassicDump, Error, KeyMap, MagicCard, NtagConfig, NtagDump, NtagVariant, PN532, Page, PassiveTarget,
+    RestoreOptions, RestoreReport, Result, Sector, SectorKey, SectorKeys, TagKind, TagVersion, Uid,
+};
+
+/// A card told apart by [`PN532::read_tag`].
+pub enum Tag<'r, P: PN532 + ?Sized> {
+    MifareClassic(MifareClassicTag<'r, P>),
+    Ntag2xx(Ntag2xxTag<'r, P>),
+    IsoDep(IsoDepTag<'r, P>),
+    /// A card without a handle of its own.
+    Other(PassiveTarget),
+}
+
+impl<'r, P: PN532 + ?Sized> Tag<'r, P> {
+    /// The handle for `card`, of the technology its SAK tells.
+    pub fn new(reader: &'r mut P, card: PassiveTarget) -> Result<Self> {
+        Ok(match TagKind::from_activation(&card) {
+            None => Tag::Ntag2xx(Ntag2xxTag::new(reader, card)?),
+            Some(TagKind::MifareClassic1K | TagKind::MifareClassic4K) => Tag::MifareClassic(MifareClassicTag::new(reader, card)?),
+            Some(TagKind::MifareDesfire | TagKind::Iso14443_4) => Tag::IsoDep(IsoDepTag::new(reader, card)?),
+            Some(_) => Tag::Other(card),
+        })
+    }
+
+    /// The card as activated.
+    pub fn card(&self) -> &PassiveTarget {
+        match self {
+            Tag::MifareClassic(tag) => tag.card(),
+            Tag::Ntag2xx(tag) => tag.card(),
+            Tag::IsoDep(tag) => tag.card(),
+            Tag::Other(card) => card,
+        }
+    }
+}
+
+/// A MIFARE Classic card.
+pub struct MifareClassicTag<'r, P: PN532 + ?Sized> {
+    reader: &'r mut P,
+    card: PassiveTarget,
+}
+
+impl<'r, P: PN532 + ?Sized> MifareClassicTag<'r, P> {
+    /// Select `card`, which SAK must tell is a MIFARE Classic.
+    pub fn new(reader: &'r mut P, card: PassiveTarget) -> Result<Self> {
+        if card.classic_sectors().is_none() {
+            return Err(Error::InvalidArgument(format!("SAK {:#04x} is not a MIFARE Classic", card.sak)));
+        }
+        reader.select_target(card.target_number)?;
+        Ok(Self { reader, card })
+    }
+
+    pub fn card(&self) -> &PassiveTarget {
+        &self.card
+    }
+
+    pub fn uid(&self) -> &Uid {
+        &self.card.uid
+    }
+
+    /// Number of sectors, from SAK.
+    pub fn sectors(&self) -> u8 {
+        self.card.classic_sectors().unwrap_or(16)
+    }
+
+    /// The reader, for operations without a method here.
+    pub fn reader(&mut self) -> &mut P {
+        self.reader
+    }
+
+    /// Authenticate the sector of `block` with `key`.  Returns `false` if
+    /// the card refused it, see [`PN532::mifare_classic_authenticate_block`];
+    /// the card then halts until deselected and selected again.
+    pub fn authenticate(&mut self, block: Block, key: &SectorKey) -> Result<bool> {
+        self.reader.mifare_classic_authenticate_block(&self.card.uid, block, key.key_type.auth_command(), &key.key)
+    }
+
+    /// Read `block`, whose sector must be authenticated.
+    pub fn read_block(&mut self, block: Block) -> Result<[u8; 16]> {
+        let mut buf = [0; 16];
+        if !self.reader.mifare_classic_read_block_into(block, &mut buf)? {
+            return Err(Error::Timeout);
+        }
+        Ok(buf)
+    }
+
+    /// Write `block`, whose sector must be authenticated; not a trailer nor
+    /// block 0.
+    pub fn write_block(&mut self, block: Block, data: &[u8; 16]) -> Result<()> {
+        if !self.reader.mifare_classic_write_block(block, data)? {
+            return Err(Error::Timeout);
+        }
+        Ok(())
+    }
+
+    /// See [`PN532::read_sector`].
+    pub fn read_sector(&mut self, sector: Sector, keys: &KeyMap) -> Result<Vec<u8>> {
+        self.reader.read_sector(&self.card.uid, sector, keys)
+    }
+
+    /// See [`PN532::write_sector`].
+    pub fn write_sector(&mut self, sector: Sector, data: &[u8], keys: &KeyMap) -> Result<()> {
+        self.reader.write_sector(&self.card.uid, sector, data, keys)
+    }
+
+    /// See [`PN532::write_sector_trailer`].
+    pub fn write_sector_trailer(&mut self, sector: Sector, keys: &SectorKeys, access_bits: [u8; 4]) -> Result<()> {
+        if !self.reader.write_sector_trailer(sector, keys, access_bits)? {
+            return Err(Error::Timeout);
+        }
+        Ok(())
+    }
+
+    /// See [`PN532::dump_classic`].
+    pub fn dump(&mut self, keys: &KeyMap) -> Result<ClassicDump> {
+        self.reader.dump_classic(&self.card, keys)
+    }
+
+    /// See [`PN532::restore_classic`].
+    pub fn restore(&mut self, dump: &ClassicDump, keys: &KeyMap, options: &RestoreOptions) -> Result<RestoreReport> {
+        self.reader.restore_classic(&self.card, dump, keys, options)
+    }
+
+    /// See [`PN532::detect_magic`].
+    pub fn detect_magic(&mut self, keys: &KeyMap) -> Result<MagicCard> {
+        self.reader.detect_magic(&self.card.uid, keys)
+    }
+}
+
+/// An NTAG2xx or MIFARE Ultralight tag.
+pub struct Ntag2xxTag<'r, P: PN532 + ?Sized> {
+    reader: &'r mut P,
+    card: PassiveTarget,
+    version: Option<TagVersion>,
+}
+
+impl<'r, P: PN532 + ?Sized> Ntag2xxTag<'r, P> {
+    /// Select `card`, which SAK must tell is a Type 2 tag.
+    pub fn new(reader: &'r mut P, card: PassiveTarget) -> Result<Self> {
+        if card.sak != 0x00 {
+            return Err(Error::InvalidArgument(format!("SAK {:#04x} is not a Type 2 tag", card.sak)));
+        }
+        reader.select_target(card.target_number)?;
+        Ok(Self { reader, card, version: None })
+    }
+
+    pub fn card(&self) -> &PassiveTarget {
+        &self.card
+    }
+
+    pub fn uid(&self) -> &Uid {
+        &self.card.uid
+    }
+
+    /// The reader, for operations without a method here.
+    pub fn reader(&mut self) -> &mut P {
+        self.reader
+    }
+
+    /// The answer to GET_VERSION, asked once.
+    pub fn version(&mut self) -> Result<TagVersion> {
+        if let Some(version) = self.version {
+            return Ok(version);
+        }
+        let version = self.reader.ntag2xx_get_version()?;
+        self.version = Some(version);
+        Ok(version)
+    }
+
+    pub fn variant(&mut self) -> Result<NtagVariant> {
+        Ok(self.version()?.variant())
+    }
+
+    pub fn read_page(&mut self, page: Page) -> Result<[u8; 4]> {
+        let mut buf = [0; 4];
+        if !self.reader.ntag2xx_read_block_into(page, &mut buf)? {
+            return Err(Error::Timeout);
+        }
+        Ok(buf)
+    }
+
+    pub fn write_page(&mut self, page: Page, data: &[u8; 4]) -> Result<()> {
+        if !self.reader.ntag2xx_write_block(page, data)? {
+            return Err(Error::Timeout);
+        }
+        Ok(())
+    }
+
+    /// See [`PN532::ntag2xx_fast_read`].
+    pub fn fast_read(&mut self, start: Page, end: Page) -> Result<Vec<u8>> {
+        self.reader.ntag2xx_fast_read(start, end)
+    }
+
+    /// See [`PN532::ntag2xx_password_auth`].
+    pub fn password_auth(&mut self, password: &[u8; 4], pack: Option<&[u8; 2]>) -> Result<[u8; 2]> {
+        self.reader.ntag2xx_password_auth(password, pack)
+    }
+
+    /// See [`PN532::ntag2xx_read_config`].
+    pub fn read_config(&mut self) -> Result<NtagConfig> {
+        let variant = self.variant()?;
+        self.reader.ntag2xx_read_config(variant)
+    }
+
+    /// See [`PN532::ntag2xx_write_config`].
+    pub fn write_config(&mut self, config: &NtagConfig) -> Result<()> {
+        let variant = self.variant()?;
+        self.reader.ntag2xx_write_config(variant, config)
+    }
+
+    /// See [`PN532::ntag2xx_read_counter`].
+    pub fn read_counter(&mut self) -> Result<u32> {
+        self.reader.ntag2xx_read_counter()
+    }
+
+    /// See [`PN532::ntag2xx_read_signature`].
+    pub fn read_signature(&mut self) -> Result<[u8; 32]> {
+        self.reader.ntag2xx_read_signature()
+    }
+
+    /// See [`PN532::ntag2xx_make_read_only`].
+    pub fn make_read_only(&mut self) -> Result<()> {
+        let variant = self.variant()?;
+        self.reader.ntag2xx_make_read_only(variant)
+    }
+
+    /// See [`PN532::dump_ntag`].
+    pub fn dump(&mut self) -> Result<NtagDump> {
+        self.reader.dump_ntag()
+    }
+
+    /// See [`PN532::restore_ntag`].
+    pub fn restore(&mut self, dump: &NtagDump, options: &RestoreOptions) -> Result<RestoreReport> {
+        self.reader.restore_ntag(dump, options)
+    }
+}
+
+/// An ISO/IEC 14443-4 (ISO-DEP) card, such as a MIFARE DESFire.
+pub struct IsoDepTag<'r, P: PN532 + ?Sized> {
+    reader: &'r mut P,
+    card: PassiveTarget,
+    timeout: Option<Duration>,
+}
+
+impl<'r, P: PN532 + ?Sized> IsoDepTag<'r, P> {
+    /// Select `card`, which SAK must tell supports ISO/IEC 14443-4.  The
+    /// exchanges wait as long as its ATS tells, see
+    /// [`Ats::response_timeout`].
+    pub fn new(reader: &'r mut P, card: PassiveTarget) -> Result<Self> {
+        if !card.is_iso14443_4() {
+            return Err(Error::InvalidArgument(format!("SAK {:#04x} is not an ISO/IEC 14443-4 card", card.sak)));
+        }
+        let timeout = match card.parse_ats() {
+            Ok(ats) => ats.map(|ats| ats.response_timeout()),
+            Err(_) => {
+                warn!("Malformed ATS, using the InDataExchange response timeout");
+                None
+            }
+        };
+        reader.select_target(card.target_number)?;
+        Ok(Self { reader, card, timeout })
+    }
+
+    pub fn card(&self) -> &PassiveTarget {
+        &self.card
+    }
+
+    pub fn uid(&self) -> &Uid {
+        &self.card.uid
+    }
+
+    /// The ATS, starting with its length byte TL.
+    pub fn ats(&self) -> Option<&[u8]> {
+        self.card.ats.as_deref()
+    }
+
+    /// See [`PassiveTarget::parse_ats`].
+    pub fn parse_ats(&self) -> Result<Option<Ats>> {
+        self.card.parse_ats()
+    }
+
+    /// How long an exchange waits for the card, `None` for the
+    /// InDataExchange response timeout of the reader.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Wait `timeout` for the card instead of what its ATS tells, e.g. for
+    /// a card asking for long waiting time extensions.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// The reader, for operations without a method here.
+    pub fn reader(&mut self) -> &mut P {
+        self.reader
+    }
+
+    /// Send `data`, an APDU or native command, and return the card's
+    /// answer.  The PN532 handles the ISO-DEP blocks and chaining.
+    pub fn exchange(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+        self.reader.in_data_exchange_with_timeout(self.card.target_number, data, self.timeout)?.ok_or(Error::Timeout)
+    }
+}
@@ -0,0 +1,205 @@
+//! Verification of the NXP originality signature of NTAG21x and Ultralight
+//! EV1 tags, read with
+//! [`PN532::ntag2xx_read_signature`](crate::pn532::PN532::ntag2xx_read_signature).
+//!
+//! The signature is an ECDSA signature on secp128r1 of the UID, taken as is
+//! without hashing.  The 128 bit curve fits the field arithmetic in `u128`,
+//! so no big integer crate is needed; speed does not matter for one check
+//! per tag.
+
+use crate::pn532::Uid;
+
+/// NXP public key of NTAG21x tags.
+pub const NTAG21X_PUBLIC_KEY: [u8; 33] = [
+    0x04, 0x49, 0x4E, 0x1A, 0x38, 0x6D, 0x3D, 0x3C, 0xFE, 0x3D, 0xC1, 0x0E, 0x5D, 0xE6, 0x8A, 0x49,
+    0x9B, 0x1C, 0x20, 0x2D, 0xB5, 0xB1, 0x32, 0x39, 0x3E, 0x89, 0xED, 0x19, 0xFE, 0x5B, 0xE8, 0xBC,
+    0x61,
+];
+
+/// NXP public key of MIFARE Ultralight EV1 tags.
+pub const ULTRALIGHT_EV1_PUBLIC_KEY: [u8; 33] = [
+    0x04, 0x90, 0x93, 0x3B, 0xDC, 0xD6, 0xE9, 0x9B, 0x4E, 0x25, 0x5E, 0x3D, 0xA5, 0x53, 0x89, 0xA8,
+    0x27, 0x56, 0x4E, 0x11, 0x71, 0x8E, 0x01, 0x72, 0x92, 0xFA, 0xF2, 0x32, 0x26, 0xA9, 0x66, 0x14,
+    0xB8,
+];
+
+// secp128r1 (SEC 2).
+const P: u128 = 0xFFFFFFFD_FFFFFFFF_FFFFFFFF_FFFFFFFF;
+const A: u128 = 0xFFFFFFFD_FFFFFFFF_FFFFFFFF_FFFFFFFC;
+const N: u128 = 0xFFFFFFFE_00000000_75A30D1B_9038A115;
+const G: Point = Some((0x161FF752_8B899B2D_0C28607C_A52C5B86, 0xCF5AC839_5BAFEB13_C02DA292_DDED7A83));
+
+/// An affine point, `None` being the point at infinity.
+type Point = Option<(u128, u128)>;
+
+/// Whether `signature` (r || s) is the signature of `uid` under
+/// `public_key` (0x04 || x || y).
+pub fn verify(uid: &Uid, signature: &[u8; 32], public_key: &[u8; 33]) -> bool {
+    let [0x04, ref key @ ..] = public_key[..] else {
+        return false;
+    };
+    let q = Some((from_be(&key[..16]), from_be(&key[16..])));
+    let (r, s) = (from_be(&signature[..16]), from_be(&signature[16..]));
+    if !(1..N).contains(&r) || !(1..N).contains(&s) {
+        return false;
+    }
+
+    let e = from_be(uid.as_bytes()) % N;
+    let w = inv_mod(s, N);
+    let u1 = mul_mod(e, w, N);
+    let u2 = mul_mod(r, w, N);
+    match add(mul(u1, G), mul(u2, q)) {
+        Some((x, _)) => x % N == r,
+        None => false,
+    }
+}
+
+fn from_be(bytes: &[u8]) -> u128 {
+    bytes.iter().fold(0, |value, &byte| value << 8 | u128::from(byte))
+}
+
+fn add_mod(x: u128, y: u128, m: u128) -> u128 {
+    let (sum, overflow) = x.overflowing_add(y);
+    if overflow || sum >= m {
+        sum.wrapping_sub(m)
+    } else {
+        sum
+    }
+}
+
+fn sub_mod(x: u128, y: u128, m: u128) -> u128 {
+    if x >= y {
+        x - y
+    } else {
+        m - (y - x)
+    }
+}
+
+/// `x * y mod m` by double and add, as the product does not fit in `u128`.
+fn mul_mod(x: u128, y: u128, m: u128) -> u128 {
+    (0..128).rev().fold(0, |acc, bit| {
+        let acc = add_mod(acc, acc, m);
+        if y >> bit & 1 == 1 {
+            add_mod(acc, x, m)
+        } else {
+            acc
+        }
+    })
+}
+
+/// `x^-1 mod m` for prime `m`, as `x^(m - 2)`.
+fn inv_mod(x: u128, m: u128) -> u128 {
+    let e = m - 2;
+    (0..128).rev().fold(1, |acc, bit| {
+        let acc = mul_mod(acc, acc, m);
+        if e >> bit & 1 == 1 {
+            mul_mod(acc, x, m)
+        } else {
+            acc
+        }
+    })
+}
+
+fn add(p1: Point, p2: Point) -> Point {
+    let ((x1, y1), (x2, y2)) = match (p1, p2) {
+        (None, p) | (p, None) => return p,
+        (Some(p1), Some(p2)) => (p1, p2),
+    };
+    let slope = if x1 == x2 {
+        if add_mod(y1, y2, P) == 0 {
+            return None;
+        }
+        // Doubling: (3x^2 + a) / 2y.
+        let x1_2 = mul_mod(x1, x1, P);
+        let num = add_mod(add_mod(add_mod(x1_2, x1_2, P), x1_2, P), A, P);
+        mul_mod(num, inv_mod(add_mod(y1, y1, P), P), P)
+    } else {
+        mul_mod(sub_mod(y2, y1, P), inv_mod(sub_mod(x2, x1, P), P), P)
+    };
+    let x3 = sub_mod(sub_mod(mul_mod(slope, slope, P), x1, P), x2, P);
+    let y3 = sub_mod(mul_mod(slope, sub_mod(x1, x3, P), P), y1, P);
+    Some((x3, y3))
+}
+
+fn mul(k: u128, point: Point) -> Point {
+    (0..128).rev().fold(None, |acc, bit| {
+        let acc = add(acc, acc);
+        if k >> bit & 1 == 1 {
+            add(acc, point)
+        } else {
+            acc
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const B: u128 = 0xE87579C1_1079F43D_D824993C_2CEE5ED3;
+
+    /// A key pair and signature of a 7 byte UID made with an independent
+    /// implementation, as no tag's private key is known.
+    const TEST_PUBLIC_KEY: [u8; 33] = [
+        0x04, 0x04, 0xC0, 0x7D, 0x66, 0x27, 0x84, 0xD5, 0x3B, 0xFD, 0x6D, 0x7D, 0x6B, 0xE2, 0x34, 0x7E,
+        0x26, 0x59, 0x58, 0xFA, 0x91, 0xCF, 0x46, 0x3E, 0xF2, 0x1C, 0x27, 0x48, 0x92, 0xEC, 0xE1, 0xBD,
+        0xF4,
+    ];
+    const TEST_UID: [u8; 7] = [0x04, 0xD1, 0xA2, 0xB2, 0xC3, 0xD4, 0xE5];
+    const TEST_SIGNATURE: [u8; 32] = [
+        0xBD, 0xF2, 0x55, 0x29, 0x9A, 0xE0, 0x4D, 0xDD, 0x6E, 0xC0, 0x48, 0x91, 0x50, 0x0A, 0x3E, 0x41,
+        0x61, 0xBE, 0xBC, 0x37, 0xC3, 0x99, 0x53, 0xBF, 0x1D, 0x26, 0x6A, 0x8B, 0xE3, 0x64, 0x6A, 0xB7,
+    ];
+
+    fn on_curve(point: Point) -> bool {
+        let Some((x, y)) = point else {
+            return true;
+        };
+        let rhs = add_mod(add_mod(mul_mod(mul_mod(x, x, P), x, P), mul_mod(A, x, P), P), B, P);
+        mul_mod(y, y, P) == rhs
+    }
+
+    fn public_key_point(key: &[u8; 33]) -> Point {
+        Some((from_be(&key[1..17]), from_be(&key[17..])))
+    }
+
+    #[test]
+    fn generator_is_on_the_curve() {
+        assert!(on_curve(G));
+        assert!(on_curve(add(G, G)));
+        assert!(on_curve(mul(0x1234_5678, G)));
+    }
+
+    #[test]
+    fn generator_has_order_n() {
+        assert_eq!(mul(N, G), None);
+        assert_eq!(mul(N - 1, G), G.map(|(x, y)| (x, P - y)));
+    }
+
+    #[test]
+    fn nxp_public_keys_are_on_the_curve() {
+        assert!(on_curve(public_key_point(&NTAG21X_PUBLIC_KEY)));
+        assert!(on_curve(public_key_point(&ULTRALIGHT_EV1_PUBLIC_KEY)));
+    }
+
+    #[test]
+    fn accepts_a_valid_signature() {
+        let uid = Uid::try_from(&TEST_UID[..]).unwrap();
+        assert!(verify(&uid, &TEST_SIGNATURE, &TEST_PUBLIC_KEY));
+    }
+
+    #[test]
+    fn rejects_a_tampered_signature() {
+        let uid = Uid::try_from(&TEST_UID[..]).unwrap();
+        // First and last bytes of r and s.
+        for i in [0, 15, 16, 31] {
+            let mut signature = TEST_SIGNATURE;
+            signature[i] ^= 0x01;
+            assert!(!verify(&uid, &signature, &TEST_PUBLIC_KEY), "byte {}", i);
+        }
+        let mut other = TEST_UID;
+        other[6] ^= 0x01;
+        assert!(!verify(&Uid::try_from(&other[..]).unwrap(), &TEST_SIGNATURE, &TEST_PUBLIC_KEY));
+        assert!(!verify(&uid, &TEST_SIGNATURE, &NTAG21X_PUBLIC_KEY));
+    }
+}
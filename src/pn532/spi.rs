@@ -1,29 +1,72 @@
+use std::sync::{Arc, Mutex, PoisonError};
+use std::path::PathBuf;
 use std::thread;
 use std::time::{Duration, Instant};
-use log::debug;
 use rppal::spi::{Bus, SlaveSelect, Mode, Spi};
-use rppal::gpio::Gpio;
-use crate::pn532::PN532;
+use rppal::gpio::{Gpio, Trigger};
+#[cfg(feature = "tokio")]
+use rppal::gpio::InputPin;
+use crate::pn532::{AuthCache, Canceller, Error, GpioCache, Hooks, Options, PN532, Retries, SelfTest, SelfTestReport, Stats, Timeouts, Transport, Watchdog};
+use crate::pn532::capture::{Capture, Direction};
+use crate::pn532::command::SamConfiguration;
 
 const SPI_STATREAD: u8 =    0x02;
 const SPI_DATAWRITE: u8 =   0x01;
 const SPI_DATAREAD: u8 =    0x03;
 const SPI_READY: u8 =       0x01;
 
-struct SpiDevice {
-    spi: Spi,
+/// Preamble, start code, LEN and LCS.
+const FRAME_HEADER_LEN: usize = 5;
+/// LENM, LENL and LCS following the header of an extended frame.
+const EXTENDED_HEADER_LEN: usize = 3;
+
+/// Time the PN532 needs to come out of power down once NSS is asserted.
+const WAKEUP_DELAY: Duration = Duration::from_millis(2);
+
+/// Status byte polling schedule: every millisecond during the first
+/// `FAST_POLL_WINDOW`, when most commands complete, then backing off from
+/// `SLOW_POLL_MIN` to `SLOW_POLL_MAX` while waiting for a card.
+const FAST_POLL_INTERVAL: Duration = Duration::from_millis(1);
+const FAST_POLL_WINDOW: Duration = Duration::from_millis(20);
+const SLOW_POLL_MIN: Duration = Duration::from_millis(20);
+const SLOW_POLL_MAX: Duration = Duration::from_millis(50);
+
+/// Longest the IRQ wait blocks between checks of the [`Canceller`].
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Handle to an SPI bus that may be shared by several PN532 modules.
+pub type SharedBus = Arc<Mutex<Spi>>;
+
+/// Open the SPI bus with the settings the PN532 expects.
+pub(crate) fn open_bus(bus: Bus) -> crate::pn532::Result<SharedBus> {
+    Ok(Arc::new(Mutex::new(open_spi(bus)?)))
+}
+
+fn open_spi(bus: Bus) -> crate::pn532::Result<Spi> {
+    Ok(Spi::new(bus, SlaveSelect::Ss0, 1_000_000, Mode::Mode2)?)
+}
+
+/// An SPI connection to a single PN532, optionally selected through a GPIO
+/// chip-select pin instead of the hardware one.
+#[derive(Clone)]
+pub struct SpiDevice {
+    bus: SharedBus,
+    bus_id: Bus,
     gpio: Gpio,
     cs: Option<u8>,
 }
 
 impl SpiDevice {
-    fn new(cs: Option<u8>) -> crate::pn532::Result<Self> {
-        let spi =
-            Spi::new(Bus::Spi0, SlaveSelect::Ss0, 1_000_000, Mode::Mode2)?;
+    pub fn new(cs: Option<u8>) -> crate::pn532::Result<Self> {
+        Self::with_bus(open_bus(Bus::Spi0)?, Bus::Spi0, cs)
+    }
+
+    fn with_bus(bus: SharedBus, bus_id: Bus, cs: Option<u8>) -> crate::pn532::Result<Self> {
         let gpio = Gpio::new()?;
 
         let this = Self {
-            spi,
+            bus,
+            bus_id,
             gpio,
             cs
         };
@@ -35,87 +78,490 @@ impl SpiDevice {
         Ok(this)
     }
 
-    fn write(&mut self, buf: &[u8]) -> crate::pn532::Result<usize> {
+    /// Run `f` against the bus with this device selected. The bus stays locked
+    /// for the whole transaction so other devices on it cannot interleave.
+    fn transaction<R>(&mut self, f: impl FnOnce(&mut Spi) -> rppal::spi::Result<R>) -> crate::pn532::Result<R> {
+        let mut spi = self.bus.lock().unwrap_or_else(PoisonError::into_inner);
+        // The PN532 only needs NSS low for some 100 ns before the first SCK
+        // edge, which setting up the transfer already takes.
         let cs = if let Some(pin) = self.cs {
             Some(self.gpio.get(pin)?.into_output_low())
         } else {
             None
         };
-        thread::sleep(Duration::from_millis(1));
-        let ret = self.spi.write(buf)?;
+        let ret = f(&mut spi)?;
 
-        cs.map(|mut pin| {
-            thread::sleep(Duration::from_millis(1));
+        if let Some(mut pin) = cs {
             pin.set_high();
-            Some(pin)
-        });
+        }
 
         Ok(ret)
     }
 
-    fn read(&mut self, buf: &mut [u8]) -> crate::pn532::Result<usize> {
-        let cs = if let Some(pin) = self.cs {
-            Some(self.gpio.get(pin)?.into_output_low())
-        } else {
-            None
-        };
-        thread::sleep(Duration::from_millis(1));
-        let ret = self.spi.read(buf)?;
+    pub fn write(&mut self, buf: &[u8]) -> crate::pn532::Result<usize> {
+        self.transaction(|spi| spi.write(buf))
+    }
 
-        cs.map(|mut pin| {
-            thread::sleep(Duration::from_millis(1));
-            pin.set_high();
-            Some(pin)
-        });
+    pub fn read(&mut self, buf: &mut [u8]) -> crate::pn532::Result<usize> {
+        self.transaction(|spi| spi.read(buf))
+    }
 
-        Ok(ret)
+    pub fn transfer(&mut self, read_buf: &mut [u8], write_buf: &[u8]) -> crate::pn532::Result<usize> {
+        self.transaction(|spi| spi.transfer(read_buf, write_buf))
     }
 
-    fn transfer(&mut self, read_buf: &mut [u8], write_buf: &[u8]) -> crate::pn532::Result<usize> {
-        let cs = if let Some(pin) = self.cs {
-            Some(self.gpio.get(pin)?.into_output_low())
-        } else {
-            None
-        };
-        thread::sleep(Duration::from_millis(1));
-        let ret = self.spi.transfer(read_buf, write_buf)?;
+    /// Close and open the bus again, e.g. after the SPI adapter was
+    /// replugged.  Every device sharing the bus gets the new handle.
+    pub fn reopen(&mut self) -> crate::pn532::Result<()> {
+        let mut spi = self.bus.lock().unwrap_or_else(PoisonError::into_inner);
+        *spi = open_spi(self.bus_id)?;
+        self.gpio = Gpio::new()?;
 
-        cs.map(|mut pin| {
-            thread::sleep(Duration::from_millis(1));
-            pin.set_high();
-            Some(pin)
-        });
+        Ok(())
+    }
 
-        Ok(ret)
+    /// The underlying bus, for handing it over to another driver.
+    pub fn into_bus(self) -> SharedBus {
+        self.bus
     }
 }
 
-struct PN532Spi {
+/// A PN532 connected over SPI.
+pub struct PN532Spi {
     spi: SpiDevice,
     cs: Option<u8>,
     irq: Option<u8>,
-    reset: Option<u8>
+    reset: Option<u8>,
+    options: Options,
+    auth_cache: AuthCache,
+    gpio_cache: GpioCache,
+    stats: Stats,
+    canceller: Canceller,
+    capture: Option<Capture>,
+    hooks: Hooks,
+    self_test_report: Option<SelfTestReport>,
+    /// Scratch buffers for SPI transfers, reused so commands do not allocate.
+    tx: Vec<u8>,
+    rx: Vec<u8>,
 }
 
 impl PN532Spi {
-    fn new(cs: Option<u8>, irq: Option<u8>, reset: Option<u8>) -> crate::pn532::Result<Self> {
-        let spi= SpiDevice::new(cs)?;
+    /// Open the PN532 on SPI0 and initialize it with the default [`Options`].
+    /// `cs`, `irq` and `reset` are the BCM numbers of the GPIO pins wired to
+    /// the chip, if any. Use [`PN532SpiBuilder`] for other settings.
+    pub fn new(cs: Option<u8>, irq: Option<u8>, reset: Option<u8>) -> crate::pn532::Result<Self> {
+        PN532SpiBuilder { cs, irq, reset, ..PN532SpiBuilder::new() }.build()
+    }
+
+    fn from_device(spi: SpiDevice, builder: PN532SpiBuilder) -> crate::pn532::Result<Self> {
         let mut this = Self {
             spi,
-            cs,
-            irq,
-            reset
+            cs: builder.cs,
+            irq: builder.irq,
+            reset: builder.reset,
+            options: builder.options,
+            auth_cache: AuthCache::default(),
+            gpio_cache: GpioCache::default(),
+            stats: Stats::default(),
+            canceller: Canceller::default(),
+            capture: None,
+            hooks: Hooks::default(),
+            self_test_report: None,
+            tx: Vec::new(),
+            rx: Vec::new(),
         };
 
+        if let Some(path) = &builder.capture {
+            this.capture = Some(Capture::create(path)?);
+        }
+
+        // There is nothing to clean up on a PN532 that never came up.
+        let cleanup_on_drop = std::mem::replace(&mut this.options.cleanup_on_drop, false);
         this.gpio_init()?;
-        this.init(reset)?;
+        this.self_test_report = this.init(this.reset)?;
+        this.options.cleanup_on_drop = cleanup_on_drop;
 
         Ok(this)
     }
+
+    /// Block until the PN532 pulls its IRQ line low, which it does while a
+    /// frame is ready to be read.
+    fn wait_irq(&mut self, irq: u8, timeout: Duration) -> crate::pn532::Result<bool> {
+        let mut pin = self.spi.gpio.get(irq)?.into_input();
+        // Arm the interrupt before looking at the line so a falling edge in
+        // between is not missed.
+        pin.set_interrupt(Trigger::FallingEdge)?;
+        if pin.is_low() {
+            return Ok(true);
+        }
+
+        let start = Instant::now();
+        loop {
+            if self.canceller.take() {
+                return Err(Error::Cancelled);
+            }
+            let remaining = timeout.saturating_sub(start.elapsed());
+            if pin.poll_interrupt(true, Some(remaining.min(CANCEL_CHECK_INTERVAL)))?.is_some() {
+                return Ok(true);
+            }
+            if remaining <= CANCEL_CHECK_INTERVAL {
+                return Ok(false);
+            }
+        }
+    }
+
+    /// Copy the `read` bytes of a DATAREAD transfer into `buf`, skipping the
+    /// first one which answers the DATAREAD command itself.
+    fn received(&mut self, buf: &mut [u8], read: usize) -> usize {
+        let read = read.saturating_sub(1).min(buf.len());
+
+        for (dst, src) in buf.iter_mut().zip(&self.rx[1..=read]) {
+            *dst = src.reverse_bits();
+        }
+        debug!("Reading: {:?}", &buf[..read]);
+        self.capture(Direction::Pn532ToHost, &buf[..read]);
+
+        read
+    }
+
+    /// Record `data` in the capture, if one is running.  A capture that
+    /// fails is stopped rather than failing the command.
+    fn capture(&mut self, direction: Direction, data: &[u8]) {
+        if let Some(capture) = &mut self.capture {
+            if capture.record(direction, data).is_err() {
+                warn!("Frame capture failed, stopping it");
+                self.capture = None;
+            }
+        }
+    }
+
+    /// Start capturing every frame exchanged with the PN532 to `capture`,
+    /// replacing the running capture if any.
+    pub fn start_capture(&mut self, capture: Capture) {
+        self.capture = Some(capture);
+    }
+
+    /// Stop the running capture, if any.
+    pub fn stop_capture(&mut self) -> Option<Capture> {
+        self.capture.take()
+    }
+
+    /// The IRQ pin as an input, if one is wired and waiting on it is enabled.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn irq_input(&self) -> crate::pn532::Result<Option<InputPin>> {
+        match (self.irq, self.options.use_irq) {
+            (Some(irq), true) => Ok(Some(self.spi.gpio.get(irq)?.into_input())),
+            _ => Ok(None),
+        }
+    }
+
+    /// Report of the self test run at initialization, if
+    /// [`Options::self_test`] was set.
+    pub fn self_test_report(&self) -> Option<&SelfTestReport> {
+        self.self_test_report.as_ref()
+    }
+
+    /// A handle to cancel the reader's waits from another thread.
+    pub fn canceller(&self) -> Canceller {
+        self.canceller.clone()
+    }
+
+    /// Timing and link health counters of this reader.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Reset the counters, e.g. before measuring a sequence of commands.
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+    }
+
+    /// Replace the timeouts used when waiting for the PN532.
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.options.timeouts = timeouts;
+    }
+
+    pub fn timeouts_mut(&mut self) -> &mut Timeouts {
+        &mut self.options.timeouts
+    }
+
+    /// Replace the retry policies, see [`Options::retries`].
+    pub fn set_retries(&mut self, retries: Retries) {
+        self.options.retries = retries;
+    }
+
+    pub fn retries_mut(&mut self) -> &mut Retries {
+        &mut self.options.retries
+    }
+
+    /// Turn automatic recovery after transport failures on or off, see
+    /// [`Options::recover_on_transport_error`].
+    pub fn set_recover_on_transport_error(&mut self, recover: bool) {
+        self.options.recover_on_transport_error = recover;
+    }
+
+    /// Turn the watchdog on or off, see [`Options::watchdog`].
+    pub fn set_watchdog(&mut self, watchdog: Option<Watchdog>) {
+        self.options.watchdog = watchdog;
+    }
+
+    /// Turn read-only mode on or off, see [`Options::read_only`].
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.options.read_only = read_only;
+    }
+
+    /// Turn recording of the exchanged bytes in errors on or off, see
+    /// [`Options::error_context`].
+    pub fn set_error_context(&mut self, error_context: bool) {
+        self.options.error_context = error_context;
+    }
+
+    /// Whether dropping the reader releases the targets and switches the RF
+    /// field off (and optionally powers the PN532 down).
+    pub fn set_cleanup_on_drop(&mut self, cleanup: bool, power_down: bool) {
+        self.options.cleanup_on_drop = cleanup;
+        self.options.power_down_on_drop = power_down;
+    }
+
+    /// Shut the reader down and hand back the SPI device: release all targets,
+    /// switch the RF field off (powering down if [`Options::power_down_on_drop`]
+    /// is set) and park the CS and reset pins high.  The pins are parked and
+    /// the device handed back even if the PN532 does not answer; the error
+    /// then comes with the device.
+    pub fn shutdown(mut self) -> Result<SpiDevice, (Error, SpiDevice)> {
+        // Whatever happens below, do not try again when `self` is dropped.
+        self.options.cleanup_on_drop = false;
+        let mut result = self.cleanup(self.options.power_down_on_drop);
+        for pin in [self.cs, self.reset].into_iter().flatten() {
+            match self.spi.gpio.get(pin) {
+                Ok(pin) => pin.into_output_high().set_reset_on_drop(false),
+                Err(e) => result = result.and(Err(e.into())),
+            }
+        }
+
+        match result {
+            Ok(()) => Ok(self.spi.clone()),
+            Err(e) => Err((e, self.spi.clone())),
+        }
+    }
+}
+
+impl Drop for PN532Spi {
+    fn drop(&mut self) {
+        if self.options.cleanup_on_drop && self.cleanup(self.options.power_down_on_drop).is_err() {
+            warn!("Failed to release targets and switch the RF field off");
+        }
+    }
 }
 
 impl PN532 for PN532Spi {
-    fn gpio_init(&self) -> crate::pn532::Result<()> {
+    fn options(&self) -> &Options {
+        &self.options
+    }
+
+    fn auth_cache(&mut self) -> &mut AuthCache {
+        &mut self.auth_cache
+    }
+
+    fn gpio_cache(&mut self) -> &mut GpioCache {
+        &mut self.gpio_cache
+    }
+
+    fn hooks(&mut self) -> &mut Hooks {
+        &mut self.hooks
+    }
+
+    fn stats_mut(&mut self) -> &mut Stats {
+        &mut self.stats
+    }
+}
+
+/// Builds a [`PN532Spi`] with non-default pins, bus or [`Options`].
+///
+/// ```no_run
+/// use pn532_nfc::PN532SpiBuilder;
+///
+/// let pn532 = PN532SpiBuilder::new()
+///     .cs(4)
+///     .reset(20)
+///     .init_retries(5)
+///     .build()?;
+/// # Ok::<(), pn532_nfc::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct PN532SpiBuilder {
+    bus: Bus,
+    cs: Option<u8>,
+    irq: Option<u8>,
+    reset: Option<u8>,
+    options: Options,
+    capture: Option<PathBuf>,
+}
+
+impl PN532SpiBuilder {
+    pub fn new() -> Self {
+        Self {
+            bus: Bus::Spi0,
+            cs: None,
+            irq: None,
+            reset: None,
+            options: Options::default(),
+            capture: None,
+        }
+    }
+
+    /// SPI bus the PN532 is on, SPI0 by default.
+    pub fn bus(mut self, bus: Bus) -> Self {
+        self.bus = bus;
+        self
+    }
+
+    /// GPIO pin used as chip select instead of the bus' hardware CS.
+    pub fn cs(mut self, pin: u8) -> Self {
+        self.cs = Some(pin);
+        self
+    }
+
+    /// GPIO pin connected to the PN532's IRQ output.
+    pub fn irq(mut self, pin: u8) -> Self {
+        self.irq = Some(pin);
+        self
+    }
+
+    /// GPIO pin connected to the PN532's reset input.
+    pub fn reset(mut self, pin: u8) -> Self {
+        self.reset = Some(pin);
+        self
+    }
+
+    /// Capture every frame exchanged with the PN532, initialization
+    /// included, to the file at `path`; see [`capture`](crate::pn532::capture).
+    pub fn capture(mut self, path: impl Into<PathBuf>) -> Self {
+        self.capture = Some(path.into());
+        self
+    }
+
+    /// Replace all [`Options`] at once.
+    pub fn options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// SAMConfiguration applied during initialization, `None` to skip it.
+    pub fn sam_configuration(mut self, sam: Option<SamConfiguration>) -> Self {
+        self.options.sam = sam;
+        self
+    }
+
+    /// Whether to wait on the IRQ pin instead of polling the status byte.
+    pub fn use_irq(mut self, use_irq: bool) -> Self {
+        self.options.use_irq = use_irq;
+        self
+    }
+
+    /// How many more times to try reaching the PN532 during initialization.
+    pub fn init_retries(mut self, retries: u8) -> Self {
+        self.options.init_retries = retries;
+        self
+    }
+
+    /// Run a health check once the PN532 is initialized, failing the build
+    /// if it does not pass.
+    pub fn self_test(mut self, self_test: SelfTest) -> Self {
+        self.options.self_test = Some(self_test);
+        self
+    }
+
+    /// Limit the card activation retries of InListPassiveTarget, see
+    /// [`Options::passive_activation_retries`].
+    pub fn passive_activation_retries(mut self, retries: u8) -> Self {
+        self.options.passive_activation_retries = Some(retries);
+        self
+    }
+
+    /// Whether to pulse the reset pin before initializing.
+    pub fn reset_on_startup(mut self, reset: bool) -> Self {
+        self.options.reset_on_startup = reset;
+        self
+    }
+
+    /// Whether to pulse the reset pin before retrying a failed initialization.
+    pub fn reset_on_init_failure(mut self, reset: bool) -> Self {
+        self.options.reset_on_init_failure = reset;
+        self
+    }
+
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.options.timeouts = timeouts;
+        self
+    }
+
+    /// How commands are retried after a transient failure.
+    pub fn retries(mut self, retries: Retries) -> Self {
+        self.options.retries = retries;
+        self
+    }
+
+    /// Reset the PN532 when it stops acknowledging commands.
+    pub fn watchdog(mut self, watchdog: Watchdog) -> Self {
+        self.options.watchdog = Some(watchdog);
+        self
+    }
+
+    /// Reopen the bus and reinitialize the PN532 when a transfer fails.
+    pub fn recover_on_transport_error(mut self, recover: bool) -> Self {
+        self.options.recover_on_transport_error = recover;
+        self
+    }
+
+    /// Refuse every operation that writes to a tag.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.options.read_only = read_only;
+        self
+    }
+
+    /// Read the GPIO ports before every single pin write, see
+    /// [`Options::gpio_force_refresh`].
+    pub fn gpio_force_refresh(mut self, force_refresh: bool) -> Self {
+        self.options.gpio_force_refresh = force_refresh;
+        self
+    }
+
+    /// Record the bytes sent and the raw response frame in errors.
+    pub fn error_context(mut self, error_context: bool) -> Self {
+        self.options.error_context = error_context;
+        self
+    }
+
+    /// Whether dropping the reader releases the targets and switches the RF
+    /// field off, and whether it also powers the PN532 down.
+    pub fn cleanup_on_drop(mut self, cleanup: bool, power_down: bool) -> Self {
+        self.options.cleanup_on_drop = cleanup;
+        self.options.power_down_on_drop = power_down;
+        self
+    }
+
+    /// Open the bus and initialize the PN532.
+    pub fn build(self) -> crate::pn532::Result<PN532Spi> {
+        let spi = SpiDevice::with_bus(open_bus(self.bus)?, self.bus, self.cs)?;
+        PN532Spi::from_device(spi, self)
+    }
+
+    /// Initialize the PN532 on `bus`, the shared handle to `bus_id`.
+    pub(crate) fn build_on(self, bus: SharedBus, bus_id: Bus) -> crate::pn532::Result<PN532Spi> {
+        let spi = SpiDevice::with_bus(bus, bus_id, self.cs)?;
+        PN532Spi::from_device(spi, self)
+    }
+}
+
+impl Default for PN532SpiBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transport for PN532Spi {
+    fn gpio_init(&mut self) -> crate::pn532::Result<()> {
         if let Some(pin) = self.reset {
             self.spi.gpio.get(pin)?.into_output_high();
         }
@@ -128,69 +574,182 @@ impl PN532 for PN532Spi {
         Ok(())
     }
 
-    fn reset(&self, pin: u8) -> crate::pn532::Result<()> {
+    fn reset(&mut self, pin: u8) -> crate::pn532::Result<()> {
+        self.gpio_cache.clear();
+        self.auth_cache.clear();
         let mut pin = self.spi.gpio.get(pin)?.into_output_high();
-        thread::sleep(Duration::from_millis(100));
+        self.sleep(Duration::from_millis(100));
         pin.set_low();
-        thread::sleep(Duration::from_millis(500));
+        self.sleep(Duration::from_millis(500));
         pin.set_high();
-        thread::sleep(Duration::from_millis(100));
+        self.sleep(Duration::from_millis(100));
 
         Ok(())
     }
 
-    fn read_data(&mut self, len: usize) -> crate::pn532::Result<Vec<u8>> {
-        let mut write_buf = vec![0x00; len];
-        let mut read_buf = vec![0x00; len];
-        write_buf[0] = SPI_DATAREAD.reverse_bits();
-        thread::sleep(Duration::from_millis(5));
+    fn reset_pin(&self) -> Option<u8> {
+        self.reset
+    }
+
+    fn reopen(&mut self) -> crate::pn532::Result<()> {
+        self.spi.reopen()
+    }
+
+    fn read_data(&mut self, buf: &mut [u8]) -> crate::pn532::Result<usize> {
+        // The first byte clocked out answers the DATAREAD command itself.
+        self.tx.clear();
+        self.tx.resize(buf.len() + 1, 0x00);
+        self.tx[0] = SPI_DATAREAD.reverse_bits();
+        self.rx.clear();
+        self.rx.resize(buf.len() + 1, 0x00);
+
+        let read = self.timed_transfer(|this| this.spi.transfer(&mut this.rx, &this.tx))?;
+
+        Ok(self.received(buf, read))
+    }
 
-        self.spi.transfer(read_buf.as_mut_slice(), &write_buf)?;
+    fn read_frame_data(&mut self, buf: &mut [u8]) -> crate::pn532::Result<usize> {
+        // Without a GPIO chip select the kernel releases NSS between
+        // transfers, so the frame has to be read in a single one.
+        if self.cs.is_none() || buf.len() < FRAME_HEADER_LEN {
+            return self.read_data(buf);
+        }
 
-        let read_buf: Vec<u8> = read_buf.into_iter().map(u8::reverse_bits).collect();
-        debug!("Reading: {:?}", read_buf);
+        self.tx.clear();
+        self.tx.resize(buf.len() + 1, 0x00);
+        self.tx[0] = SPI_DATAREAD.reverse_bits();
+        self.rx.clear();
+        self.rx.resize(buf.len() + 1, 0x00);
+
+        let read = self.timed_transfer(|this| this.spi.transaction(|spi| {
+            let (tx, rx) = (&this.tx, &mut this.rx);
+            // DATAREAD, then preamble, start code, LEN and LCS.
+            let header = 1 + FRAME_HEADER_LEN;
+            let mut read = spi.transfer(&mut rx[..header], &tx[..header])?;
+            if read < header {
+                return Ok(read);
+            }
 
-        Ok(read_buf[1..].to_owned())
+            let [preamble, start1, start2, len, lcs] = [rx[1], rx[2], rx[3], rx[4], rx[5]].map(u8::reverse_bits);
+            let mut header = header;
+            let data_len = match [preamble, start1, start2, len, lcs] {
+                [0x00, 0x00, 0xFF, 0xFF, 0xFF] if tx.len() >= header + EXTENDED_HEADER_LEN => {
+                    // Extended frame: LENM, LENL and LCS follow.
+                    let extended = header + EXTENDED_HEADER_LEN;
+                    read += spi.transfer(&mut rx[header..extended], &tx[header..extended])?;
+                    if read < extended {
+                        return Ok(read);
+                    }
+                    header = extended;
+                    let [lenm, lenl, lcs] = [rx[6], rx[7], rx[8]].map(u8::reverse_bits);
+                    (lenm.wrapping_add(lenl).wrapping_add(lcs) == 0).then(|| u16::from_be_bytes([lenm, lenl]) as usize)
+                }
+                [0x00, 0x00, 0xFF, len, lcs] if len.wrapping_add(lcs) == 0 => Some(len as usize),
+                _ => None,
+            };
+            let rest = match data_len {
+                // Data, DCS and postamble.
+                Some(len) => (len + 2).min(tx.len() - header),
+                // Not a plain frame header, leave the parsing to the caller.
+                None => tx.len() - header,
+            };
+            read += spi.transfer(&mut rx[header..header + rest], &tx[header..header + rest])?;
+
+            Ok(read)
+        }))?;
+
+        Ok(self.received(buf, read))
     }
 
     fn write_data(&mut self, frame: &[u8]) -> crate::pn532::Result<()> {
-        let mut write_buf = vec![SPI_DATAWRITE];
-        write_buf.extend_from_slice(frame);
-        let write_buf: Vec<u8> = write_buf.into_iter().map(u8::reverse_bits).collect();
-        debug!("Writing: {:?}", write_buf);
-        thread::sleep(Duration::from_millis(20));
+        self.capture(Direction::HostToPn532, frame);
+        self.tx.clear();
+        self.tx.push(SPI_DATAWRITE.reverse_bits());
+        self.tx.extend(frame.iter().map(|byte| byte.reverse_bits()));
+        debug!("Writing: {:?}", self.tx);
+        // Normal information frame: 00 00 FF LEN LCS D4 command ...
+        if let [0x00, 0x00, 0xFF, _, _, 0xD4, command, ..] = *frame {
+            self.stats.start_command(command);
+        }
+
+        self.timed_transfer(|this| this.spi.write(&this.tx)).map(|_| ())
+    }
 
-        self.spi.write(&write_buf).map(|_| ())
+    fn wait_ready(&mut self, timeout: Duration) -> crate::pn532::Result<bool> {
+        let start = Instant::now();
+        let ready = if let (Some(irq), true) = (self.irq, self.options.use_irq) {
+            self.wait_irq(irq, timeout)
+        } else {
+            self.poll_ready(timeout)
+        };
+        self.stats.record_wait_ready(start.elapsed());
+
+        ready
     }
 
-    fn wait_ready(&mut self, timeout: f64) -> crate::pn532::Result<bool> {
-        let mut write_buf = [SPI_STATREAD.reverse_bits(), 0x00];
+    fn is_ready(&mut self) -> crate::pn532::Result<bool> {
+        if let (Some(irq), true) = (self.irq, self.options.use_irq) {
+            return Ok(self.spi.gpio.get(irq)?.into_input().is_low());
+        }
+
+        let write_buf = [SPI_STATREAD.reverse_bits(), 0x00];
         let mut read_buf = [0; 2];
-        let timestamp = Instant::now();
-        while timestamp.elapsed() < Duration::from_secs_f64(timeout) {
-            thread::sleep(Duration::from_millis(10));
+        self.timed_transfer(|this| this.spi.transfer(&mut read_buf, &write_buf))?;
 
-            self.spi.transfer(&mut read_buf,&write_buf)?;
-            if read_buf[1].reverse_bits() == SPI_READY {
+        Ok(read_buf[1].reverse_bits() == SPI_READY)
+    }
+
+    fn wake_up(&mut self) -> crate::pn532::Result<()> {
+        // Asserting NSS wakes the PN532 up; its oscillator then needs about
+        // 2 ms to start before it can take a command.
+        let start = Instant::now();
+        self.spi.transaction(|spi| {
+            thread::sleep(WAKEUP_DELAY);
+            spi.write(&[0x55_u8.reverse_bits(); 2])
+        })?;
+        self.stats.record_sleep(WAKEUP_DELAY);
+        self.stats.record_transfer(start.elapsed().saturating_sub(WAKEUP_DELAY));
+        self.sleep(WAKEUP_DELAY);
+
+        Ok(())
+    }
+}
+
+impl PN532Spi {
+    /// Poll the status byte until the PN532 is ready or `timeout` expires.
+    fn poll_ready(&mut self, timeout: Duration) -> crate::pn532::Result<bool> {
+        let timestamp = Instant::now();
+        let mut interval = FAST_POLL_INTERVAL;
+        loop {
+            if self.is_ready()? {
                 return Ok(true);
-            } else {
-                thread::sleep(Duration::from_millis(5))
             }
-            write_buf.copy_from_slice(&read_buf);
+            if self.canceller.take() {
+                return Err(Error::Cancelled);
+            }
+            let elapsed = timestamp.elapsed();
+            if elapsed >= timeout {
+                return Ok(false);
+            }
+            if elapsed >= FAST_POLL_WINDOW {
+                interval = (interval * 3 / 2).clamp(SLOW_POLL_MIN, SLOW_POLL_MAX);
+            }
+            self.sleep(interval.min(timeout - elapsed));
         }
+    }
 
-        Ok(false)
+    fn sleep(&mut self, duration: Duration) {
+        let start = Instant::now();
+        thread::sleep(duration);
+        self.stats.record_sleep(start.elapsed());
     }
 
-    fn wake_up(&mut self) -> crate::pn532::Result<()> {
-        thread::sleep(Duration::from_secs(1));
-        if let Some(pin) = self.cs {
-            self.spi.gpio.get(pin)?.into_output_low();
-        }
-        thread::sleep(Duration::from_millis(2));
-        self.spi.write(&[0x00])?;
-        thread::sleep(Duration::from_secs(1));
+    /// Run the bus transfer `f`, counting its time in [`PN532Spi::stats`].
+    fn timed_transfer<R>(&mut self, f: impl FnOnce(&mut Self) -> crate::pn532::Result<R>) -> crate::pn532::Result<R> {
+        let start = Instant::now();
+        let ret = f(self);
+        self.stats.record_transfer(start.elapsed());
 
-        Ok(())
+        ret
     }
-}
\ No newline at end of file
+}
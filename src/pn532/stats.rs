@@ -0,0 +1,143 @@
+//! Timing counters of the transport, to measure where a command's time goes.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::pn532::{Command, Status};
+
+/// Time spent by the transport on behalf of one command.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CommandStats {
+    /// Number of times the command was sent.
+    pub count: u32,
+    /// Time spent in bus transfers, including waiting for a shared bus.
+    pub transfer_time: Duration,
+    /// Time spent waiting for the PN532 to be ready, including the status
+    /// polls and the sleeps between them.
+    pub wait_ready_time: Duration,
+    /// Time spent sleeping.
+    pub sleep_time: Duration,
+}
+
+impl CommandStats {
+    fn add(&mut self, other: &CommandStats) {
+        self.count += other.count;
+        self.transfer_time += other.transfer_time;
+        self.wait_ready_time += other.wait_ready_time;
+        self.sleep_time += other.sleep_time;
+    }
+}
+
+/// Link health counters of a reader.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Counters {
+    /// Command frames written.
+    pub commands_sent: u64,
+    /// Commands the PN532 did not acknowledge in time.
+    pub ack_timeouts: u64,
+    /// Commands answered with something else than an ACK frame.
+    pub ack_errors: u64,
+    /// Response frames received corrupted, each NACKed or failing.
+    pub checksum_errors: u64,
+    /// Resets of the PN532 by the [`Watchdog`](crate::pn532::Watchdog).
+    pub watchdog_resets: u64,
+    /// Successful [recoveries](crate::pn532::PN532::recover), watchdog
+    /// resets included.
+    pub recoveries: u64,
+    /// Commands tried again under their retry policy.
+    pub retries: u64,
+    /// Errors reported by the PN532 in status bytes, by status.
+    pub chip_errors: HashMap<Status, u64>,
+    /// Frame bytes written, ACK and NACK frames included.
+    pub bytes_sent: u64,
+    /// Frame bytes read.
+    pub bytes_received: u64,
+}
+
+/// Per command timing counters of a reader, and its [`Counters`].
+///
+/// Transport time is attributed to the last command sent, so a wake up after
+/// a failed write counts towards the command that failed.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    commands: HashMap<u8, CommandStats>,
+    other: CommandStats,
+    current: Option<u8>,
+    counters: Counters,
+    pub(crate) consecutive_ack_timeouts: u32,
+    pub(crate) recovering: bool,
+    /// The PN532 was put into power down and needs a wake up before the
+    /// next command.
+    pub(crate) asleep: bool,
+}
+
+impl Stats {
+    /// Counters of `command`, if it was sent.
+    pub fn command(&self, command: Command) -> Option<&CommandStats> {
+        self.commands.get(&command.code())
+    }
+
+    /// Counters of every command sent, by command code.
+    pub fn commands(&self) -> impl Iterator<Item = (u8, &CommandStats)> {
+        self.commands.iter().map(|(&code, stats)| (code, stats))
+    }
+
+    /// Transport time outside of any command, such as the reset and wake up
+    /// during initialization.
+    pub fn other(&self) -> &CommandStats {
+        &self.other
+    }
+
+    /// Counters summed over all commands and [`Stats::other`].
+    pub fn total(&self) -> CommandStats {
+        let mut total = self.other;
+        for stats in self.commands.values() {
+            total.add(stats);
+        }
+        total
+    }
+
+    /// Link health counters.
+    pub fn counters(&self) -> &Counters {
+        &self.counters
+    }
+
+    pub(crate) fn counters_mut(&mut self) -> &mut Counters {
+        &mut self.counters
+    }
+
+    /// Commands in a row the PN532 did not acknowledge, up to now.
+    pub fn consecutive_ack_timeouts(&self) -> u32 {
+        self.consecutive_ack_timeouts
+    }
+
+    pub(crate) fn record_ack_timeout(&mut self) {
+        self.counters.ack_timeouts += 1;
+        self.consecutive_ack_timeouts += 1;
+    }
+
+    /// Start attributing time to the command with code `code`.
+    pub(crate) fn start_command(&mut self, code: u8) {
+        self.current = Some(code);
+        self.current_mut().count += 1;
+    }
+
+    pub(crate) fn record_transfer(&mut self, time: Duration) {
+        self.current_mut().transfer_time += time;
+    }
+
+    pub(crate) fn record_wait_ready(&mut self, time: Duration) {
+        self.current_mut().wait_ready_time += time;
+    }
+
+    pub(crate) fn record_sleep(&mut self, time: Duration) {
+        self.current_mut().sleep_time += time;
+    }
+
+    fn current_mut(&mut self) -> &mut CommandStats {
+        match self.current {
+            Some(code) => self.commands.entry(code).or_default(),
+            None => &mut self.other,
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt;
+
+/// Error code reported by the PN532 in the status byte of a response
+/// (UM0701-02 §7.1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Status {
+    /// 0x01: the target has not answered in time.
+    Timeout,
+    /// 0x02: CRC error detected by the CIU.
+    Crc,
+    /// 0x03: parity error detected by the CIU.
+    Parity,
+    /// 0x04: erroneous bit count during anti-collision or select.
+    CollisionBitCount,
+    /// 0x05: framing error during a MIFARE operation.
+    MifareFraming,
+    /// 0x06: abnormal bit collision during bitwise anti-collision at 106 kbps.
+    CollisionBitCollision,
+    /// 0x07: communication buffer size insufficient.
+    NoBufs,
+    /// 0x09: RF buffer overflow detected by the CIU.
+    RfNoBufs,
+    /// 0x0A: the RF field has not been switched on in time in active mode.
+    ActiveTooSlow,
+    /// 0x0B: RF protocol error.
+    RfProtocol,
+    /// 0x0D: the antenna drivers were switched off because of overheating.
+    TooHot,
+    /// 0x0E: internal buffer overflow.
+    InternalNoBufs,
+    /// 0x10: invalid parameter.
+    InvalidParameter,
+    /// 0x12: DEP command received that the PN532 does not support.
+    DepInvalidCommand,
+    /// 0x13: DEP, ISO/IEC 14443-4 or FeliCa data format does not match the
+    /// specification.
+    DepBadData,
+    /// 0x14: MIFARE authentication error.
+    MifareAuth,
+    /// 0x18: the target does not support secure communication.
+    NoSecure,
+    /// 0x19: the I2C bus is busy.
+    I2cBusy,
+    /// 0x23: ISO/IEC 14443-3 UID check byte is wrong.
+    UidChecksum,
+    /// 0x25: invalid device state, the command is not allowed.
+    DepState,
+    /// 0x26: operation not allowed in this configuration (host controller
+    /// interface).
+    HciInvalid,
+    /// 0x27: command not acceptable in the current context.
+    Context,
+    /// 0x29: the target has been released by the initiator.
+    Released,
+    /// 0x2A: the ID of the card does not match, the card has been swapped.
+    CardSwapped,
+    /// 0x2B: the previously activated card has disappeared.
+    NoCard,
+    /// 0x2C: mismatch between the NFCID3 of the initiator and the target.
+    Mismatch,
+    /// 0x2D: an over-current event has been detected.
+    OverCurrent,
+    /// 0x2E: NAD missing in the DEP frame.
+    NoNad,
+    /// A code not documented in UM0701-02.
+    Unknown(u8),
+}
+
+impl Status {
+    /// The raw status code.
+    pub fn code(self) -> u8 {
+        match self {
+            Status::Timeout => 0x01,
+            Status::Crc => 0x02,
+            Status::Parity => 0x03,
+            Status::CollisionBitCount => 0x04,
+            Status::MifareFraming => 0x05,
+            Status::CollisionBitCollision => 0x06,
+            Status::NoBufs => 0x07,
+            Status::RfNoBufs => 0x09,
+            Status::ActiveTooSlow => 0x0A,
+            Status::RfProtocol => 0x0B,
+            Status::TooHot => 0x0D,
+            Status::InternalNoBufs => 0x0E,
+            Status::InvalidParameter => 0x10,
+            Status::DepInvalidCommand => 0x12,
+            Status::DepBadData => 0x13,
+            Status::MifareAuth => 0x14,
+            Status::NoSecure => 0x18,
+            Status::I2cBusy => 0x19,
+            Status::UidChecksum => 0x23,
+            Status::DepState => 0x25,
+            Status::HciInvalid => 0x26,
+            Status::Context => 0x27,
+            Status::Released => 0x29,
+            Status::CardSwapped => 0x2A,
+            Status::NoCard => 0x2B,
+            Status::Mismatch => 0x2C,
+            Status::OverCurrent => 0x2D,
+            Status::NoNad => 0x2E,
+            Status::Unknown(code) => code,
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Status::Timeout => "target did not answer in time",
+            Status::Crc => "CRC error",
+            Status::Parity => "parity error",
+            Status::CollisionBitCount => "erroneous bit count during anti-collision",
+            Status::MifareFraming => "framing error during MIFARE operation",
+            Status::CollisionBitCollision => "abnormal bit collision during anti-collision",
+            Status::NoBufs => "communication buffer size insufficient",
+            Status::RfNoBufs => "RF buffer overflow",
+            Status::ActiveTooSlow => "RF field not switched on in time",
+            Status::RfProtocol => "RF protocol error",
+            Status::TooHot => "antenna drivers overheated",
+            Status::InternalNoBufs => "internal buffer overflow",
+            Status::InvalidParameter => "invalid parameter",
+            Status::DepInvalidCommand => "unsupported DEP command",
+            Status::DepBadData => "data format does not match the specification",
+            Status::MifareAuth => "MIFARE authentication error",
+            Status::NoSecure => "secure communication not supported",
+            Status::I2cBusy => "I2C bus busy",
+            Status::UidChecksum => "wrong UID check byte",
+            Status::DepState => "invalid device state",
+            Status::HciInvalid => "operation not allowed in this configuration",
+            Status::Context => "command not acceptable in the current context",
+            Status::Released => "target released by the initiator",
+            Status::CardSwapped => "card swapped",
+            Status::NoCard => "activated card disappeared",
+            Status::Mismatch => "NFCID3 mismatch",
+            Status::OverCurrent => "over-current detected",
+            Status::NoNad => "NAD missing in DEP frame",
+            Status::Unknown(_) => "unknown error",
+        }
+    }
+}
+
+impl From<u8> for Status {
+    fn from(code: u8) -> Self {
+        match code {
+            0x01 => Status::Timeout,
+            0x02 => Status::Crc,
+            0x03 => Status::Parity,
+            0x04 => Status::CollisionBitCount,
+            0x05 => Status::MifareFraming,
+            0x06 => Status::CollisionBitCollision,
+            0x07 => Status::NoBufs,
+            0x09 => Status::RfNoBufs,
+            0x0A => Status::ActiveTooSlow,
+            0x0B => Status::RfProtocol,
+            0x0D => Status::TooHot,
+            0x0E => Status::InternalNoBufs,
+            0x10 => Status::InvalidParameter,
+            0x12 => Status::DepInvalidCommand,
+            0x13 => Status::DepBadData,
+            0x14 => Status::MifareAuth,
+            0x18 => Status::NoSecure,
+            0x19 => Status::I2cBusy,
+            0x23 => Status::UidChecksum,
+            0x25 => Status::DepState,
+            0x26 => Status::HciInvalid,
+            0x27 => Status::Context,
+            0x29 => Status::Released,
+            0x2A => Status::CardSwapped,
+            0x2B => Status::NoCard,
+            0x2C => Status::Mismatch,
+            0x2D => Status::OverCurrent,
+            0x2E => Status::NoNad,
+            code => Status::Unknown(code),
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (status {:#04x})", self.description(), self.code())
+    }
+}
@@ -0,0 +1,264 @@
+use std::fmt;
+
+use crate::pn532::command::InListPassiveTarget;
+use crate::pn532::{Ats, Error, NtagVariant, Result, Target, Uid};
+
+/// An ISO/IEC 14443 type A target activated by InListPassiveTarget at
+/// 106 kbps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassiveTarget {
+    /// Logical number the PN532 assigned to the target.
+    pub target_number: u8,
+    /// ATQA (SENS_RES), as sent by the card.
+    pub atqa: [u8; 2],
+    /// SAK (SEL_RES).
+    pub sak: u8,
+    pub uid: Uid,
+    /// ATS, starting with its length byte TL, if the card is ISO/IEC 14443-4
+    /// compliant.
+    pub ats: Option<Vec<u8>>,
+}
+
+impl PassiveTarget {
+    /// Whether the card supports ISO/IEC 14443-4 (ISO-DEP), according to SAK.
+    pub fn is_iso14443_4(&self) -> bool {
+        self.sak & 0x20 != 0
+    }
+
+    /// The ATS parsed, if the card sent one.
+    pub fn parse_ats(&self) -> Result<Option<Ats>> {
+        self.ats.as_deref().map(Ats::from_bytes).transpose()
+    }
+
+    /// Number of sectors if the card is a MIFARE Classic, according to SAK:
+    /// 5 for a Mini, 16 for a 1K, 32 for a 2K and 40 for a 4K.
+    pub fn classic_sectors(&self) -> Option<u8> {
+        match self.sak {
+            0x09 => Some(5),
+            0x08 | 0x28 | 0x88 => Some(16),
+            0x19 => Some(32),
+            0x18 | 0x38 | 0x98 => Some(40),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<&Target> for PassiveTarget {
+    type Error = Error;
+
+    /// Parse the target data of a 106 kbps type A target:
+    /// SENS_RES (2), SEL_RES, NFCIDLength, NFCID1, [ATS].
+    fn try_from(target: &Target) -> Result<Self> {
+        let data = &target.data;
+        let too_short = || Error::UnexpectedResponse("Type A target data is too short!");
+        let (atqa, sak, uid_len) = match data[..] {
+            [atqa0, atqa1, sak, uid_len, ..] => ([atqa0, atqa1], sak, uid_len as usize),
+            _ => return Err(too_short()),
+        };
+        let uid = data.get(4..4 + uid_len).ok_or_else(too_short)?;
+        let uid = Uid::try_from(uid)
+            .map_err(|_| Error::UnexpectedResponse("Found card with unexpected UID length!"))?;
+        let ats = match data.get(4 + uid_len..) {
+            Some(ats) if !ats.is_empty() => Some(ats.to_owned()),
+            _ => None,
+        };
+
+        Ok(PassiveTarget {
+            target_number: target.number,
+            atqa,
+            sak,
+            uid,
+            ats,
+        })
+    }
+}
+
+/// An ISO/IEC 14443 type B target activated by InListPassiveTarget at
+/// 106 kbps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeBTarget {
+    /// Logical number the PN532 assigned to the target.
+    pub target_number: u8,
+    /// Pseudo-Unique PICC Identifier.
+    pub pupi: [u8; 4],
+    /// Application data of the ATQB.
+    pub application_data: [u8; 4],
+    /// Protocol info of the ATQB: bit rates, frame size, protocol type and
+    /// frame waiting time.
+    pub protocol_info: [u8; 3],
+    /// ATTRIB response of the card.
+    pub attrib_res: Vec<u8>,
+}
+
+impl TypeBTarget {
+    /// Whether the card supports ISO/IEC 14443-4, according to its protocol
+    /// type.
+    pub fn is_iso14443_4(&self) -> bool {
+        self.protocol_info[1] & 0x01 != 0
+    }
+}
+
+impl TryFrom<&Target> for TypeBTarget {
+    type Error = Error;
+
+    /// Parse the target data of a 106 kbps type B target:
+    /// ATQB (12), ATTRIB_RES length, ATTRIB_RES.
+    fn try_from(target: &Target) -> Result<Self> {
+        let data = &target.data;
+        let [0x50, p0, p1, p2, p3, a0, a1, a2, a3, i0, i1, i2, attrib_len, ref attrib_res @ ..] = data[..] else {
+            return Err(Error::UnexpectedResponse("Type B target data is not an ATQB!"));
+        };
+        let attrib_res = attrib_res.get(..attrib_len as usize)
+            .ok_or(Error::UnexpectedResponse("Type B target data is too short!"))?;
+
+        Ok(TypeBTarget {
+            target_number: target.number,
+            pupi: [p0, p1, p2, p3],
+            application_data: [a0, a1, a2, a3],
+            protocol_info: [i0, i1, i2],
+            attrib_res: attrib_res.to_owned(),
+        })
+    }
+}
+
+/// A FeliCa target found by InListPassiveTarget at 212 or 424 kbps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FelicaTarget {
+    /// Logical number the PN532 assigned to the target.
+    pub target_number: u8,
+    /// Manufacture ID.
+    pub idm: [u8; 8],
+    /// Manufacture parameter.
+    pub pmm: [u8; 8],
+    /// System code, if the polling asked for it.
+    pub system_code: Option<u16>,
+}
+
+impl TryFrom<&Target> for FelicaTarget {
+    type Error = Error;
+
+    /// Parse the target data of a FeliCa target: POL_RES length, response
+    /// code 0x01, IDm (8), PMm (8), [system code (2)].
+    fn try_from(target: &Target) -> Result<Self> {
+        let data = &target.data;
+        if data.len() < 18 || data[1] != 0x01 {
+            return Err(Error::UnexpectedResponse("FeliCa target data is not a POL_RES!"));
+        }
+        let mut idm = [0; 8];
+        idm.copy_from_slice(&data[2..10]);
+        let mut pmm = [0; 8];
+        pmm.copy_from_slice(&data[10..18]);
+        let system_code = data.get(18..20).map(|code| u16::from_be_bytes([code[0], code[1]]));
+
+        Ok(FelicaTarget {
+            target_number: target.number,
+            idm,
+            pmm,
+            system_code,
+        })
+    }
+}
+
+/// An NFCIP-1 target activated by InJumpForDEP, ready for DEP exchanges.
+/// The fields after the target number are its ATR_RES.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepTarget {
+    /// Logical number the PN532 assigned to the target, always 1.
+    pub target_number: u8,
+    /// Random identifier of the target.
+    pub nfcid3t: [u8; 10],
+    /// DID byte of the target.
+    pub did: u8,
+    /// Send bit rates supported by the target (BSt).
+    pub send_bit_rates: u8,
+    /// Receive bit rates supported by the target (BRt).
+    pub receive_bit_rates: u8,
+    /// Timeout value of the target (TO).
+    pub timeout: u8,
+    /// Optional parameters of the target (PPt): length reduction, NAD and
+    /// general bytes.
+    pub optional_parameters: u8,
+    /// General bytes of the target, e.g. the LLCP parameters of a phone.
+    pub general_bytes: Vec<u8>,
+}
+
+/// A passive target detection started by
+/// [`PN532::start_passive_detection`](crate::pn532::PN532::start_passive_detection),
+/// whose result is collected with
+/// [`PN532::poll_result`](crate::pn532::PN532::poll_result).
+#[derive(Debug)]
+#[must_use = "the PN532 accepts no other command until the detection is polled to completion or aborted"]
+pub struct PassiveDetection {
+    pub(crate) request: InListPassiveTarget,
+}
+
+/// Tag technology, as told by
+/// [`PN532::identify_tag`](crate::pn532::PN532::identify_tag).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TagKind {
+    MifareClassic1K,
+    MifareClassic4K,
+    /// MIFARE Ultralight or Ultralight C, which do not answer GET_VERSION.
+    Ultralight,
+    UltralightEv1,
+    Ntag213,
+    Ntag215,
+    Ntag216,
+    MifareDesfire,
+    /// Any other ISO/IEC 14443-4 card.
+    Iso14443_4,
+    Felica,
+    Unknown,
+}
+
+impl TagKind {
+    /// The kind SAK and ATQA tell, `None` for a Type 2 tag, which only
+    /// GET_VERSION tells apart.
+    pub(crate) fn from_activation(target: &PassiveTarget) -> Option<Self> {
+        match (target.sak, target.atqa) {
+            (0x00, _) => None,
+            (0x08 | 0x28 | 0x88, _) => Some(TagKind::MifareClassic1K),
+            (0x18 | 0x38 | 0x98, _) => Some(TagKind::MifareClassic4K),
+            (0x20, [0x03, 0x44]) => Some(TagKind::MifareDesfire),
+            _ if target.is_iso14443_4() && target.ats.is_some() => Some(TagKind::Iso14443_4),
+            _ => Some(TagKind::Unknown),
+        }
+    }
+}
+
+impl From<NtagVariant> for TagKind {
+    fn from(variant: NtagVariant) -> Self {
+        match variant {
+            NtagVariant::Ntag213 => TagKind::Ntag213,
+            NtagVariant::Ntag215 => TagKind::Ntag215,
+            NtagVariant::Ntag216 => TagKind::Ntag216,
+            NtagVariant::UltralightEv1Mf0ul11 | NtagVariant::UltralightEv1Mf0ul21 => TagKind::UltralightEv1,
+            NtagVariant::Unknown => TagKind::Unknown,
+        }
+    }
+}
+
+impl From<&FelicaTarget> for TagKind {
+    fn from(_: &FelicaTarget) -> Self {
+        TagKind::Felica
+    }
+}
+
+impl fmt::Display for TagKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            TagKind::MifareClassic1K => "MIFARE Classic 1K",
+            TagKind::MifareClassic4K => "MIFARE Classic 4K",
+            TagKind::Ultralight => "MIFARE Ultralight",
+            TagKind::UltralightEv1 => "MIFARE Ultralight EV1",
+            TagKind::Ntag213 => "NTAG213",
+            TagKind::Ntag215 => "NTAG215",
+            TagKind::Ntag216 => "NTAG216",
+            TagKind::MifareDesfire => "MIFARE DESFire",
+            TagKind::Iso14443_4 => "ISO/IEC 14443-4 card",
+            TagKind::Felica => "FeliCa",
+            TagKind::Unknown => "unknown tag",
+        })
+    }
+}
@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::pn532::Command;
+
+/// How long the reader waits for the PN532 at each step of a command.
+///
+/// The response timeout can be overridden per command, e.g. to give
+/// `InDataExchange` more time with slow tags or long-range antennas.
+#[derive(Debug, Clone)]
+pub struct Timeouts {
+    /// Time allowed for the ACK frame once a command has been written.
+    pub ack: Duration,
+    /// Time allowed for the response once the command has been acknowledged.
+    pub response: Duration,
+    overrides: HashMap<Command, Duration>,
+}
+
+impl Timeouts {
+    /// Use `timeout` as the response timeout of `command`.
+    pub fn with_override(mut self, command: Command, timeout: Duration) -> Self {
+        self.set_override(command, timeout);
+        self
+    }
+
+    /// Use `timeout` as the response timeout of `command`.
+    pub fn set_override(&mut self, command: Command, timeout: Duration) {
+        self.overrides.insert(command, timeout);
+    }
+
+    /// Go back to the default response timeout for `command`.
+    pub fn clear_override(&mut self, command: Command) {
+        self.overrides.remove(&command);
+    }
+
+    /// The response timeout used for `command`.
+    pub fn response_for(&self, command: Command) -> Duration {
+        self.overrides.get(&command).copied().unwrap_or(self.response)
+    }
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            ack: Duration::from_secs(1),
+            response: Duration::from_secs(1),
+            overrides: HashMap::new(),
+        }.with_override(Command::GetFirmwareVersion, Duration::from_millis(500))
+    }
+}
@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use crate::pn532::{Error, Result};
+
+/// The byte-level link between the host and a PN532 (SPI, I2C, UART, ...).
+///
+/// Implementations only move bytes and manage the control pins; framing is
+/// done by the [`PN532`](crate::pn532::PN532) trait on top of it.
+pub trait Transport {
+    /// Configure the GPIO pins used to drive the PN532.
+    fn gpio_init(&mut self) -> Result<()>;
+
+    /// Pulse the PN532's reset pin.
+    fn reset(&mut self, pin: u8) -> Result<()>;
+
+    /// Close and open the underlying device again after an I/O failure.  The
+    /// default does nothing.
+    fn reopen(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Whether [`set_baud_rate`](Transport::set_baud_rate) can switch this
+    /// transport.  The default is `false`; serial links that implement
+    /// `set_baud_rate` return `true`.
+    fn supports_baud_rate(&self) -> bool {
+        false
+    }
+
+    /// Switch the host side of a serial link to `bits_per_second`.  The
+    /// default fails, for transports that are not serial links.
+    fn set_baud_rate(&mut self, bits_per_second: u32) -> Result<()> {
+        Err(Error::InvalidArgument(format!("cannot switch this transport to {} baud", bits_per_second)))
+    }
+
+    /// The pin wired to the PN532's reset input, if any.
+    fn reset_pin(&self) -> Option<u8> {
+        None
+    }
+
+    /// Read up to `buf.len()` bytes of output from the PN532 into `buf`.
+    /// Returns the number of bytes actually read, which may be less than
+    /// requested; callers must only look at `buf[..n]`.
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// Read a complete response frame into `buf`, returning its length.
+    /// `buf` should hold the largest frame expected.
+    ///
+    /// Transports that can keep a read going read the frame header first and
+    /// then exactly the rest of the frame announced by its LEN byte; the
+    /// default reads `buf.len()` bytes, which only over-reads.
+    fn read_frame_data(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.read_data(buf)
+    }
+
+    /// Write a complete frame to the PN532.
+    fn write_data(&mut self, frame: &[u8]) -> Result<()>;
+
+    /// Wait up to `timeout` for the PN532 to have a response ready. Returns
+    /// `false` if it did not become ready in time.
+    fn wait_ready(&mut self, timeout: Duration) -> Result<bool>;
+
+    /// Whether the PN532 has a response ready, without waiting.
+    fn is_ready(&mut self) -> Result<bool> {
+        self.wait_ready(Duration::ZERO)
+    }
+
+    /// Bring the PN532 out of power down so it accepts commands.
+    fn wake_up(&mut self) -> Result<()>;
+}
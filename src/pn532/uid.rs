@@ -0,0 +1,163 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::pn532::Error;
+
+/// The UID (NFCID1) of an ISO/IEC 14443 type A card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Uid {
+    /// 4 byte single size UID, e.g. MIFARE Classic.
+    Single([u8; 4]),
+    /// 7 byte double size UID, e.g. NTAG21x and MIFARE Ultralight.
+    Double([u8; 7]),
+    /// 10 byte triple size UID.
+    Triple([u8; 10]),
+}
+
+impl Uid {
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Uid::Single(uid) => uid,
+            Uid::Double(uid) => uid,
+            Uid::Triple(uid) => uid,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.as_bytes().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl AsRef<[u8]> for Uid {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl From<[u8; 4]> for Uid {
+    fn from(uid: [u8; 4]) -> Self {
+        Uid::Single(uid)
+    }
+}
+
+impl From<[u8; 7]> for Uid {
+    fn from(uid: [u8; 7]) -> Self {
+        Uid::Double(uid)
+    }
+}
+
+impl From<[u8; 10]> for Uid {
+    fn from(uid: [u8; 10]) -> Self {
+        Uid::Triple(uid)
+    }
+}
+
+impl TryFrom<&[u8]> for Uid {
+    type Error = Error;
+
+    fn try_from(uid: &[u8]) -> Result<Self, Error> {
+        if let Ok(uid) = <[u8; 4]>::try_from(uid) {
+            Ok(Uid::Single(uid))
+        } else if let Ok(uid) = <[u8; 7]>::try_from(uid) {
+            Ok(Uid::Double(uid))
+        } else if let Ok(uid) = <[u8; 10]>::try_from(uid) {
+            Ok(Uid::Triple(uid))
+        } else {
+            Err(Error::InvalidArgument(format!("UID must be 4, 7 or 10 bytes long, got {}", uid.len())))
+        }
+    }
+}
+
+impl From<Uid> for Vec<u8> {
+    fn from(uid: Uid) -> Self {
+        uid.to_vec()
+    }
+}
+
+/// Formats the UID as colon separated hex bytes, e.g. `04:A2:3B:11:5C:80:00`.
+impl fmt::Display for Uid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (idx, byte) in self.as_bytes().iter().enumerate() {
+            if idx > 0 {
+                write!(f, ":")?;
+            }
+            write!(f, "{:02X}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses hex bytes, optionally separated by `:`, `-` or spaces.
+impl FromStr for Uid {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let digits: Vec<char> = s.chars().filter(|c| !matches!(c, ':' | '-' | ' ')).collect();
+        if digits.len() % 2 != 0 {
+            return Err(Error::InvalidArgument(format!("invalid UID {:?}: odd number of hex digits", s)));
+        }
+        let bytes = digits.chunks(2)
+            .map(|pair| {
+                let byte: String = pair.iter().collect();
+                // from_str_radix alone would take a sign, as in "+F".
+                u8::from_str_radix(&byte, 16).ok()
+                    .filter(|_| pair.iter().all(char::is_ascii_hexdigit))
+                    .ok_or_else(|| Error::InvalidArgument(format!("invalid UID {:?}: {:?} is not a hex byte", s, byte)))
+            })
+            .collect::<Result<Vec<u8>, Error>>()?;
+
+        Uid::try_from(bytes.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_colon_separated_hex() {
+        assert_eq!(Uid::from([0xDE, 0xAD, 0xBE, 0xEF]).to_string(), "DE:AD:BE:EF");
+        assert_eq!(Uid::from([0x04, 0xA2, 0x3B, 0x11, 0x5C, 0x80, 0x00]).to_string(), "04:A2:3B:11:5C:80:00");
+        assert_eq!(Uid::from([0x0A; 10]).to_string(), "0A:0A:0A:0A:0A:0A:0A:0A:0A:0A");
+    }
+
+    #[test]
+    fn parses_hex_with_any_separator() {
+        let uid = Uid::from([0xDE, 0xAD, 0xBE, 0xEF]);
+        for s in ["DE:AD:BE:EF", "de-ad-be-ef", "DE AD BE EF", "deadbeef", "De:aD BE-ef"] {
+            assert_eq!(s.parse::<Uid>().unwrap(), uid, "{:?}", s);
+        }
+        assert_eq!("04A23B115C8000".parse::<Uid>().unwrap(), Uid::Double([0x04, 0xA2, 0x3B, 0x11, 0x5C, 0x80, 0x00]));
+        assert_eq!("00112233445566778899".parse::<Uid>().unwrap().len(), 10);
+    }
+
+    #[test]
+    fn round_trips_through_strings() {
+        for uid in [Uid::from([0x01, 0x23, 0x45, 0x67]), Uid::from([0x89, 0xAB, 0xCD, 0xEF, 0x00, 0xFF, 0x10]), Uid::from([0x5A; 10])] {
+            assert_eq!(uid.to_string().parse::<Uid>().unwrap(), uid);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_lengths() {
+        for s in ["", "DE:AD:BE", "DE:AD:BE:EF:01", "00112233445566778899AA", "DEADBEE"] {
+            assert!(matches!(s.parse::<Uid>(), Err(Error::InvalidArgument(_))), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn rejects_non_hex_input() {
+        for s in ["DE:AD:BE:EG", "+F:AD:BE:EF", "DE:AD:BE:E\u{e9}", "0x:DE:AD:BE", "DE_AD_BE_EF"] {
+            assert!(matches!(s.parse::<Uid>(), Err(Error::InvalidArgument(_))), "{:?}", s);
+        }
+    }
+}
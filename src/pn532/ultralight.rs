@@ -0,0 +1,43 @@
+//! MIFARE Ultralight EV1 (MF0ULx1) commands the NTAG2xx helpers lack: the
+//! one-way counters and VCSL, plus the layout of its OTP page and static
+//! lock bytes.  [`TagVersion`](crate::pn532::TagVersion) tells it apart.
+
+use crate::pn532::{Error, Page, Result};
+
+pub(crate) const CMD_INCR_CNT: u8 =    0xA5;
+pub(crate) const CMD_VCSL: u8 =        0x4B;
+
+/// Number of one-way counters.
+pub const COUNTERS: u8 = 3;
+/// Largest counter value; counters are 24 bits and stop there.
+pub const COUNTER_MAX: u32 = 0x00FF_FFFF;
+
+/// Page holding the static lock bytes, in its bytes 2 and 3.
+pub const LOCK_PAGE: Page = Page(2);
+/// The one-time programmable page.
+pub const OTP_PAGE: Page = Page(3);
+
+/// Static lock byte 0 bit locking the OTP page (L-OTP).
+pub(crate) const LOCK_OTP: u8 = 0x08;
+
+/// The 4 bit ACK of a write-like command.
+const ACK: u8 = 0x0A;
+
+/// Check `counter` is one of the three.
+pub(crate) fn check_counter(counter: u8) -> Result<()> {
+    if counter < COUNTERS {
+        Ok(())
+    } else {
+        Err(Error::InvalidArgument(format!("Ultralight EV1 counters are 0 to {}, got {}", COUNTERS - 1, counter)))
+    }
+}
+
+/// Check the answer to a write-like command is an ACK.  The PN532 passes
+/// the 4 bit answer on as a byte, if at all.
+pub(crate) fn check_ack(answer: &[u8]) -> Result<()> {
+    match answer.first() {
+        None => Ok(()),
+        Some(&ack) if ack & 0x0F == ACK => Ok(()),
+        Some(_) => Err(Error::UnexpectedResponse("Ultralight EV1 tag answered NAK!")),
+    }
+}
@@ -0,0 +1,113 @@
+//! MIFARE Ultralight C 3DES authentication (AUTHENTICATE, 0x1A) and key
+//! storage.
+//!
+//! The handshake proves both sides know the 16 byte two-key 3DES key: the
+//! tag sends ek(RndB), the reader answers ek(RndA || RndB') and the tag
+//! ek(RndA'), where ' is a rotation left by one byte.  Everything is
+//! enciphered in CBC mode, each message chained on the last block of the
+//! previous one.
+
+use des::cipher::generic_array::GenericArray;
+use des::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use des::TdesEde2;
+
+use crate::pn532::{Error, Page, Result};
+
+pub(crate) const CMD_AUTHENTICATE: u8 = 0x1A;
+/// First byte of the tag's challenge and of the reader's answer: more
+/// frames follow.
+const ADDITIONAL_FRAME: u8 = 0xAF;
+
+/// Key of a tag fresh from the factory.
+pub const DEFAULT_KEY: [u8; 16] = *b"BREAKMEIFYOUCAN!";
+/// First of the 4 pages holding the key; they cannot be read back.
+pub const KEY_PAGE: Page = Page(0x2C);
+/// AUTH0: first page that needs authentication.
+pub const AUTH0_PAGE: Page = Page(0x2A);
+/// AUTH1: whether unauthenticated reads are allowed past AUTH0 (bit 0
+/// clear) or only writes are protected (bit 0 set).
+pub const AUTH1_PAGE: Page = Page(0x2B);
+
+/// The reader's side of an authentication in progress.
+pub(crate) struct Handshake {
+    cipher: TdesEde2,
+    rnd_a: [u8; 8],
+    iv: [u8; 8],
+}
+
+impl Handshake {
+    pub(crate) fn new(key: &[u8; 16]) -> Result<Self> {
+        let mut rnd_a = [0; 8];
+        getrandom::getrandom(&mut rnd_a).map_err(|e| Error::Io(e.into()))?;
+        Ok(Self {
+            cipher: TdesEde2::new(GenericArray::from_slice(key)),
+            rnd_a,
+            iv: [0; 8],
+        })
+    }
+
+    /// Answer the tag's challenge, 0xAF followed by ek(RndB).
+    pub(crate) fn answer(&mut self, challenge: &[u8]) -> Result<Vec<u8>> {
+        let [ADDITIONAL_FRAME, ref ek_rnd_b @ ..] = challenge[..] else {
+            return Err(Error::CardAuthentication);
+        };
+        let [mut rnd_b] = self.decrypt::<1>(ek_rnd_b)?;
+        rnd_b.rotate_left(1);
+
+        let mut answer = vec![ADDITIONAL_FRAME];
+        for block in [self.rnd_a, rnd_b] {
+            answer.extend_from_slice(&self.encrypt(block));
+        }
+        Ok(answer)
+    }
+
+    /// Check the tag's final answer, 0x00 followed by ek(RndA').
+    pub(crate) fn verify(&mut self, response: &[u8]) -> Result<()> {
+        let [0x00, ref ek_rnd_a @ ..] = response[..] else {
+            return Err(Error::CardAuthentication);
+        };
+        let [rnd_a] = self.decrypt::<1>(ek_rnd_a)?;
+        let mut expected = self.rnd_a;
+        expected.rotate_left(1);
+        if rnd_a != expected {
+            return Err(Error::CardAuthentication);
+        }
+        Ok(())
+    }
+
+    /// Encipher one block, chained on the previous one.
+    fn encrypt(&mut self, mut block: [u8; 8]) -> [u8; 8] {
+        block.iter_mut().zip(self.iv).for_each(|(b, iv)| *b ^= iv);
+        self.cipher.encrypt_block(GenericArray::from_mut_slice(&mut block));
+        self.iv = block;
+        block
+    }
+
+    /// Decipher exactly `N` blocks, chained on the previous one.
+    fn decrypt<const N: usize>(&mut self, data: &[u8]) -> Result<[[u8; 8]; N]> {
+        if data.len() != 8 * N {
+            return Err(Error::CardAuthentication);
+        }
+        let mut blocks = [[0; 8]; N];
+        for (block, cipher) in blocks.iter_mut().zip(data.chunks_exact(8)) {
+            block.copy_from_slice(cipher);
+            self.cipher.decrypt_block(GenericArray::from_mut_slice(block));
+            block.iter_mut().zip(self.iv).for_each(|(b, iv)| *b ^= iv);
+            self.iv.copy_from_slice(cipher);
+        }
+        Ok(blocks)
+    }
+}
+
+/// The 4 pages storing `key`, from [`KEY_PAGE`] on: each half of the key
+/// is stored with its bytes reversed.
+pub(crate) fn key_pages(key: &[u8; 16]) -> [[u8; 4]; 4] {
+    let mut stored = *key;
+    stored[..8].reverse();
+    stored[8..].reverse();
+    let mut pages = [[0; 4]; 4];
+    for (page, bytes) in pages.iter_mut().zip(stored.chunks_exact(4)) {
+        page.copy_from_slice(bytes);
+    }
+    pages
+}
@@ -0,0 +1,25 @@
+/// Recovery of a PN532 that stopped answering altogether.
+///
+/// After `max_ack_timeouts` commands in a row got no ACK, the reader pulses
+/// the reset pin (when one is wired), wakes the PN532 up and sends the
+/// SAMConfiguration of its [`Options`](crate::pn532::Options) again.  The
+/// command that tripped the watchdog fails with
+/// [`Error::ChipReset`](crate::pn532::Error::ChipReset) and
+/// [`Hook::on_chip_reset`](crate::pn532::Hook::on_chip_reset) is called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchdog {
+    /// Number of consecutive ACK timeouts that trigger a reset.
+    pub max_ack_timeouts: u32,
+}
+
+impl Watchdog {
+    pub fn new(max_ack_timeouts: u32) -> Self {
+        Self { max_ack_timeouts }
+    }
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}